@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use bevy::prelude::Color;
+
+use crate::kinematics::KinematicTree;
+use crate::mrr::Assembly;
+
+/// How a part looks, from the appearance set on it in CAD.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Appearance {
+    /// sRGB from 0 to 1
+    pub color: [f32; 3],
+    pub metallic: f32,
+    pub roughness: f32,
+    /// 1 for opaque, 0 for invisible
+    pub opacity: f32,
+}
+
+impl Appearance {
+    pub const fn plastic(color: [f32; 3]) -> Self {
+        Self { color, metallic: 0., roughness: 0.6, opacity: 1. }
+    }
+
+    pub const fn metal(color: [f32; 3], roughness: f32) -> Self {
+        Self { color, metallic: 1., roughness, opacity: 1. }
+    }
+
+    pub fn base_color(&self) -> Color {
+        Color::rgba(self.color[0], self.color[1], self.color[2], self.opacity)
+    }
+}
+
+/// Colors parts are given in turn when nothing else decides their color.
+pub const PALETTE: [[f32; 3]; 8] = [
+    [0.85, 0.33, 0.10],
+    [0.00, 0.45, 0.74],
+    [0.93, 0.69, 0.13],
+    [0.47, 0.67, 0.19],
+    [0.49, 0.18, 0.56],
+    [0.30, 0.75, 0.93],
+    [0.64, 0.08, 0.18],
+    [0.60, 0.60, 0.60],
+];
+
+/// Parts without a color of their own, such as ones without mass properties when coloring by mass.
+const UNKNOWN: Appearance = Appearance::plastic([0.5, 0.5, 0.5]);
+
+/// Appearances of common robot materials, by words found in CAD material names such as "Aluminum - 6061".
+const MATERIAL_APPEARANCES: [(&[&str], Appearance); 11] = [
+    (&["aluminum", "aluminium"], Appearance::metal([0.77, 0.78, 0.80], 0.35)),
+    (&["steel", "stainless", "iron"], Appearance::metal([0.56, 0.57, 0.58], 0.3)),
+    (&["brass", "bronze"], Appearance::metal([0.78, 0.62, 0.33], 0.3)),
+    (&["copper"], Appearance::metal([0.72, 0.45, 0.20], 0.3)),
+    (&["titanium"], Appearance::metal([0.62, 0.60, 0.58], 0.4)),
+    (&["polycarbonate", "lexan", "acrylic"], Appearance { color: [0.85, 0.90, 0.95], metallic: 0., roughness: 0.05, opacity: 0.35 }),
+    (&["carbon"], Appearance { color: [0.12, 0.12, 0.13], metallic: 0., roughness: 0.3, opacity: 1. }),
+    (&["delrin", "acetal", "nylon", "hdpe", "uhmw", "polyethylene"], Appearance::plastic([0.92, 0.92, 0.88])),
+    (&["pla", "petg", "abs", "onyx", "plastic"], Appearance::plastic([0.25, 0.25, 0.28])),
+    (&["rubber", "tpu", "urethane", "polyurethane", "silicone", "neoprene"], Appearance { color: [0.10, 0.10, 0.10], metallic: 0., roughness: 0.9, opacity: 1. }),
+    (&["wood", "plywood", "birch", "mdf"], Appearance { color: [0.76, 0.60, 0.42], metallic: 0., roughness: 0.8, opacity: 1. }),
+];
+
+/// Returns the appearance of a known kind of material in a CAD material name, ignoring case.
+pub fn material_appearance(material: &str) -> Option<Appearance> {
+    let material = material.to_ascii_lowercase();
+    let words: Vec<&str> = material.split(|c: char| !c.is_ascii_alphanumeric()).collect();
+    MATERIAL_APPEARANCES.iter()
+        .find(|(keywords, _)| keywords.iter().any(|keyword| words.contains(keyword)))
+        .map(|(_, appearance)| *appearance)
+}
+
+/// What decides the color of each part in the viewer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorBy {
+    /// The part's appearance from CAD, or the appearance of its material
+    #[default]
+    Part,
+    /// One color for all the parts that move together
+    RigidGroup,
+    /// From blue for the lightest parts to red for the heaviest
+    Mass,
+    /// One color for each material
+    Material,
+}
+
+impl ColorBy {
+    pub const ALL: [ColorBy; 4] = [ColorBy::Part, ColorBy::RigidGroup, ColorBy::Mass, ColorBy::Material];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorBy::Part => "Part",
+            ColorBy::RigidGroup => "Rigid Group",
+            ColorBy::Mass => "Mass",
+            ColorBy::Material => "Material",
+        }
+    }
+}
+
+/// Maps `t` from 0 to 1 onto blue, yellow and red.
+fn heat_color(t: f32) -> [f32; 3] {
+    const STOPS: [[f32; 3]; 3] = [[0.20, 0.40, 1.00], [1.00, 0.85, 0.20], [0.90, 0.10, 0.10]];
+    let t = t.clamp(0., 1.) * 2.;
+    let (from, to) = if t < 1. { (STOPS[0], STOPS[1]) } else { (STOPS[1], STOPS[2]) };
+    let t = if t < 1. { t } else { t - 1. };
+    [0, 1, 2].map(|i| from[i] + (to[i] - from[i]) * t)
+}
+
+/// Returns the appearance of every part of `assembly`.
+pub fn part_appearances(assembly: &Assembly, color_by: ColorBy) -> Vec<Appearance> {
+    let parts = assembly.parts.iter();
+    match color_by {
+        ColorBy::Part => parts
+            .enumerate()
+            .map(|(index, part)| part.appearance
+                .or_else(|| part.material.as_deref().and_then(material_appearance))
+                .unwrap_or_else(|| Appearance::plastic(PALETTE[index % PALETTE.len()])))
+            .collect(),
+        ColorBy::RigidGroup => {
+            let tree = KinematicTree::new(assembly);
+            tree.part_links.iter()
+                .map(|link| Appearance::plastic(PALETTE[link % PALETTE.len()]))
+                .collect()
+        }
+        ColorBy::Mass => {
+            let heaviest = assembly.parts.iter()
+                .filter_map(|part| part.inertial.map(|inertial| inertial.mass))
+                .fold(0., f64::max);
+            parts
+                .map(|part| match part.inertial {
+                    Some(inertial) if heaviest > 0. => Appearance::plastic(heat_color((inertial.mass / heaviest) as f32)),
+                    _ => UNKNOWN,
+                })
+                .collect()
+        }
+        ColorBy::Material => {
+            // Materials that aren't known are told apart by giving each name its own color
+            let mut others: HashMap<&str, usize> = HashMap::new();
+            parts
+                .map(|part| match part.material.as_deref() {
+                    Some(material) => material_appearance(material).unwrap_or_else(|| {
+                        let count = others.len();
+                        let index = *others.entry(material).or_insert(count);
+                        Appearance::plastic(PALETTE[index % PALETTE.len()])
+                    }),
+                    None => UNKNOWN,
+                })
+                .collect()
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+
+use crate::mrr::Assembly;
+use crate::sim::{ActuatorStates, SimSet, SimSettings, SimStep, Telemetry};
+
+/// How far below its nominal voltage a flat battery's voltage with no load is.
+const DISCHARGED_VOLTAGE_DROP: f64 = 1.;
+
+/// The robot's battery, which every motor draws from.
+#[derive(Resource, Debug, Clone)]
+pub struct Battery {
+    /// Volts with no load when fully charged
+    pub nominal_voltage: f64,
+    /// Ohms, including the main breaker and wiring
+    pub internal_resistance: f64,
+    /// Amp hours
+    pub capacity: f64,
+    /// From 0 for flat to 1 for fully charged
+    pub state_of_charge: f64,
+    /// Motor controllers are disabled below this voltage, as the roboRIO does
+    pub brownout_voltage: f64,
+    /// Motor controllers are enabled again once the voltage is back above this
+    pub recovery_voltage: f64,
+    /// Volts at the terminals during the last step
+    pub voltage: f64,
+    /// Amps drawn by all the motor controllers during the last step
+    pub current: f64,
+    pub browned_out: bool,
+}
+
+impl Default for Battery {
+    fn default() -> Self {
+        Self {
+            nominal_voltage: 12.8,
+            internal_resistance: 0.02,
+            capacity: 18.,
+            state_of_charge: 1.,
+            brownout_voltage: 6.8,
+            recovery_voltage: 7.5,
+            voltage: 12.8,
+            current: 0.,
+            browned_out: false,
+        }
+    }
+}
+
+impl Battery {
+    /// Volts with no load at the current state of charge.
+    pub fn open_circuit_voltage(&self) -> f64 {
+        self.nominal_voltage - (1. - self.state_of_charge) * DISCHARGED_VOLTAGE_DROP
+    }
+
+    /// Volts available to the motor controllers, none while browned out.
+    pub fn output_voltage(&self) -> f64 {
+        if self.browned_out { 0. } else { self.voltage }
+    }
+
+    /// Draws `current` amps for `dt` seconds, updating the voltage and whether the robot is browned out.
+    pub fn draw(&mut self, current: f64, dt: f64) {
+        self.current = current;
+        self.state_of_charge = (self.state_of_charge - current * dt / 3600. / self.capacity).clamp(0., 1.);
+        self.voltage = (self.open_circuit_voltage() - current * self.internal_resistance).max(0.);
+
+        if self.voltage < self.brownout_voltage {
+            self.browned_out = true;
+        } else if self.voltage > self.recovery_voltage {
+            self.browned_out = false;
+        }
+    }
+}
+
+fn draw_current(assembly: Res<Assembly>, settings: Res<SimSettings>, actuator_states: Res<ActuatorStates>, mut battery: ResMut<Battery>) {
+    // Motor controllers pass on power rather than current, so they draw less than their motors at part throttle
+    let current = assembly.actuators.iter()
+        .zip(&actuator_states.0)
+        .filter(|(actuator, _)| actuator.motor.is_some())
+        .map(|(_, state)| if battery.voltage > 0. { state.current * state.voltage / battery.voltage } else { 0. })
+        .sum();
+    battery.draw(current, settings.timestep);
+}
+
+fn record_battery_telemetry(battery: Res<Battery>, mut telemetry: ResMut<Telemetry>) {
+    telemetry.record("battery.voltage", battery.voltage);
+    telemetry.record("battery.current", battery.current);
+    telemetry.record("battery.state_of_charge", battery.state_of_charge);
+    telemetry.record("battery.brownout", battery.browned_out as u8 as f64);
+}
+
+pub struct BatteryPlugin;
+
+impl Plugin for BatteryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Battery>()
+        .add_system(draw_current.in_schedule(SimStep).in_set(SimSet::Sensors))
+        .add_system(record_battery_telemetry.in_schedule(SimStep).in_set(SimSet::Telemetry));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn voltage_sags_under_load() {
+        let mut battery = Battery::default();
+        battery.draw(100., 0.02);
+        assert!((battery.voltage - (battery.open_circuit_voltage() - 2.)).abs() < 1e-9, "{battery:?}");
+        assert_eq!(battery.output_voltage(), battery.voltage);
+
+        battery.draw(0., 0.02);
+        assert_eq!(battery.voltage, battery.open_circuit_voltage());
+    }
+
+    #[test]
+    fn drawing_current_discharges() {
+        let mut battery = Battery::default();
+        // Half the capacity, in amps for an hour
+        battery.draw(battery.capacity / 2., 3600.);
+        assert!((battery.state_of_charge - 0.5).abs() < 1e-9, "{battery:?}");
+        assert!((battery.open_circuit_voltage() - (battery.nominal_voltage - DISCHARGED_VOLTAGE_DROP / 2.)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn brownouts_last_until_the_voltage_recovers() {
+        let mut battery = Battery::default();
+        // 310 A drops 6.2 V, below the brownout voltage
+        battery.draw(310., 0.);
+        assert!(battery.browned_out);
+        assert_eq!(battery.output_voltage(), 0.);
+
+        // Between the brownout and recovery voltages, it stays browned out
+        battery.draw(270., 0.);
+        assert!(battery.voltage > battery.brownout_voltage && battery.voltage < battery.recovery_voltage, "{battery:?}");
+        assert!(battery.browned_out);
+
+        battery.draw(200., 0.);
+        assert!(!battery.browned_out);
+        assert_eq!(battery.output_voltage(), battery.voltage);
+
+        // And between them on the way down, it stays enabled
+        battery.draw(270., 0.);
+        assert!(!battery.browned_out);
+    }
+}
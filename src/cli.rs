@@ -0,0 +1,285 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use bevy::prelude::App;
+use clap::{Args, Parser, Subcommand};
+
+use mechsim::gltf::{self, GlbOptions};
+use mechsim::halsim::{HalSim, HalSimMode, RobotMode};
+use mechsim::hardware::HardwareFilter;
+use mechsim::instances::MeshInstances;
+use mechsim::interference::{self, SweepOptions};
+use mechsim::mrr::{Assembly, JointType};
+use mechsim::networktables::{self as nt, NetworkTables};
+use mechsim::sim::Telemetry;
+
+#[derive(Parser, Debug)]
+#[command(name = "mechsim", version, about = "A 3D robot visualizer, physics simulator, and robot code verification tool.")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// Store assemblies, logs, caches and settings in this directory
+    #[arg(long, global = true, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// Robot to open in the viewer
+    #[cfg(feature = "gui")]
+    pub file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Open a robot in the viewer
+    #[cfg(feature = "gui")]
+    Open {
+        file: PathBuf,
+    },
+    /// Print a summary of a robot
+    Info {
+        file: PathBuf,
+    },
+    /// Check that a robot loads and is consistent, exiting with an error if it is not
+    Validate {
+        file: PathBuf,
+    },
+    /// Simulate a robot
+    Simulate(SimulateArgs),
+    /// Convert a robot to another format, picked by the extension of the output file (.urdf, .glb or .mrr)
+    Export {
+        file: PathBuf,
+        output: PathBuf,
+
+        /// Animate a .glb export with the joint positions recorded in this telemetry CSV
+        #[arg(long, value_name = "FILE")]
+        telemetry: Option<PathBuf>,
+    },
+    /// Move joints through their range of motion and report bodies that intersect, exiting with an error if any do
+    Interference {
+        file: PathBuf,
+
+        /// Joint to sweep, by name. Joints given together are swept on a grid of every combination of their
+        /// positions. Without any, each movable joint is swept on its own
+        #[arg(long = "joint", value_name = "NAME")]
+        joints: Vec<String>,
+
+        /// Positions checked along the range of each joint
+        #[arg(long, default_value_t = 10)]
+        steps: usize,
+
+        /// Also report bodies joined to each other that already intersect with every joint at zero, which
+        /// are usually touching where they are joined
+        #[arg(long)]
+        adjacent: bool,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct SimulateArgs {
+    /// Run as fast as possible without opening a window, writing telemetry to disk
+    #[arg(long, requires = "duration")]
+    pub headless: bool,
+
+    /// Stop after this many seconds of simulated time
+    #[arg(long, value_name = "SECONDS", value_parser = parse_duration)]
+    pub duration: Option<f64>,
+
+    /// Where to write telemetry in headless mode, as CSV. Defaults to the telemetry data directory
+    #[arg(long, value_name = "FILE")]
+    pub telemetry: Option<PathBuf>,
+
+    /// Drive the robot from robot code over WPILib's simulation WebSocket protocol, as a "server" for robot
+    /// programs using halsim_ws_client or as a "client" of one using halsim_ws_server. Headless runs wait for
+    /// the robot program to connect, then keep pace with it in real time
+    #[arg(long, value_name = "MODE", value_parser = parse_halsim_mode)]
+    pub halsim: Option<HalSimMode>,
+
+    /// Address to listen on as a server, or of the robot program as a client. Defaults to localhost:3300
+    #[arg(long, value_name = "ADDRESS", requires = "halsim")]
+    pub halsim_address: Option<String>,
+
+    /// Enable the robot program in teleop, autonomous or test mode. Without it the robot stays disabled
+    #[arg(long, value_name = "MODE", value_parser = parse_robot_mode, requires = "halsim")]
+    pub enable: Option<RobotMode>,
+
+    /// Host a NetworkTables 4 server for dashboards, and for robot programs driving actuators as clients.
+    /// Headless runs keep pace with the wall clock while clients are connected
+    #[arg(long)]
+    pub networktables: bool,
+
+    /// Port of the NetworkTables server
+    #[arg(long, value_name = "PORT", default_value_t = nt::DEFAULT_PORT, requires = "networktables")]
+    pub networktables_port: u16,
+
+    pub file: PathBuf,
+}
+
+impl SimulateArgs {
+    /// Starts the connections to robot code asked for.
+    pub fn connections(&self) -> Result<Connections> {
+        let mut connections = Connections::default();
+        if let Some(mode) = self.halsim {
+            let mut hal_sim = HalSim::default();
+            hal_sim.mode = mode;
+            if let Some(address) = &self.halsim_address {
+                hal_sim.address = address.clone();
+            }
+            hal_sim.enabled = self.enable.is_some();
+            hal_sim.robot_mode = self.enable.unwrap_or_default();
+            hal_sim.start()?;
+            connections.hal_sim = Some(hal_sim);
+        }
+        if self.networktables {
+            let mut network_tables = NetworkTables::default();
+            network_tables.port = self.networktables_port;
+            network_tables.start()?;
+            connections.network_tables = Some(network_tables);
+        }
+        Ok(connections)
+    }
+}
+
+/// Connections to robot code started from the command line, replacing the stopped ones the app starts with.
+#[derive(Default)]
+pub struct Connections {
+    pub hal_sim: Option<HalSim>,
+    pub network_tables: Option<NetworkTables>,
+}
+
+impl Connections {
+    pub fn insert_into(self, app: &mut App) {
+        if let Some(hal_sim) = self.hal_sim {
+            app.insert_resource(hal_sim);
+        }
+        if let Some(network_tables) = self.network_tables {
+            app.insert_resource(network_tables);
+        }
+    }
+}
+
+fn parse_duration(value: &str) -> Result<f64, String> {
+    let duration: f64 = value.parse().map_err(|_| format!("\"{value}\" isn't a number"))?;
+    if !duration.is_finite() || duration <= 0.0 {
+        return Err("must be a finite number of seconds greater than zero".to_owned());
+    }
+    Ok(duration)
+}
+
+fn parse_halsim_mode(value: &str) -> Result<HalSimMode, String> {
+    HalSimMode::from_name(value).ok_or_else(|| "expected server or client".to_owned())
+}
+
+fn parse_robot_mode(value: &str) -> Result<RobotMode, String> {
+    RobotMode::from_name(value).ok_or_else(|| "expected teleop, autonomous or test".to_owned())
+}
+
+pub fn info(path: &Path) -> Result<()> {
+    let mut assembly = Assembly::open(path)?;
+
+    let count_joints = |joint_type: JointType| assembly.joints.iter()
+        .filter(|joint| joint.joint_type == joint_type)
+        .count();
+
+    println!("{}", assembly.get_name());
+    println!("  File:      {}", path.display());
+    println!("  Parts:     {}", assembly.parts.len());
+    println!("  Bodies:    {} ({} distinct shapes)", assembly.body_count(), MeshInstances::new(&assembly).shapes.len());
+    println!("  Triangles: {}", assembly.triangle_count());
+    println!(
+        "  Joints:    {} ({} rigid, {} revolute, {} slider)",
+        assembly.joints.len(),
+        count_joints(JointType::Rigid),
+        count_joints(JointType::Revolute),
+        count_joints(JointType::Slider),
+    );
+    if !assembly.actuators.is_empty() {
+        let motors = assembly.actuators.iter().filter(|actuator| actuator.motor.is_some()).count();
+        println!("  Actuators: {} ({motors} with motors)", assembly.actuators.len());
+    }
+    if !assembly.couplings.is_empty() {
+        println!("  Couplings: {}", assembly.couplings.len());
+    }
+    let hardware = HardwareFilter::default().apply(&mut assembly);
+    if hardware > 0 {
+        println!("  Hardware:  {hardware} part(s)");
+    }
+    for warning in &assembly.warnings {
+        println!("  Warning: {warning}");
+    }
+
+    Ok(())
+}
+
+pub fn validate(path: &Path) -> Result<()> {
+    let assembly = Assembly::open(path)?;
+    let errors = assembly.validate();
+    for warning in &assembly.warnings {
+        eprintln!("Warning: {warning}");
+    }
+
+    if errors.is_empty() {
+        println!("{} is valid", path.display());
+        return Ok(());
+    }
+
+    for error in &errors {
+        eprintln!("{error}");
+    }
+    bail!("{} has {} problem(s)", path.display(), errors.len())
+}
+
+pub fn export(path: &Path, output: &Path, telemetry: Option<&Path>) -> Result<()> {
+    let assembly = Assembly::open(path)?;
+    let warnings = match telemetry {
+        Some(telemetry) => {
+            if output.extension().and_then(OsStr::to_str) != Some("glb") {
+                bail!("Only .glb exports can be animated with telemetry")
+            }
+            let telemetry = Telemetry::read_csv(telemetry)?;
+            gltf::save(&assembly, output, &GlbOptions { telemetry: Some(&telemetry), ..Default::default() })?
+        }
+        None => assembly.export(output)?,
+    };
+
+    for warning in warnings {
+        eprintln!("Warning: {warning}");
+    }
+
+    println!("Exported {} to {}", assembly.get_name(), output.display());
+    Ok(())
+}
+
+pub fn interference(path: &Path, joint_names: &[String], steps: usize, adjacent: bool) -> Result<()> {
+    let assembly = Assembly::open(path)?;
+    let options = SweepOptions { steps, include_adjacent: adjacent };
+
+    let sweeps: Vec<Vec<usize>> = if joint_names.is_empty() {
+        (0..assembly.joints.len())
+            .filter(|&joint| interference::sweep_range(&assembly, joint).is_ok())
+            .map(|joint| vec![joint])
+            .collect()
+    } else {
+        let joints = joint_names.iter()
+            .map(|name| assembly.joints.iter()
+                .position(|joint| &joint.name == name)
+                .with_context(|| format!("{} has no joint named \"{name}\"", path.display())))
+            .collect::<Result<Vec<_>>>()?;
+        vec![joints]
+    };
+
+    let mut count = 0;
+    for joints in &sweeps {
+        for found in interference::sweep(&assembly, joints, &[], &options)? {
+            println!("{}", found.describe(&assembly, joints));
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        println!("No interference found in {}", path.display());
+        return Ok(());
+    }
+    bail!("Found {count} interference(s) in {}", path.display())
+}
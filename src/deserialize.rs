@@ -0,0 +1,227 @@
+use std::path::Path;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DeserializeError {
+    #[error("Unexpected end of data: needed {needed} bytes at offset {offset}, but only {available} remain")]
+    UnexpectedEof { offset: usize, needed: usize, available: usize },
+    #[error("Invalid UTF-8 in string at offset {0}")]
+    InvalidUtf8(usize),
+    #[error("Invalid {type_name} value {value} at offset {offset}")]
+    InvalidValue { type_name: &'static str, value: u64, offset: usize },
+}
+
+/// A type that can be read from little-endian binary data.
+pub trait Deserialize
+where
+    Self: Sized
+{
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, DeserializeError>;
+}
+
+/// Reads values one after another from a buffer of little-endian binary data.
+///
+/// Vectors and strings are prefixed with their length as a `u64`, matching how
+/// `std::vector` and `std::string` sizes are written by the exporters.
+pub struct Deserializer {
+    input: Vec<u8>,
+    offset: usize,
+}
+
+macro_rules! deserializer_fn_read_primitive {
+    ($name: ident, $num_type: ty) => {
+        pub fn $name(&mut self) -> Result<$num_type, DeserializeError> {
+            Ok(<$num_type>::from_le_bytes(self.get_byte_array()?))
+        }
+    };
+}
+
+impl Deserializer {
+    pub fn new(input: Vec<u8>) -> Self {
+        Self {
+            input,
+            offset: 0,
+        }
+    }
+
+    pub fn from_file(path: &Path) -> std::io::Result<Self> {
+        Ok(Self::new(std::fs::read(path)?))
+    }
+
+    /// Position of the next byte to be read.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The bytes that haven't been read yet.
+    pub fn remaining(&self) -> &[u8] {
+        &self.input[self.offset..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset >= self.input.len()
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&[u8], DeserializeError> {
+        let available = self.input.len() - self.offset;
+        if len > available {
+            return Err(DeserializeError::UnexpectedEof { offset: self.offset, needed: len, available });
+        }
+
+        let bytes = &self.input[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    pub fn skip(&mut self, len: usize) -> Result<(), DeserializeError> {
+        self.read_bytes(len).map(|_| ())
+    }
+
+    fn get_byte_array<const SIZE: usize>(&mut self) -> Result<[u8; SIZE], DeserializeError> {
+        let mut bytes = [0; SIZE];
+        bytes.copy_from_slice(self.read_bytes(SIZE)?);
+        Ok(bytes)
+    }
+
+    deserializer_fn_read_primitive!(read_u8, u8);
+    deserializer_fn_read_primitive!(read_u16, u16);
+    deserializer_fn_read_primitive!(read_u32, u32);
+    deserializer_fn_read_primitive!(read_u64, u64);
+
+    deserializer_fn_read_primitive!(read_i8, i8);
+    deserializer_fn_read_primitive!(read_i16, i16);
+    deserializer_fn_read_primitive!(read_i32, i32);
+    deserializer_fn_read_primitive!(read_i64, i64);
+
+    deserializer_fn_read_primitive!(read_f32, f32);
+    deserializer_fn_read_primitive!(read_f64, f64);
+
+    /// Reads the `u64` length prefix of a vector or string.
+    pub fn read_len(&mut self) -> Result<usize, DeserializeError> {
+        let offset = self.offset;
+        let len = self.read_u64()?;
+        usize::try_from(len).map_err(|_| DeserializeError::InvalidValue { type_name: "length", value: len, offset })
+    }
+
+    pub fn read_vec<T: Deserialize>(&mut self) -> Result<Vec<T>, DeserializeError> {
+        let len = self.read_len()?;
+        // Every element takes at least one byte, so a corrupt length can't make us allocate
+        // more than the size of the input
+        let mut vec = Vec::with_capacity(len.min(self.remaining().len()));
+        for _ in 0..len {
+            vec.push(T::deserialize(self)?);
+        }
+        Ok(vec)
+    }
+
+    /// Reads values written by `Serializer::write_records`, ignoring any bytes left over in each record.
+    pub fn read_records<T: Deserialize>(&mut self) -> Result<Vec<T>, DeserializeError> {
+        let len = self.read_len()?;
+        let mut vec = Vec::with_capacity(len.min(self.remaining().len()));
+        for _ in 0..len {
+            let record_len = self.read_len()?;
+            vec.push(Deserializer::new(self.read_bytes(record_len)?.to_vec()).read()?);
+        }
+        Ok(vec)
+    }
+
+    pub fn read_string(&mut self) -> Result<String, DeserializeError> {
+        let len = self.read_len()?;
+        let offset = self.offset;
+        String::from_utf8(self.read_bytes(len)?.to_vec()).map_err(|_| DeserializeError::InvalidUtf8(offset))
+    }
+
+    pub fn read<T: Deserialize>(&mut self) -> Result<T, DeserializeError> {
+        T::deserialize(self)
+    }
+}
+
+macro_rules! impl_deserialize_primitive {
+    ($num_type: ty, $read_fn: ident) => {
+        impl Deserialize for $num_type {
+            fn deserialize(deserializer: &mut Deserializer) -> Result<Self, DeserializeError> {
+                deserializer.$read_fn()
+            }
+        }
+    };
+}
+
+impl_deserialize_primitive!(u8, read_u8);
+impl_deserialize_primitive!(u16, read_u16);
+impl_deserialize_primitive!(u32, read_u32);
+impl_deserialize_primitive!(u64, read_u64);
+
+impl_deserialize_primitive!(i8, read_i8);
+impl_deserialize_primitive!(i16, read_i16);
+impl_deserialize_primitive!(i32, read_i32);
+impl_deserialize_primitive!(i64, read_i64);
+
+impl_deserialize_primitive!(f32, read_f32);
+impl_deserialize_primitive!(f64, read_f64);
+
+impl<T: Deserialize> Deserialize for Vec<T> {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, DeserializeError> {
+        deserializer.read_vec()
+    }
+}
+
+impl Deserialize for String {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, DeserializeError> {
+        deserializer.read_string()
+    }
+}
+
+/// Implements [`Deserialize`] for a struct by reading each of its fields in order.
+///
+/// ```ignore
+/// impl_deserialize!(Body { triangle_count, verticies, indicies, normals, uvs });
+/// ```
+macro_rules! impl_deserialize {
+    ($type: ident { $($field: ident),* $(,)? }) => {
+        impl $crate::deserialize::Deserialize for $type {
+            fn deserialize(
+                deserializer: &mut $crate::deserialize::Deserializer
+            ) -> Result<Self, $crate::deserialize::DeserializeError> {
+                Ok(Self {
+                    $($field: deserializer.read()?,)*
+                })
+            }
+        }
+    };
+}
+
+pub(crate) use impl_deserialize;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialize::Serializer;
+
+    #[test]
+    fn reads_what_serializer_writes() {
+        let mut serializer = Serializer::new();
+        serializer.write_u8(7);
+        serializer.write_i32(-3);
+        serializer.write_f64(1.5);
+        serializer.write_string("Arm");
+        serializer.write(&vec![1u32, 2, 3]);
+
+        let mut deserializer = Deserializer::new(serializer.into_bytes());
+        assert_eq!(deserializer.read_u8().unwrap(), 7);
+        assert_eq!(deserializer.read_i32().unwrap(), -3);
+        assert_eq!(deserializer.read_f64().unwrap(), 1.5);
+        assert_eq!(deserializer.read_string().unwrap(), "Arm");
+        assert_eq!(deserializer.read::<Vec<u32>>().unwrap(), vec![1, 2, 3]);
+        assert!(deserializer.is_empty());
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let mut deserializer = Deserializer::new(vec![1, 0, 0]);
+        assert!(matches!(
+            deserializer.read_u32(),
+            Err(DeserializeError::UnexpectedEof { offset: 0, needed: 4, available: 3 }),
+        ));
+    }
+}
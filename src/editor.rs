@@ -0,0 +1,499 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::math::{DQuat, DVec3, Ray};
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy::window::PrimaryWindow;
+use bevy_egui::EguiContexts;
+
+use crate::mrr::{Assembly, JointType, Pose, DISPLAY_SCALE, METERS_PER_UNIT};
+use crate::sim::JointStates;
+use crate::viewer::{display_transform, CameraController, PartEntity, RobotPose, ViewerSettings, OVERLAY_LAYER};
+
+/// Length of the translation arrows, in viewer units.
+const ARROW_LENGTH: f32 = 1.;
+/// Radius of the rotation rings, in viewer units.
+const RING_RADIUS: f32 = 0.8;
+/// How close the cursor has to be to a handle to grab it, in viewer units.
+const HANDLE_TOLERANCE: f32 = 0.08;
+/// Radius around a joint that selects it when clicked, in viewer units.
+const JOINT_PICK_RADIUS: f32 = 0.15;
+
+const AXES: [(Vec3, Color); 3] = [
+    (Vec3::X, Color::RED),
+    (Vec3::Y, Color::GREEN),
+    (Vec3::Z, Color::BLUE),
+];
+
+/// What clicking and dragging in the viewport does.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EditorMode {
+    /// Only move the camera
+    #[default]
+    View,
+    /// Drag joints to pose the robot
+    Pose,
+    /// Drag joints to move them within the assembly
+    Edit,
+}
+
+/// State of the assembly editor, which poses the robot and moves and rotates joints with handles in the viewport.
+#[derive(Resource, Default)]
+pub struct AssemblyEditor {
+    pub mode: EditorMode,
+    pub selected_joint: Option<usize>,
+    pub selected_part: Option<usize>,
+    drag: Option<Drag>,
+}
+
+impl AssemblyEditor {
+    /// Returns the frame of the selected joint in the posed robot, including a drag in progress.
+    pub fn selected_frame(&self, robot_pose: &RobotPose) -> Option<Pose> {
+        let frame = *robot_pose.joint_frames.get(self.selected_joint?)?;
+        match &self.drag {
+            Some(Drag { target: DragTarget::Joint { frame, .. }, .. }) => Some(*frame),
+            _ => Some(frame),
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+enum GizmoHandle {
+    Translate(Vec3),
+    Rotate(Vec3),
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum EditorGizmo {
+    /// Arrows and rings along the world axes, to move the selected joint in [`EditorMode::Edit`]
+    Edit,
+    /// Ring or arrow along the selected joint's axis, to pose the robot in [`EditorMode::Pose`]
+    Pose,
+    /// Line along the selected joint's axis
+    Axis,
+}
+
+/// Part of the pose handle that is shown for joints of this type.
+#[derive(Component)]
+struct PoseHandleShape(JointType);
+
+enum DragTarget {
+    /// Moving the joint within the assembly. Written to the assembly once released, as
+    /// `parent.inverse().then(frame)`, where `parent` is the pose of the parts the joint moves with.
+    Joint { parent: Pose, start: Pose, frame: Pose },
+    /// Moving the robot about the joint, from the joint position at the start of the drag
+    Position { start: f64 },
+}
+
+struct Drag {
+    joint: usize,
+    handle: GizmoHandle,
+    center: Vec3,
+    /// Where the cursor grabbed the handle
+    grab_point: Vec3,
+    target: DragTarget,
+}
+
+fn spawn_gizmo(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let arrow = meshes.add(shape::Cylinder { radius: 0.02, height: ARROW_LENGTH, ..default() }.into());
+    let tip = meshes.add(shape::Cube { size: 0.08 }.into());
+    let ring = meshes.add(shape::Torus {
+        radius: RING_RADIUS,
+        ring_radius: 0.012,
+        subdivisions_segments: 48,
+        subdivisions_sides: 8,
+    }.into());
+
+    commands.spawn((SpatialBundle { visibility: Visibility::Hidden, ..default() }, EditorGizmo::Edit))
+    .with_children(|parent| {
+        for (axis, color) in AXES {
+            let material = materials.add(StandardMaterial { base_color: color, unlit: true, ..default() });
+            // The shapes are built around Y
+            let rotation = Quat::from_rotation_arc(Vec3::Y, axis);
+
+            parent.spawn((PbrBundle {
+                mesh: arrow.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(axis * ARROW_LENGTH / 2.).with_rotation(rotation),
+                ..default()
+            }, GizmoHandle::Translate(axis), RenderLayers::layer(OVERLAY_LAYER)));
+            parent.spawn((PbrBundle {
+                mesh: tip.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(axis * ARROW_LENGTH).with_rotation(rotation),
+                ..default()
+            }, RenderLayers::layer(OVERLAY_LAYER)));
+            parent.spawn((PbrBundle {
+                mesh: ring.clone(),
+                material,
+                transform: Transform::from_rotation(rotation),
+                ..default()
+            }, GizmoHandle::Rotate(axis), RenderLayers::layer(OVERLAY_LAYER)));
+        }
+    });
+
+    // In the joint's frame, where Z is the axis it moves along
+    let pose_material = materials.add(StandardMaterial { base_color: Color::FUCHSIA, unlit: true, ..default() });
+    let along_z = Quat::from_rotation_x(FRAC_PI_2);
+    commands.spawn((SpatialBundle { visibility: Visibility::Hidden, ..default() }, EditorGizmo::Pose))
+    .with_children(|parent| {
+        parent.spawn((PbrBundle {
+            mesh: ring,
+            material: pose_material.clone(),
+            transform: Transform::from_rotation(along_z),
+            ..default()
+        }, PoseHandleShape(JointType::Revolute), RenderLayers::layer(OVERLAY_LAYER)));
+        parent.spawn((PbrBundle {
+            mesh: arrow,
+            material: pose_material.clone(),
+            transform: Transform::from_translation(Vec3::Z * ARROW_LENGTH / 2.).with_rotation(along_z),
+            ..default()
+        }, PoseHandleShape(JointType::Slider), RenderLayers::layer(OVERLAY_LAYER)));
+        parent.spawn((PbrBundle {
+            mesh: tip,
+            material: pose_material,
+            transform: Transform::from_translation(Vec3::Z * ARROW_LENGTH),
+            ..default()
+        }, PoseHandleShape(JointType::Slider), RenderLayers::layer(OVERLAY_LAYER)));
+    });
+
+    commands.spawn((PbrBundle {
+        mesh: meshes.add(shape::Cylinder { radius: 0.01, height: 2. * ARROW_LENGTH, ..default() }.into()),
+        material: materials.add(StandardMaterial { base_color: Color::YELLOW, unlit: true, ..default() }),
+        visibility: Visibility::Hidden,
+        ..default()
+    }, EditorGizmo::Axis, RenderLayers::layer(OVERLAY_LAYER)));
+}
+
+fn cursor_ray(
+    windows: &Query<&Window, With<PrimaryWindow>>,
+    cameras: &Query<(&Camera, &GlobalTransform), With<CameraController>>,
+) -> Option<Ray> {
+    let cursor = windows.get_single().ok()?.cursor_position()?;
+    let (camera, transform) = cameras.get_single().ok()?;
+    camera.viewport_to_world(transform, cursor)
+}
+
+/// Returns the parameter along `ray` and along the line through `point` in `direction` where they come closest.
+fn closest_params(ray: &Ray, point: Vec3, direction: Vec3) -> Option<(f32, f32)> {
+    let w = point - ray.origin;
+    let b = direction.dot(ray.direction);
+    let denominator = 1. - b * b;
+    if denominator.abs() < 1e-6 {
+        return None;
+    }
+
+    let line = (b * ray.direction.dot(w) - direction.dot(w)) / denominator;
+    let along_ray = ray.direction.dot(w) + line * b;
+    Some((along_ray, line))
+}
+
+fn intersect_plane(ray: &Ray, point: Vec3, normal: Vec3) -> Option<Vec3> {
+    let distance = ray.intersect_plane(point, normal)?;
+    Some(ray.get_point(distance))
+}
+
+/// Returns the distance along `ray` to the handle if it is under the cursor.
+fn hit_handle(ray: &Ray, center: Vec3, handle: GizmoHandle) -> Option<f32> {
+    match handle {
+        GizmoHandle::Translate(axis) => {
+            let (along_ray, along_axis) = closest_params(ray, center, axis)?;
+            let distance = ray.get_point(along_ray).distance(center + axis * along_axis);
+            (along_ray > 0. && (0. ..=ARROW_LENGTH).contains(&along_axis) && distance < HANDLE_TOLERANCE).then_some(along_ray)
+        }
+        GizmoHandle::Rotate(axis) => {
+            let point = intersect_plane(ray, center, axis)?;
+            ((point.distance(center) - RING_RADIUS).abs() < HANDLE_TOLERANCE).then(|| point.distance(ray.origin))
+        }
+    }
+}
+
+/// Point on the handle's axis or plane that the cursor is over.
+fn grab_point(ray: &Ray, center: Vec3, handle: GizmoHandle) -> Option<Vec3> {
+    match handle {
+        GizmoHandle::Translate(axis) => {
+            let (_, along_axis) = closest_params(ray, center, axis)?;
+            Some(center + axis * along_axis)
+        }
+        GizmoHandle::Rotate(axis) => intersect_plane(ray, center, axis),
+    }
+}
+
+/// How far the handle has been dragged from `from` to `to`: the distance along the axis for
+/// arrows, or the angle around it for rings.
+fn handle_motion(handle: GizmoHandle, center: Vec3, from: Vec3, to: Vec3) -> f32 {
+    match handle {
+        GizmoHandle::Translate(axis) => axis.dot(to - from),
+        GizmoHandle::Rotate(axis) => {
+            let (from, to) = (from - center, to - center);
+            axis.dot(from.cross(to)).atan2(from.dot(to))
+        }
+    }
+}
+
+/// Returns the handle that poses the robot about a joint with the frame `frame`.
+fn pose_handle(joint_type: JointType, frame: &Pose) -> Option<GizmoHandle> {
+    let axis = (frame.1 * DVec3::Z).as_vec3();
+    match joint_type {
+        JointType::Rigid => None,
+        JointType::Revolute => Some(GizmoHandle::Rotate(axis)),
+        JointType::Slider => Some(GizmoHandle::Translate(axis)),
+    }
+}
+
+/// Möller–Trumbore ray triangle intersection, returning the distance along the ray.
+fn intersect_triangle(ray: &Ray, [a, b, c]: [Vec3; 3]) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = ray.direction.cross(edge2);
+    let determinant = edge1.dot(p);
+    if determinant.abs() < 1e-9 {
+        return None;
+    }
+
+    let inverse = 1. / determinant;
+    let t = ray.origin - a;
+    let u = t.dot(p) * inverse;
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
+    let q = t.cross(edge1);
+    let v = ray.direction.dot(q) * inverse;
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+
+    let distance = edge2.dot(q) * inverse;
+    (distance > 0.).then_some(distance)
+}
+
+fn pick_part(ray: &Ray, assembly: &Assembly, robot_pose: &RobotPose, settings: &ViewerSettings) -> Option<usize> {
+    let mut nearest: Option<(usize, f32)> = None;
+    for (index, part) in assembly.parts.iter().enumerate().filter(|(_, part)| settings.part_visible(part)) {
+        let transform = robot_pose.part_poses.get(index).map_or(Transform::IDENTITY, display_transform);
+        for body in &part.bodies {
+            for triangle in body.indicies.chunks_exact(3) {
+                let vertices = [0, 1, 2].map(|i| transform.transform_point(body.vertex(triangle[i] as usize) * DISPLAY_SCALE));
+                if let Some(distance) = intersect_triangle(ray, vertices) {
+                    if nearest.is_none_or(|(_, nearest)| distance < nearest) {
+                        nearest = Some((index, distance));
+                    }
+                }
+            }
+        }
+    }
+    nearest.map(|(index, _)| index)
+}
+
+fn pick_joint(ray: &Ray, robot_pose: &RobotPose) -> Option<usize> {
+    robot_pose.joint_frames.iter()
+        .enumerate()
+        .filter_map(|(index, frame)| {
+            let center = frame.0.as_vec3() * DISPLAY_SCALE;
+            let along_ray = (center - ray.origin).dot(ray.direction);
+            (along_ray > 0. && ray.get_point(along_ray).distance(center) < JOINT_PICK_RADIUS).then_some((index, along_ray))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+/// Starts dragging a handle of the selected joint if the cursor is over one.
+fn grab_handle(
+    ray: &Ray,
+    editor: &AssemblyEditor,
+    assembly: &Assembly,
+    robot_pose: &RobotPose,
+    joint_states: &JointStates,
+    edit_handles: &Query<&GizmoHandle>,
+) -> Option<Drag> {
+    let joint = editor.selected_joint.filter(|&joint| joint < assembly.joints.len())?;
+    let frame = *robot_pose.joint_frames.get(joint)?;
+    let center = frame.0.as_vec3() * DISPLAY_SCALE;
+
+    let (handle, target) = match editor.mode {
+        EditorMode::View => return None,
+        EditorMode::Pose => {
+            let handle = pose_handle(assembly.joints[joint].joint_type, &frame)?;
+            hit_handle(ray, center, handle)?;
+            let start = joint_states.0.get(joint).map_or(0., |state| state.position);
+            (handle, DragTarget::Position { start })
+        }
+        EditorMode::Edit => {
+            let (handle, _) = edit_handles.iter()
+                .filter_map(|&handle| Some((handle, hit_handle(ray, center, handle)?)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))?;
+            let parent = frame.then(&assembly.joints[joint].pose.inverse());
+            (handle, DragTarget::Joint { parent, start: frame, frame })
+        }
+    };
+
+    Some(Drag {
+        joint,
+        handle,
+        center,
+        grab_point: grab_point(ray, center, handle)?,
+        target,
+    })
+}
+
+fn pick_and_drag(
+    mut editor: ResMut<AssemblyEditor>,
+    mut assembly: ResMut<Assembly>,
+    mut joint_states: ResMut<JointStates>,
+    robot_pose: Res<RobotPose>,
+    viewer_settings: Res<ViewerSettings>,
+    mut contexts: EguiContexts,
+    mouse: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<CameraController>>,
+    edit_handles: Query<&GizmoHandle>,
+    mut camera_controllers: Query<&mut CameraController>,
+) {
+    if editor.mode == EditorMode::View {
+        if editor.drag.is_some() {
+            editor.drag = None;
+        }
+        return;
+    }
+
+    let ctx = contexts.ctx_mut();
+    let over_ui = ctx.is_pointer_over_area() || ctx.wants_pointer_input();
+    let Some(ray) = cursor_ray(&windows, &cameras) else {
+        return;
+    };
+
+    if mouse.just_pressed(MouseButton::Left) && !over_ui {
+        let grabbed = grab_handle(&ray, &editor, &assembly, &robot_pose, &joint_states, &edit_handles);
+        if grabbed.is_some() {
+            editor.drag = grabbed;
+        } else if let Some(joint) = pick_joint(&ray, &robot_pose) {
+            editor.selected_joint = Some(joint);
+        } else {
+            editor.selected_part = pick_part(&ray, &assembly, &robot_pose, &viewer_settings);
+        }
+    }
+
+    // Only mark the editor changed when the drag does, rather than every frame it is held
+    let released = !mouse.pressed(MouseButton::Left);
+    let mut finished = released;
+    let mut moved = false;
+    if let Some(drag) = &mut editor.bypass_change_detection().drag {
+        let motion = grab_point(&ray, drag.center, drag.handle)
+            .map(|point| handle_motion(drag.handle, drag.center, drag.grab_point, point));
+
+        match &mut drag.target {
+            DragTarget::Joint { parent, start, frame } => {
+                if let Some(motion) = motion {
+                    *frame = match drag.handle {
+                        GizmoHandle::Translate(axis) => Pose(start.0 + (axis * motion / DISPLAY_SCALE).as_dvec3(), start.1),
+                        GizmoHandle::Rotate(axis) => Pose(start.0, DQuat::from_axis_angle(axis.as_dvec3(), motion as f64) * start.1),
+                    };
+                    moved = true;
+                }
+                if released {
+                    if let Some(joint) = assembly.joints.get_mut(drag.joint) {
+                        joint.pose = parent.inverse().then(frame);
+                    }
+                }
+            }
+            DragTarget::Position { start } => match assembly.joints.get(drag.joint) {
+                Some(joint) => {
+                    if let (Some(motion), Some(state)) = (motion, joint_states.0.get_mut(drag.joint)) {
+                        let position = match joint.joint_type {
+                            JointType::Slider => *start + (motion / DISPLAY_SCALE) as f64 * METERS_PER_UNIT,
+                            _ => *start + motion as f64,
+                        };
+                        state.position = match joint.limits {
+                            Some((lower, upper)) => position.clamp(lower, upper),
+                            None => position,
+                        };
+                        state.velocity = 0.;
+                    }
+                }
+                // The joint was removed while it was being dragged
+                None => finished = true,
+            },
+        }
+    }
+
+    if editor.drag.is_some() {
+        if finished {
+            editor.drag = None;
+        } else if moved {
+            editor.set_changed();
+        }
+    }
+
+    for mut controller in &mut camera_controllers {
+        let enabled = editor.drag.is_none();
+        if controller.enabled != enabled {
+            controller.enabled = enabled;
+        }
+    }
+}
+
+fn update_gizmo(
+    editor: Res<AssemblyEditor>,
+    assembly: Res<Assembly>,
+    robot_pose: Res<RobotPose>,
+    mut gizmos: Query<(&EditorGizmo, &mut Transform, &mut Visibility)>,
+    mut pose_shapes: Query<(&PoseHandleShape, &mut Visibility), Without<EditorGizmo>>,
+) {
+    let frame = editor.selected_frame(&robot_pose);
+    let joint_type = editor.selected_joint
+        .and_then(|joint| assembly.joints.get(joint))
+        .map(|joint| joint.joint_type);
+
+    for (gizmo, mut transform, mut visibility) in &mut gizmos {
+        let shown = frame.is_some() && match gizmo {
+            EditorGizmo::Edit => editor.mode == EditorMode::Edit,
+            EditorGizmo::Pose => editor.mode == EditorMode::Pose && joint_type != Some(JointType::Rigid),
+            EditorGizmo::Axis => editor.mode != EditorMode::View,
+        };
+        *visibility = if shown { Visibility::Inherited } else { Visibility::Hidden };
+
+        let frame = frame.unwrap_or(Pose::IDENTITY);
+        *transform = match gizmo {
+            EditorGizmo::Edit => Transform::from_translation(frame.0.as_vec3() * DISPLAY_SCALE),
+            EditorGizmo::Pose => display_transform(&frame),
+            // The cylinder is built around Y, and joints move along their Z axis
+            EditorGizmo::Axis => display_transform(&frame) * Transform::from_rotation(Quat::from_rotation_x(FRAC_PI_2)),
+        };
+    }
+
+    for (shape, mut visibility) in &mut pose_shapes {
+        *visibility = if Some(shape.0) == joint_type { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+fn highlight_selected_part(
+    editor: Res<AssemblyEditor>,
+    parts: Query<(&PartEntity, &Handle<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let selected = editor.selected_part.filter(|_| editor.mode != EditorMode::View);
+    for (part, material) in &parts {
+        let emissive = if Some(part.0) == selected { Color::rgb(0.3, 0.3, 0.) } else { Color::BLACK };
+        if let Some(material) = materials.get_mut(material) {
+            if material.emissive != emissive {
+                material.emissive = emissive;
+            }
+        }
+    }
+}
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AssemblyEditor>()
+        .add_startup_system(spawn_gizmo)
+        .add_system(pick_and_drag)
+        .add_system(update_gizmo.after(pick_and_drag))
+        .add_system(highlight_selected_part.run_if(resource_changed::<AssemblyEditor>()));
+    }
+}
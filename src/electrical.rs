@@ -0,0 +1,648 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use bevy::prelude::*;
+use serde_json::{Value, json};
+
+use crate::log::LogMessages;
+use crate::motors::MotorModel;
+use crate::mrr::Assembly;
+
+/// Highest CAN ID a device can have.
+pub const MAX_CAN_ID: u8 = 62;
+/// Number of PWM outputs on the roboRIO, counting the MXP.
+pub const PWM_CHANNELS: u8 = 20;
+/// Number of digital inputs on the roboRIO, counting the MXP.
+pub const DIO_CHANNELS: u8 = 26;
+/// Number of analog inputs on the roboRIO, counting the MXP.
+pub const ANALOG_CHANNELS: u8 = 8;
+/// Volts an analog input reads at full scale.
+pub const ANALOG_VOLTAGE: f64 = 5.;
+
+/// Where a device is plugged in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Port {
+    /// CAN bus, with the device's ID
+    Can(u8),
+    /// roboRIO PWM output
+    Pwm(u8),
+    /// roboRIO digital input
+    Dio(u8),
+    /// Two roboRIO digital inputs, for the A and B channels of quadrature encoders
+    DioPair(u8, u8),
+    /// roboRIO analog input, for absolute encoders and potentiometers
+    Analog(u8),
+    /// The data port of a motor controller, by the controller's name
+    Controller(String),
+    /// roboRIO MXP expansion port, where IMUs such as the navX plug in
+    Mxp,
+}
+
+impl Port {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Port::Can(_) => "CAN",
+            Port::Pwm(_) => "PWM",
+            Port::Dio(_) => "DIO",
+            Port::DioPair(..) => "DIO Pair",
+            Port::Analog(_) => "Analog",
+            Port::Controller(_) => "Controller",
+            Port::Mxp => "MXP",
+        }
+    }
+
+    fn to_json(&self) -> (&'static str, Value) {
+        match self {
+            Port::Can(id) => ("can_id", json!(id)),
+            Port::Pwm(channel) => ("pwm", json!(channel)),
+            Port::Dio(channel) => ("dio", json!(channel)),
+            Port::DioPair(a, b) => ("dio", json!([a, b])),
+            Port::Analog(channel) => ("analog", json!(channel)),
+            Port::Controller(name) => ("controller", json!(name)),
+            Port::Mxp => ("mxp", json!(true)),
+        }
+    }
+
+    fn from_json(device: &Value) -> Result<Self> {
+        let channel = |value: &Value| value.as_u64()
+            .and_then(|channel| u8::try_from(channel).ok())
+            .with_context(|| format!("Invalid channel {value}"));
+
+        if let Some(id) = device.get("can_id") {
+            Ok(Port::Can(channel(id)?))
+        } else if let Some(channel_value) = device.get("pwm") {
+            Ok(Port::Pwm(channel(channel_value)?))
+        } else if let Some(dio) = device.get("dio") {
+            match dio.as_array().map(Vec::as_slice) {
+                Some([a, b]) => Ok(Port::DioPair(channel(a)?, channel(b)?)),
+                Some(_) => bail!("A DIO pair needs two channels"),
+                None => Ok(Port::Dio(channel(dio)?)),
+            }
+        } else if let Some(analog) = device.get("analog") {
+            Ok(Port::Analog(channel(analog)?))
+        } else if let Some(controller) = device.get("controller") {
+            Ok(Port::Controller(controller.as_str().context("Invalid controller name")?.to_owned()))
+        } else if device.get("mxp").and_then(Value::as_bool) == Some(true) {
+            Ok(Port::Mxp)
+        } else {
+            bail!("No port, expected can_id, pwm, dio, analog, controller or mxp")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControllerKind {
+    #[default]
+    SparkMax,
+    SparkFlex,
+    TalonFx,
+    TalonSrx,
+    VictorSpx,
+    VictorSp,
+    Spark,
+}
+
+impl ControllerKind {
+    pub const ALL: [ControllerKind; 7] = [
+        ControllerKind::SparkMax,
+        ControllerKind::SparkFlex,
+        ControllerKind::TalonFx,
+        ControllerKind::TalonSrx,
+        ControllerKind::VictorSpx,
+        ControllerKind::VictorSp,
+        ControllerKind::Spark,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ControllerKind::SparkMax => "Spark MAX",
+            ControllerKind::SparkFlex => "Spark Flex",
+            ControllerKind::TalonFx => "Talon FX",
+            ControllerKind::TalonSrx => "Talon SRX",
+            ControllerKind::VictorSpx => "Victor SPX",
+            ControllerKind::VictorSp => "Victor SP",
+            ControllerKind::Spark => "Spark",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// Whether the controller can run `motor`. Falcons and Krakens have a Talon FX built in, and brushless
+    /// REV motors need a Spark.
+    pub fn can_drive(&self, motor: &MotorModel) -> bool {
+        match motor.name {
+            "Falcon 500" | "Kraken X60" => *self == ControllerKind::TalonFx,
+            "NEO" | "NEO 550" | "NEO Vortex" => matches!(self, ControllerKind::SparkMax | ControllerKind::SparkFlex),
+            _ => *self != ControllerKind::TalonFx,
+        }
+    }
+
+    /// Whether the controller can be on the CAN bus. All of them can be driven over PWM.
+    pub fn has_can(&self) -> bool {
+        !matches!(self, ControllerKind::VictorSp | ControllerKind::Spark)
+    }
+}
+
+/// A motor controller, driving the motors of one of the assembly's actuators.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotorController {
+    pub name: String,
+    pub kind: ControllerKind,
+    pub port: Port,
+    /// Name of the driven actuator in [`Assembly::actuators`]
+    pub actuator: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SensorKind {
+    #[default]
+    Encoder,
+    LimitSwitch,
+    Imu,
+}
+
+impl SensorKind {
+    pub const ALL: [SensorKind; 3] = [SensorKind::Encoder, SensorKind::LimitSwitch, SensorKind::Imu];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SensorKind::Encoder => "Encoder",
+            SensorKind::LimitSwitch => "Limit Switch",
+            SensorKind::Imu => "IMU",
+        }
+    }
+
+    /// Name used in the map file.
+    fn key(&self) -> &'static str {
+        match self {
+            SensorKind::Encoder => "encoder",
+            SensorKind::LimitSwitch => "limit_switch",
+            SensorKind::Imu => "imu",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.key() == key)
+    }
+}
+
+/// How an encoder counts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncoderSettings {
+    /// Reports where it is within a turn rather than counting from where it started
+    pub absolute: bool,
+    /// Counts for each turn of a revolute joint, or each meter a slider travels
+    pub cpr: f64,
+    /// Standard deviation of the noise added to every reading, in counts
+    pub noise: f64,
+}
+
+impl Default for EncoderSettings {
+    fn default() -> Self {
+        Self { absolute: false, cpr: 2048., noise: 0. }
+    }
+}
+
+/// What presses a limit switch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitTrigger {
+    /// The joint is below this position
+    Below(f64),
+    /// The joint is above this position
+    Above(f64),
+    /// The part the switch is mounted on touches this part
+    Contact(String),
+}
+
+impl Default for LimitTrigger {
+    fn default() -> Self {
+        LimitTrigger::Below(0.)
+    }
+}
+
+impl LimitTrigger {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LimitTrigger::Below(_) => "Below",
+            LimitTrigger::Above(_) => "Above",
+            LimitTrigger::Contact(_) => "Touches",
+        }
+    }
+
+    fn to_json(&self) -> (&'static str, Value) {
+        match self {
+            LimitTrigger::Below(position) => ("below", json!(position)),
+            LimitTrigger::Above(position) => ("above", json!(position)),
+            LimitTrigger::Contact(part) => ("touches", json!(part)),
+        }
+    }
+
+    fn from_json(sensor: &Value) -> Result<Self> {
+        let position = |key: &str| sensor[key].as_f64().with_context(|| format!("{key} is not a number"));
+        if sensor.get("below").is_some() {
+            Ok(LimitTrigger::Below(position("below")?))
+        } else if sensor.get("above").is_some() {
+            Ok(LimitTrigger::Above(position("above")?))
+        } else if let Some(part) = sensor.get("touches") {
+            Ok(LimitTrigger::Contact(part.as_str().context("touches is not a part name")?.to_owned()))
+        } else {
+            bail!("Limit switch without below, above or touches")
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensor {
+    pub name: String,
+    pub kind: SensorKind,
+    pub port: Port,
+    /// Name of the joint encoders and limit switches measure, or of the part IMUs and contact
+    /// limit switches are mounted on
+    pub attached_to: Option<String>,
+    /// Only used by encoders
+    pub encoder: EncoderSettings,
+    /// Only used by limit switches
+    pub trigger: LimitTrigger,
+}
+
+impl Sensor {
+    /// Whether the sensor is attached to a part rather than a joint.
+    pub fn on_part(&self) -> bool {
+        match self.kind {
+            SensorKind::Encoder => false,
+            SensorKind::LimitSwitch => matches!(self.trigger, LimitTrigger::Contact(_)),
+            SensorKind::Imu => true,
+        }
+    }
+}
+
+/// The robot's motor controllers and sensors and where they're plugged in, kept in a file next to the robot.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct ElectricalMap {
+    pub controllers: Vec<MotorController>,
+    pub sensors: Vec<Sensor>,
+    /// File of the robot the map was loaded for
+    pub robot: PathBuf,
+}
+
+impl ElectricalMap {
+    /// Returns where the map of the robot at `robot` is kept, such as `robot.electrical.json` for `robot.mrr`.
+    pub fn path_for(robot: &Path) -> PathBuf {
+        robot.with_extension("electrical.json")
+    }
+
+    /// Reads the map of the robot at `robot`, or an empty one if it doesn't have one yet.
+    pub fn load_for(robot: &Path) -> Result<Self> {
+        let path = Self::path_for(robot);
+        let mut map = if path.exists() {
+            let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            Self::parse(&text).with_context(|| format!("Failed to parse {}", path.display()))?
+        } else {
+            Self::default()
+        };
+        map.robot = robot.to_owned();
+        Ok(map)
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(text)?;
+        let list = |key: &str| value.get(key).map_or(Ok(&[][..]), |list| list.as_array()
+            .map(Vec::as_slice)
+            .with_context(|| format!("{key} is not a list")));
+        let name = |device: &Value| device["name"].as_str().map(str::to_owned).context("Device without a name");
+
+        let controllers = list("controllers")?.iter()
+            .map(|controller| {
+                let name = name(controller)?;
+                let kind = controller["type"].as_str().unwrap_or_default();
+                Ok(MotorController {
+                    kind: ControllerKind::from_name(kind).with_context(|| format!("Controller \"{name}\" has an unknown type \"{kind}\""))?,
+                    port: Port::from_json(controller).with_context(|| format!("In controller \"{name}\""))?,
+                    actuator: controller["actuator"].as_str().map(str::to_owned),
+                    name,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let sensors = list("sensors")?.iter()
+            .map(|sensor| {
+                let name = name(sensor)?;
+                let kind = sensor["type"].as_str().unwrap_or_default();
+                let kind = SensorKind::from_key(kind)
+                    .with_context(|| format!("Sensor \"{name}\" has an unknown type \"{kind}\", expected encoder, limit_switch or imu"))?;
+                let defaults = EncoderSettings::default();
+                let mut parsed = Sensor {
+                    port: Port::from_json(sensor).with_context(|| format!("In sensor \"{name}\""))?,
+                    attached_to: None,
+                    encoder: EncoderSettings {
+                        absolute: sensor["absolute"].as_bool().unwrap_or(defaults.absolute),
+                        cpr: sensor["cpr"].as_f64().unwrap_or(defaults.cpr),
+                        noise: sensor["noise"].as_f64().unwrap_or(defaults.noise),
+                    },
+                    trigger: match kind {
+                        SensorKind::LimitSwitch => LimitTrigger::from_json(sensor).with_context(|| format!("In sensor \"{name}\""))?,
+                        _ => LimitTrigger::default(),
+                    },
+                    kind,
+                    name,
+                };
+                parsed.attached_to = sensor[if parsed.on_part() { "part" } else { "joint" }].as_str().map(str::to_owned);
+                Ok(parsed)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { controllers, sensors, robot: PathBuf::new() })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path_for(&self.robot);
+        let controllers: Vec<Value> = self.controllers.iter()
+            .map(|controller| {
+                let mut value = json!({ "name": controller.name, "type": controller.kind.name() });
+                let (key, port) = controller.port.to_json();
+                value[key] = port;
+                if let Some(actuator) = &controller.actuator {
+                    value["actuator"] = json!(actuator);
+                }
+                value
+            })
+            .collect();
+        let sensors: Vec<Value> = self.sensors.iter()
+            .map(|sensor| {
+                let mut value = json!({ "name": sensor.name, "type": sensor.kind.key() });
+                let (key, port) = sensor.port.to_json();
+                value[key] = port;
+                if let Some(attached_to) = &sensor.attached_to {
+                    value[if sensor.on_part() { "part" } else { "joint" }] = json!(attached_to);
+                }
+                match sensor.kind {
+                    SensorKind::Encoder => {
+                        value["absolute"] = json!(sensor.encoder.absolute);
+                        value["cpr"] = json!(sensor.encoder.cpr);
+                        value["noise"] = json!(sensor.encoder.noise);
+                    }
+                    SensorKind::LimitSwitch => {
+                        let (key, trigger) = sensor.trigger.to_json();
+                        value[key] = trigger;
+                    }
+                    SensorKind::Imu => (),
+                }
+                value
+            })
+            .collect();
+
+        let text = serde_json::to_string_pretty(&json!({ "controllers": controllers, "sensors": sensors }))?;
+        std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Returns what's wired wrong, such as devices sharing an ID or a channel, or not attached to anything.
+    pub fn check(&self, assembly: &Assembly) -> Vec<String> {
+        let mut problems = vec![];
+
+        // Devices of different types can share a CAN ID, but not two of the same type
+        let mut can_ids: HashMap<(&str, u8), Vec<&str>> = HashMap::new();
+        let mut pwm_channels: HashMap<u8, Vec<&str>> = HashMap::new();
+        let mut dio_channels: HashMap<u8, Vec<&str>> = HashMap::new();
+        let mut analog_channels: HashMap<u8, Vec<&str>> = HashMap::new();
+        let devices = self.controllers.iter()
+            .map(|controller| (controller.name.as_str(), controller.kind.name(), &controller.port))
+            .chain(self.sensors.iter().map(|sensor| (sensor.name.as_str(), sensor.kind.name(), &sensor.port)));
+        for (name, kind, port) in devices {
+            match *port {
+                Port::Can(id) if id > MAX_CAN_ID => problems.push(format!("{name} has CAN ID {id}, but IDs only go up to {MAX_CAN_ID}")),
+                Port::Can(id) => can_ids.entry((kind, id)).or_default().push(name),
+                Port::Pwm(channel) if channel >= PWM_CHANNELS => problems.push(format!("{name} is on PWM {channel}, but the roboRIO only has {PWM_CHANNELS} PWM channels")),
+                Port::Pwm(channel) => pwm_channels.entry(channel).or_default().push(name),
+                Port::Dio(channel) => dio_channels.entry(channel).or_default().push(name),
+                Port::DioPair(a, b) => {
+                    dio_channels.entry(a).or_default().push(name);
+                    if b != a {
+                        dio_channels.entry(b).or_default().push(name);
+                    }
+                }
+                Port::Analog(channel) if channel >= ANALOG_CHANNELS => problems.push(format!("{name} is on analog input {channel}, but the roboRIO only has {ANALOG_CHANNELS} analog inputs")),
+                Port::Analog(channel) => analog_channels.entry(channel).or_default().push(name),
+                Port::Controller(ref controller) if !self.controllers.iter().any(|other| other.name == *controller) => {
+                    problems.push(format!("{name} is plugged into {controller}, which isn't in the map"))
+                }
+                Port::Controller(_) | Port::Mxp => (),
+            }
+        }
+
+        let shared = |kind: &str, channel: u8, names: &[&str]| (names.len() > 1)
+            .then(|| format!("{} share {kind} {channel}", names.join(", ")));
+        for ((kind, id), names) in sorted(can_ids) {
+            problems.extend(shared(&format!("{kind} CAN ID"), id, &names));
+        }
+        for (channel, names) in sorted(pwm_channels) {
+            problems.extend(shared("PWM", channel, &names));
+        }
+        for (channel, names) in sorted(dio_channels) {
+            if channel >= DIO_CHANNELS {
+                problems.push(format!("{} use DIO {channel}, but the roboRIO only has {DIO_CHANNELS} DIO channels", names.join(", ")));
+            }
+            problems.extend(shared("DIO", channel, &names));
+        }
+        for (channel, names) in sorted(analog_channels) {
+            problems.extend(shared("analog input", channel, &names));
+        }
+
+        for controller in &self.controllers {
+            match (&controller.port, controller.kind.has_can()) {
+                (Port::Can(_), false) => problems.push(format!("{} is a {}, which can't be on CAN", controller.name, controller.kind.name())),
+                (Port::Can(_) | Port::Pwm(_), _) => (),
+                (port, _) => problems.push(format!("{} is on {}, but motor controllers go on CAN or PWM", controller.name, port.name())),
+            }
+            let Some(name) = &controller.actuator else {
+                problems.push(format!("{} doesn't drive anything", controller.name));
+                continue;
+            };
+            match assembly.actuators.iter().find(|actuator| actuator.name == *name) {
+                None => problems.push(format!("{} drives {name}, which the robot doesn't have", controller.name)),
+                Some(actuator) => match actuator.motor {
+                    Some(motor) if !controller.kind.can_drive(&motor.model) => {
+                        problems.push(format!("{} is a {}, which can't run the {} of {name}", controller.name, controller.kind.name(), motor.model.name))
+                    }
+                    Some(_) => (),
+                    None => problems.push(format!("{} drives {name}, which has no motor", controller.name)),
+                },
+            }
+        }
+
+        for actuator in assembly.actuators.iter() {
+            let Some(motor) = actuator.motor else {
+                continue;
+            };
+            let controllers = self.controllers.iter()
+                .filter(|controller| controller.actuator.as_ref() == Some(&actuator.name))
+                .count();
+            if controllers == 0 {
+                problems.push(format!("{} has no motor controller", actuator.name));
+            } else if controllers > motor.count as usize {
+                problems.push(format!("{} has {controllers} motor controllers for {} motor(s)", actuator.name, motor.count));
+            }
+        }
+
+        for sensor in &self.sensors {
+            let (attachment, exists) = match &sensor.attached_to {
+                Some(name) if sensor.on_part() => ("part", assembly.parts.iter().any(|part| part.name == *name)),
+                Some(name) => ("joint", assembly.joints.iter().any(|joint| joint.name == *name)),
+                None => {
+                    problems.push(format!("{} isn't attached to anything", sensor.name));
+                    continue;
+                }
+            };
+            if !exists {
+                problems.push(format!("{} is attached to {}, a {attachment} the robot doesn't have", sensor.name, sensor.attached_to.as_deref().unwrap_or_default()));
+            }
+            match (&sensor.port, sensor.kind) {
+                (Port::Pwm(_), _) => problems.push(format!("{} is on PWM, which is only for motor controllers", sensor.name)),
+                (Port::DioPair(..), SensorKind::LimitSwitch | SensorKind::Imu) => problems.push(format!("{} is on a DIO pair, which is only for encoders", sensor.name)),
+                (Port::Analog(_), SensorKind::Encoder) if !sensor.encoder.absolute => {
+                    problems.push(format!("{} is on an analog input, which only reads absolute encoders", sensor.name))
+                }
+                (Port::Analog(_), SensorKind::LimitSwitch | SensorKind::Imu) => problems.push(format!("{} is on an analog input, which is only for encoders", sensor.name)),
+                _ => (),
+            }
+            match (sensor.kind, &sensor.trigger) {
+                (SensorKind::Encoder, _) if sensor.encoder.cpr <= 0. => problems.push(format!("{} counts {} per revolution, which should be more than 0", sensor.name, sensor.encoder.cpr)),
+                (SensorKind::LimitSwitch, LimitTrigger::Contact(part)) if !assembly.parts.iter().any(|other| other.name == *part) => {
+                    problems.push(format!("{} is pressed by {part}, a part the robot doesn't have", sensor.name))
+                }
+                _ => (),
+            }
+        }
+
+        problems
+    }
+}
+
+fn sorted<K: Ord, V>(map: HashMap<K, V>) -> Vec<(K, V)> {
+    let mut entries: Vec<(K, V)> = map.into_iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+/// Logs anything wired wrong in `map`.
+pub fn log_problems(map: &ElectricalMap, assembly: &Assembly, log: &mut LogMessages) {
+    let problems = map.check(assembly);
+    if !problems.is_empty() {
+        log.warn("Wiring Problems", &problems.join("\n"));
+    }
+}
+
+/// Loads the electrical map of a robot when it's opened.
+fn load_electrical_map(assembly: Res<Assembly>, mut map: ResMut<ElectricalMap>, mut log: ResMut<LogMessages>) {
+    if assembly.file_path == map.robot {
+        return;
+    }
+
+    match ElectricalMap::load_for(&assembly.file_path) {
+        Ok(loaded) => {
+            *map = loaded;
+            log_problems(&map, &assembly, &mut log);
+        }
+        Err(err) => {
+            *map = ElectricalMap { robot: assembly.file_path.clone(), ..default() };
+            log.error("Loading Electrical Map Failed", &format!("{err:#}"), true);
+        }
+    }
+}
+
+pub struct ElectricalPlugin;
+
+impl Plugin for ElectricalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ElectricalMap>()
+        .add_system(load_electrical_map.run_if(resource_changed::<Assembly>()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_every_kind_of_port() {
+        let ports = [
+            (json!({ "can_id": 3 }), Port::Can(3)),
+            (json!({ "pwm": 1 }), Port::Pwm(1)),
+            (json!({ "dio": 4 }), Port::Dio(4)),
+            (json!({ "dio": [0, 1] }), Port::DioPair(0, 1)),
+            (json!({ "analog": 2 }), Port::Analog(2)),
+            (json!({ "controller": "Arm Motor" }), Port::Controller("Arm Motor".to_owned())),
+            (json!({ "mxp": true }), Port::Mxp),
+        ];
+        for (json, port) in ports {
+            assert_eq!(Port::from_json(&json).unwrap(), port);
+            let (key, value) = port.to_json();
+            assert_eq!(json[key], value);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_ports() {
+        for json in [json!({}), json!({ "can_id": 300 }), json!({ "pwm": -1 }), json!({ "dio": [0, 1, 2] }), json!({ "mxp": false })] {
+            assert!(Port::from_json(&json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn sensors_attach_to_joints_or_parts() {
+        let map = ElectricalMap::parse(r#"{ "sensors": [
+            { "name": "Encoder", "type": "encoder", "dio": [0, 1], "joint": "Shoulder", "cpr": 2048 },
+            { "name": "Gyro", "type": "imu", "mxp": true, "part": "Frame" }
+        ] }"#).unwrap();
+
+        assert_eq!(map.sensors[0].port, Port::DioPair(0, 1));
+        assert_eq!(map.sensors[0].attached_to.as_deref(), Some("Shoulder"));
+        assert_eq!(map.sensors[0].encoder.cpr, 2048.);
+        assert_eq!(map.sensors[1].attached_to.as_deref(), Some("Frame"));
+    }
+
+    #[test]
+    fn finds_shared_and_missing_ports() {
+        let controller = |name: &str, kind: ControllerKind, port: Port| MotorController {
+            name: name.to_owned(),
+            kind,
+            port,
+            actuator: None,
+        };
+        let sensor = |name: &str, port: Port| Sensor {
+            name: name.to_owned(),
+            kind: SensorKind::LimitSwitch,
+            port,
+            attached_to: None,
+            encoder: EncoderSettings::default(),
+            trigger: LimitTrigger::default(),
+        };
+        let map = ElectricalMap {
+            controllers: vec![
+                controller("Left", ControllerKind::SparkMax, Port::Can(1)),
+                controller("Right", ControllerKind::SparkMax, Port::Can(1)),
+                controller("Intake", ControllerKind::TalonFx, Port::Can(1)),
+                controller("Victor", ControllerKind::VictorSp, Port::Can(2)),
+            ],
+            sensors: vec![
+                sensor("Top", Port::Dio(3)),
+                sensor("Bottom", Port::Dio(3)),
+                sensor("Far", Port::Dio(DIO_CHANNELS)),
+                sensor("Follower", Port::Controller("Missing".to_owned())),
+            ],
+            robot: PathBuf::new(),
+        };
+
+        let problems = map.check(&Assembly::default());
+        let has = |problem: &str| problems.iter().any(|found| found == problem);
+        assert!(has("Left, Right share Spark MAX CAN ID 1"), "{problems:?}");
+        // A controller of another type can have the same ID
+        assert!(!problems.iter().any(|found| found.contains("Intake") && found.contains("share")), "{problems:?}");
+        assert!(has("Victor is a Victor SP, which can't be on CAN"), "{problems:?}");
+        assert!(has("Top, Bottom share DIO 3"), "{problems:?}");
+        assert!(has(&format!("Far use DIO {DIO_CHANNELS}, but the roboRIO only has {DIO_CHANNELS} DIO channels")), "{problems:?}");
+        assert!(has("Follower is plugged into Missing, which isn't in the map"), "{problems:?}");
+    }
+}
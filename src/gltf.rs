@@ -0,0 +1,390 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use bevy::math::{DQuat, DVec3};
+use serde_json::{Value, json};
+
+use crate::appearance::{self, ColorBy};
+use crate::kinematics::KinematicTree;
+use crate::mrr::{Assembly, Body, JointType, Pose, METERS_PER_UNIT};
+use crate::sim::{joint_position_channel, Telemetry};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const JSON_CHUNK: u32 = 0x4E4F534A;
+const BIN_CHUNK: u32 = 0x004E4942;
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GlbOptions<'a> {
+    /// Joint positions baked into the node transforms, indexed like [`Assembly::joints`]. Joints without
+    /// a position are exported at rest.
+    pub joint_positions: &'a [f64],
+    /// Recorded joint positions to export as an animation
+    pub telemetry: Option<&'a Telemetry>,
+}
+
+/// Writes `assembly` as a binary glTF 2.0 file. Every rigid group becomes a node, nested under the node
+/// it is jointed to, with a child node for each of its parts. Returns anything that couldn't be exported.
+pub fn save(assembly: &Assembly, path: &Path, options: &GlbOptions) -> Result<Vec<String>> {
+    let tree = KinematicTree::new(assembly);
+    let warnings = tree.warnings.clone();
+
+    let mut builder = Builder::default();
+    let mut nodes = vec![];
+    let mut meshes = vec![];
+    let mut materials = vec![];
+    let appearances = appearance::part_appearances(assembly, ColorBy::Part);
+
+    let positions: Vec<f64> = (0..assembly.joints.len())
+        .map(|joint| options.joint_positions.get(joint).copied().unwrap_or(0.))
+        .collect();
+
+    // Link nodes come first so their indices match the tree
+    for (index, link) in tree.links.iter().enumerate() {
+        let name = &assembly.parts[link.parts[0]].name;
+        let mut node = transform_json(&tree.local_frame(assembly, index, link.parent.map_or(0., |(_, joint)| positions[joint])));
+        node["name"] = json!(name);
+        nodes.push(node);
+    }
+
+    for (index, link) in tree.links.iter().enumerate() {
+        let mut children: Vec<usize> = tree.links.iter()
+            .enumerate()
+            .filter(|(_, child)| child.parent.is_some_and(|(parent, _)| parent == index))
+            .map(|(child, _)| child)
+            .collect();
+
+        // Bodies are stored in assembly coordinates, so move them into the link's frame
+        let to_link = tree.rest_frame(assembly, index).inverse();
+        for &part_index in &link.parts {
+            let part = &assembly.parts[part_index];
+            if part.bodies.is_empty() {
+                continue;
+            }
+
+            let appearance = &appearances[part_index];
+            materials.push(json!({
+                "name": part.name,
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": appearance.base_color().as_linear_rgba_f32(),
+                    "metallicFactor": appearance.metallic,
+                    "roughnessFactor": appearance.roughness,
+                },
+                "alphaMode": if appearance.opacity < 1. { "BLEND" } else { "OPAQUE" },
+            }));
+
+            let primitives: Vec<Value> = part.bodies.iter()
+                .map(|body| {
+                    let mut body = body.clone();
+                    body.transform(&to_link, 1.);
+                    builder.primitive(&body, materials.len() - 1)
+                })
+                .collect();
+            meshes.push(json!({ "name": part.name, "primitives": primitives }));
+
+            children.push(nodes.len());
+            nodes.push(json!({ "name": part.name, "mesh": meshes.len() - 1 }));
+        }
+
+        if !children.is_empty() {
+            nodes[index]["children"] = json!(children);
+        }
+    }
+
+    let roots: Vec<usize> = (0..tree.links.len())
+        .filter(|&link| tree.links[link].parent.is_none())
+        .collect();
+
+    let mut document = json!({
+        "asset": { "version": "2.0", "generator": "MechSim" },
+        "scene": 0,
+        "scenes": [{ "name": assembly.get_name(), "nodes": roots }],
+        "nodes": nodes,
+        "meshes": meshes,
+        "materials": materials,
+    });
+
+    if let Some(telemetry) = options.telemetry {
+        if let Some(animation) = animation(assembly, &tree, telemetry, &mut builder) {
+            document["animations"] = json!([animation]);
+        }
+    }
+
+    if !builder.buffer.is_empty() {
+        document["accessors"] = json!(builder.accessors);
+        document["bufferViews"] = json!(builder.buffer_views);
+        document["buffers"] = json!([{ "byteLength": builder.buffer.len() }]);
+    }
+
+    // glTF doesn't allow empty arrays
+    let object = document.as_object_mut().unwrap();
+    object.retain(|_, value| value.as_array().is_none_or(|array| !array.is_empty()));
+
+    std::fs::write(path, glb(&document, builder.buffer)?).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(warnings)
+}
+
+/// Animates every link whose joint has recorded positions in `telemetry`.
+fn animation(assembly: &Assembly, tree: &KinematicTree, telemetry: &Telemetry, builder: &mut Builder) -> Option<Value> {
+    let mut samplers = vec![];
+    let mut channels = vec![];
+
+    for (index, link) in tree.links.iter().enumerate() {
+        let Some((_, joint)) = link.parent else {
+            continue;
+        };
+        let (path, components) = match assembly.joints[joint].joint_type {
+            JointType::Rigid => continue,
+            JointType::Revolute => ("rotation", 4),
+            JointType::Slider => ("translation", 3),
+        };
+
+        let samples: Vec<(f64, f64)> = telemetry.samples(&joint_position_channel(joint)).collect();
+        if samples.is_empty() {
+            continue;
+        }
+
+        let times: Vec<f32> = samples.iter().map(|&(time, _)| time as f32).collect();
+        let values: Vec<f32> = samples.iter()
+            .flat_map(|&(_, position)| {
+                let frame = to_meters(&tree.local_frame(assembly, index, position));
+                match components {
+                    4 => frame.1.as_f32().to_array().to_vec(),
+                    _ => frame.0.as_vec3().to_array().to_vec(),
+                }
+            })
+            .collect();
+
+        let input = builder.accessor(&times, "SCALAR", None, true);
+        let output = builder.accessor(&values, if components == 4 { "VEC4" } else { "VEC3" }, None, false);
+        samplers.push(json!({ "input": input, "output": output, "interpolation": "LINEAR" }));
+        channels.push(json!({ "sampler": samplers.len() - 1, "target": { "node": index, "path": path } }));
+    }
+
+    if channels.is_empty() {
+        return None;
+    }
+    Some(json!({ "name": "Telemetry", "samplers": samplers, "channels": channels }))
+}
+
+fn to_meters(pose: &Pose) -> Pose {
+    Pose(pose.0 * METERS_PER_UNIT, pose.1)
+}
+
+/// Node transform of a pose in assembly units. The assembly's [`UP`](crate::mrr::UP) axis is Y, like glTF's, so poses are
+/// written as they are.
+fn transform_json(pose: &Pose) -> Value {
+    let pose = to_meters(pose);
+    let mut node = json!({});
+    if pose.0 != DVec3::ZERO {
+        node["translation"] = json!(pose.0.as_vec3().to_array());
+    }
+    if pose.1 != DQuat::IDENTITY {
+        node["rotation"] = json!(pose.1.as_f32().normalize().to_array());
+    }
+    node
+}
+
+/// Packs the JSON document and binary buffer into a GLB file.
+fn glb(document: &Value, mut buffer: Vec<u8>) -> Result<Vec<u8>> {
+    let mut json = serde_json::to_vec(document)?;
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+    while !buffer.len().is_multiple_of(4) {
+        buffer.push(0);
+    }
+
+    let length = 12 + 8 + json.len() + 8 + buffer.len();
+    let mut data = Vec::with_capacity(length);
+    data.extend(GLB_MAGIC);
+    data.extend(GLB_VERSION.to_le_bytes());
+    data.extend((length as u32).to_le_bytes());
+
+    for (chunk_type, chunk) in [(JSON_CHUNK, json), (BIN_CHUNK, buffer)] {
+        data.extend((chunk.len() as u32).to_le_bytes());
+        data.extend(chunk_type.to_le_bytes());
+        data.extend(chunk);
+    }
+    Ok(data)
+}
+
+/// Collects the binary data of the file and the accessors and buffer views describing it.
+#[derive(Default)]
+struct Builder {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl Builder {
+    fn buffer_view(&mut self, bytes: impl IntoIterator<Item = u8>, target: Option<u32>) -> usize {
+        let offset = self.buffer.len();
+        self.buffer.extend(bytes);
+
+        let mut view = json!({ "buffer": 0, "byteOffset": offset, "byteLength": self.buffer.len() - offset });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        self.buffer_views.push(view);
+        self.buffer_views.len() - 1
+    }
+
+    /// Adds an accessor for floats grouped as `accessor_type`, with their bounds if `bounds` is set.
+    fn accessor(&mut self, values: &[f32], accessor_type: &str, target: Option<u32>, bounds: bool) -> usize {
+        let components = match accessor_type {
+            "VEC4" => 4,
+            "VEC3" => 3,
+            _ => 1,
+        };
+        let view = self.buffer_view(values.iter().flat_map(|value| value.to_le_bytes()), target);
+
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": values.len() / components,
+            "type": accessor_type,
+        });
+        if bounds {
+            let mut min = vec![f32::INFINITY; components];
+            let mut max = vec![f32::NEG_INFINITY; components];
+            for element in values.chunks_exact(components) {
+                for (i, &value) in element.iter().enumerate() {
+                    min[i] = min[i].min(value);
+                    max[i] = max[i].max(value);
+                }
+            }
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    /// Adds the mesh data of `body`, which is in assembly units, and returns the primitive drawing it.
+    fn primitive(&mut self, body: &Body, material: usize) -> Value {
+        let mut body = body.clone();
+        if body.normals.len() != body.verticies.len() {
+            body.compute_normals();
+        }
+
+        let scale = METERS_PER_UNIT as f32;
+        let positions: Vec<f32> = body.verticies.iter().map(|&value| value * scale).collect();
+        let position = self.accessor(&positions, "VEC3", Some(ARRAY_BUFFER), true);
+        let normal = self.accessor(&body.normals, "VEC3", Some(ARRAY_BUFFER), false);
+
+        let view = self.buffer_view(body.indicies.iter().flat_map(|&index| (index as u32).to_le_bytes()), Some(ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_INT,
+            "count": body.indicies.len(),
+            "type": "SCALAR",
+        }));
+
+        json!({
+            "attributes": { "POSITION": position, "NORMAL": normal },
+            "indices": self.accessors.len() - 1,
+            "material": material,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::mrr::{Joint, Part};
+
+    fn part(name: &str, rigid_group: u32) -> Part {
+        Part {
+            name: name.to_owned(),
+            pose: Pose::IDENTITY,
+            joint_references: vec![0],
+            rigid_group_references: vec![rigid_group],
+            bodies: vec![Body::from_triangles(vec![0., 0., 0., 10., 0., 0., 0., 10., 0.], vec![0, 1, 2])],
+            inertial: None,
+            hardware: false,
+            appearance: None,
+            material: None,
+            physical_material: None,
+        }
+    }
+
+    /// A base and an arm on a revolute joint 10 units above it.
+    fn arm() -> Assembly {
+        Assembly {
+            parts: vec![part("Base", 0), part("Arm", 1)],
+            joints: vec![Joint {
+                name: "Shoulder".to_owned(),
+                joint_type: JointType::Revolute,
+                pose: Pose(DVec3::Z * 10., DQuat::IDENTITY),
+                limits: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Saves `assembly` and returns the JSON chunk and the length of the binary chunk, checking the GLB layout.
+    fn save_and_read(assembly: &Assembly, options: &GlbOptions) -> (Value, usize) {
+        let path = std::env::temp_dir().join(format!("mechsim-gltf-{}-{:?}.glb", std::process::id(), std::thread::current().id()));
+        save(assembly, &path, options).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let word = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        assert_eq!(&data[..4], GLB_MAGIC);
+        assert_eq!(word(4), GLB_VERSION as usize);
+        assert_eq!(word(8), data.len());
+
+        let json_length = word(12);
+        assert_eq!(word(16), JSON_CHUNK as usize);
+        assert_eq!(json_length % 4, 0);
+        let document = serde_json::from_slice(&data[20..20 + json_length]).unwrap();
+
+        let bin = 20 + json_length;
+        assert_eq!(word(bin + 4), BIN_CHUNK as usize);
+        assert_eq!(word(bin) % 4, 0);
+        assert_eq!(bin + 8 + word(bin), data.len());
+        (document, word(bin))
+    }
+
+    #[test]
+    fn nests_jointed_parts() {
+        let (document, bin_length) = save_and_read(&arm(), &GlbOptions::default());
+
+        assert_eq!(document["scenes"][0]["nodes"], json!([0]));
+        let nodes = document["nodes"].as_array().unwrap();
+        // Two links, then a mesh node for each part
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(nodes[0]["children"], json!([1, 2]));
+        assert_eq!(nodes[1]["children"], json!([3]));
+        assert_eq!(nodes[1]["translation"], json!([0., 0., 0.254f32]));
+        assert_eq!(nodes[3]["mesh"], json!(1));
+
+        assert!(document["buffers"][0]["byteLength"].as_u64().unwrap() as usize <= bin_length);
+        let position = &document["accessors"][document["meshes"][0]["primitives"][0]["attributes"]["POSITION"].as_u64().unwrap() as usize];
+        assert_eq!(position["min"], json!([0., 0., 0.]));
+        assert_eq!(position["max"], json!([0.254f32, 0.254f32, 0.]));
+        assert!(document.get("animations").is_none());
+    }
+
+    #[test]
+    fn bakes_joint_positions() {
+        let (document, _) = save_and_read(&arm(), &GlbOptions { joint_positions: &[FRAC_PI_2], telemetry: None });
+        let rotation: Vec<f64> = document["nodes"][1]["rotation"].as_array().unwrap().iter().map(|value| value.as_f64().unwrap()).collect();
+        assert!(DQuat::from_slice(&rotation).abs_diff_eq(DQuat::from_rotation_z(FRAC_PI_2), 1e-6), "{rotation:?}");
+    }
+
+    #[test]
+    fn reports_unwritable_files() {
+        let path = std::env::temp_dir().join(format!("mechsim-missing-{}", std::process::id())).join("robot.glb");
+        assert!(save(&arm(), &path, &GlbOptions::default()).is_err());
+    }
+}
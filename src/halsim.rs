@@ -0,0 +1,603 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use bevy::prelude::*;
+use serde_json::{Map, Value, json};
+use tungstenite::{Message, WebSocket};
+
+use crate::battery::Battery;
+use crate::electrical::{ElectricalMap, MotorController, Port, ANALOG_VOLTAGE};
+use crate::log::LogMessages;
+use crate::mrr::Assembly;
+use crate::sensors::{ImuReading, SensorReading, SensorReadings};
+use crate::sim::{ActuatorStates, JointStates, SimSet, SimStep};
+
+/// Port WPILib's simulation WebSocket extensions use by default.
+pub const DEFAULT_PORT: u16 = 3300;
+/// Path of the WebSocket on the robot program's server.
+const URI: &str = "/wpilibws";
+/// How long the connection waits for a message before sending the ones queued up.
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+/// How long to wait before trying to reach the robot program again as a client.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+/// Values of SimDevices that hold the output of a motor controller from -1 to 1, such as those of REV's Spark MAX.
+const OUTPUT_VALUES: [&str; 2] = ["Applied Output", "Duty Cycle"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HalSimMode {
+    /// Wait for robot programs using the `halsim_ws_client` extension to connect
+    #[default]
+    Server,
+    /// Connect to a robot program using the `halsim_ws_server` extension
+    Client,
+}
+
+impl HalSimMode {
+    pub const ALL: [HalSimMode; 2] = [HalSimMode::Server, HalSimMode::Client];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HalSimMode::Server => "Server",
+            HalSimMode::Client => "Client",
+        }
+    }
+
+    /// Finds the mode with `name`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RobotMode {
+    #[default]
+    Teleop,
+    Autonomous,
+    Test,
+}
+
+impl RobotMode {
+    pub const ALL: [RobotMode; 3] = [RobotMode::Teleop, RobotMode::Autonomous, RobotMode::Test];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RobotMode::Teleop => "Teleop",
+            RobotMode::Autonomous => "Autonomous",
+            RobotMode::Test => "Test",
+        }
+    }
+
+    /// Finds the mode with `name`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// What the connection's thread reports.
+enum Event {
+    Connected(String),
+    Message(Value),
+    Disconnected(String),
+}
+
+/// A thread listening for or connecting to the robot program, stopped when dropped.
+struct Connection {
+    events: Mutex<Receiver<Event>>,
+    outgoing: Sender<Value>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The hardware the robot program has told MechSim about.
+#[derive(Debug, Default)]
+struct RobotHardware {
+    /// Speed of every PWM output from -1 to 1, by channel
+    pwm: HashMap<u8, f64>,
+    /// DIO channels of the A and B inputs of every encoder, by the robot program's index for it
+    encoders: HashMap<String, (Option<u8>, Option<u8>)>,
+    /// Values of every SimDevice, such as `SPARK MAX [3]`, keyed with the direction prefix they were sent with
+    sim_devices: HashMap<String, Map<String, Value>>,
+}
+
+impl RobotHardware {
+    fn update(&mut self, message: &Value) {
+        let (Some(kind), Some(device), Some(data)) = (message["type"].as_str(), message["device"].as_str(), message["data"].as_object()) else {
+            return;
+        };
+        let closed = data.get("<init") == Some(&json!(false));
+
+        match kind {
+            "PWM" => {
+                let Ok(channel) = device.parse::<u8>() else {
+                    return;
+                };
+                if closed {
+                    self.pwm.remove(&channel);
+                } else if let Some(speed) = data.get("<speed").and_then(Value::as_f64) {
+                    self.pwm.insert(channel, speed);
+                }
+            }
+            "Encoder" if closed => {
+                self.encoders.remove(device);
+            }
+            "Encoder" => {
+                let channel = |key: &str| data.get(key).and_then(Value::as_u64).and_then(|channel| u8::try_from(channel).ok());
+                let channels = self.encoders.entry(device.to_owned()).or_default();
+                channels.0 = channel("<channel_a").or(channels.0);
+                channels.1 = channel("<channel_b").or(channels.1);
+            }
+            "SimDevice" => self.sim_devices.entry(device.to_owned()).or_default().extend(data.clone()),
+            _ => (),
+        }
+    }
+
+    /// Returns the name and values of the SimDevice for the motor controller with CAN ID `id`.
+    fn controller_device(&self, id: u8) -> Option<(&String, &Map<String, Value>)> {
+        self.sim_devices.iter()
+            .find(|(name, values)| can_id(name) == Some(id) && OUTPUT_VALUES.iter().any(|output| find_value(values, output).is_some()))
+    }
+
+    /// Returns the output of `controller` from -1 to 1, if the robot program drives it.
+    fn output(&self, controller: &MotorController) -> Option<f64> {
+        match controller.port {
+            Port::Pwm(channel) => self.pwm.get(&channel).copied(),
+            Port::Can(id) => {
+                let (_, values) = self.controller_device(id)?;
+                OUTPUT_VALUES.iter()
+                    .find_map(|output| find_value(values, output))
+                    .and_then(|key| values[key].as_f64())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns the CAN ID at the end of a SimDevice's name, such as 3 for `SPARK MAX [3]`.
+fn can_id(name: &str) -> Option<u8> {
+    let (_, id) = name.trim_end().strip_suffix(']')?.rsplit_once('[')?;
+    id.trim().parse().ok()
+}
+
+/// Returns the key of the value called `name` in a SimDevice's values, with its direction prefix.
+fn find_value<'a>(values: &'a Map<String, Value>, name: &str) -> Option<&'a String> {
+    values.keys().find(|key| key.trim_start_matches(['<', '>']) == name)
+}
+
+/// Sets the values of a SimDevice that the robot program created and reads, leaving out any it doesn't have.
+fn sim_device_inputs(reported: &Map<String, Value>, values: &[(&str, f64)]) -> Map<String, Value> {
+    values.iter()
+        .filter_map(|&(name, value)| {
+            let key = find_value(reported, name).filter(|key| key.starts_with('>') || key.starts_with("<>"))?;
+            Some((key.clone(), json!(value)))
+        })
+        .collect()
+}
+
+/// Seconds per count of an encoder turning at `rate` counts per second. The period is signed, giving the
+/// direction, and as long as it can be when the encoder isn't turning.
+fn encoder_period(rate: f64) -> f64 {
+    if rate == 0. { f64::MAX } else { 1. / rate }
+}
+
+/// Values of gyro SimDevices such as the navX's and ADXRS450's. Both measure yaw clockwise, in degrees.
+fn gyro_values(imu: &ImuReading) -> [(&'static str, f64); 6] {
+    let yaw = -imu.yaw.to_degrees();
+    let rate = -imu.angular_velocity.z.to_degrees();
+    [
+        ("Yaw", yaw),
+        ("Pitch", imu.pitch.to_degrees()),
+        ("Roll", imu.roll.to_degrees()),
+        ("Rate", rate),
+        ("angle_x", yaw),
+        ("rate_x", rate),
+    ]
+}
+
+/// Connects robot code to the simulation with WPILib's simulation WebSocket protocol, `halsim_ws`. The robot
+/// program's motor controller outputs drive the actuators, and the simulated sensors, battery and driver
+/// station are sent back to it.
+#[derive(Resource)]
+pub struct HalSim {
+    pub mode: HalSimMode,
+    /// Address to listen on as a server, or of the robot program as a client
+    pub address: String,
+    pub enabled: bool,
+    pub robot_mode: RobotMode,
+    /// Address of the robot program while it's connected
+    pub peer: Option<String>,
+    connection: Option<Connection>,
+    hardware: RobotHardware,
+    /// Values last sent for every device, by type and device, so only changes are sent
+    sent: HashMap<(&'static str, String), Map<String, Value>>,
+    /// Actuators the robot program drives
+    driven: Vec<usize>,
+    /// Actuators the robot program stopped driving, to be stopped
+    released: Vec<usize>,
+}
+
+impl Default for HalSim {
+    fn default() -> Self {
+        Self {
+            mode: HalSimMode::default(),
+            address: format!("localhost:{DEFAULT_PORT}"),
+            enabled: false,
+            robot_mode: RobotMode::default(),
+            peer: None,
+            connection: None,
+            hardware: RobotHardware::default(),
+            sent: HashMap::new(),
+            driven: vec![],
+            released: vec![],
+        }
+    }
+}
+
+impl HalSim {
+    pub fn is_running(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.peer.is_some()
+    }
+
+    /// Starts listening for the robot program, or trying to connect to it.
+    pub fn start(&mut self) -> Result<()> {
+        self.stop();
+
+        let (event_sender, events) = mpsc::channel();
+        let (outgoing, outgoing_receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        match self.mode {
+            HalSimMode::Server => {
+                let listener = TcpListener::bind(&self.address).with_context(|| format!("Failed to listen on {}", self.address))?;
+                listener.set_nonblocking(true)?;
+                thread::spawn(move || serve(listener, event_sender, outgoing_receiver, thread_stop));
+            }
+            HalSimMode::Client => {
+                let address = self.address.clone();
+                thread::spawn(move || connect(address, event_sender, outgoing_receiver, thread_stop));
+            }
+        }
+
+        self.connection = Some(Connection { events: Mutex::new(events), outgoing, stop });
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.connection = None;
+        self.disconnected();
+    }
+
+    fn disconnected(&mut self) {
+        self.peer = None;
+        self.hardware = RobotHardware::default();
+        self.sent.clear();
+        self.released.append(&mut self.driven);
+    }
+}
+
+/// Waits for robot programs to connect, one at a time.
+fn serve(listener: TcpListener, events: Sender<Event>, outgoing: Receiver<Value>, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                let result = stream.set_nonblocking(false)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|()| tungstenite::accept(stream).map_err(|err| anyhow!("{err}")))
+                    .and_then(|socket| exchange(socket, peer.to_string(), &events, &outgoing, &stop));
+                events.send(Event::Disconnected(result.err().map_or("Connection closed".to_owned(), |err| format!("{err:#}")))).ok();
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL * 10),
+            Err(err) => {
+                events.send(Event::Disconnected(format!("Stopped listening: {err}"))).ok();
+                return;
+            }
+        }
+    }
+}
+
+/// Connects to the robot program, and again whenever the connection drops.
+fn connect(address: String, events: Sender<Event>, outgoing: Receiver<Value>, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::Relaxed) {
+        // The robot program may not be up yet, so failing to connect is expected
+        let Ok(stream) = TcpStream::connect(&address) else {
+            thread::sleep(RETRY_INTERVAL);
+            continue;
+        };
+        let result = tungstenite::client(format!("ws://{address}{URI}"), stream)
+            .map_err(|err| anyhow!("{err}"))
+            .and_then(|(socket, _)| exchange(socket, address.clone(), &events, &outgoing, &stop));
+        events.send(Event::Disconnected(result.err().map_or("Connection closed".to_owned(), |err| format!("{err:#}")))).ok();
+        thread::sleep(RETRY_INTERVAL);
+    }
+}
+
+/// Passes messages between the robot program and the app until either side closes the connection.
+fn exchange(mut socket: WebSocket<TcpStream>, peer: String, events: &Sender<Event>, outgoing: &Receiver<Value>, stop: &AtomicBool) -> Result<()> {
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+    // Anything queued before the robot program connected is out of date
+    while outgoing.try_recv().is_ok() {}
+    events.send(Event::Connected(peer)).ok();
+
+    while !stop.load(Ordering::Relaxed) {
+        for message in outgoing.try_iter() {
+            socket.write(Message::Text(message.to_string()))?;
+        }
+        socket.flush()?;
+
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                if let Ok(message) = serde_json::from_str(&text) {
+                    events.send(Event::Message(message)).ok();
+                }
+            }
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => (),
+            Err(tungstenite::Error::Io(err)) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => (),
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    socket.close(None).ok();
+    socket.flush().ok();
+    Ok(())
+}
+
+fn find_actuator(controller: &MotorController, assembly: &Assembly) -> Option<usize> {
+    let name = controller.actuator.as_ref()?;
+    assembly.actuators.iter().position(|actuator| actuator.name == *name)
+}
+
+/// Handles what the robot program sent, and drives the actuators with its motor controllers.
+fn receive_messages(
+    mut hal_sim: ResMut<HalSim>,
+    assembly: Res<Assembly>,
+    map: Res<ElectricalMap>,
+    battery: Res<Battery>,
+    mut actuator_states: ResMut<ActuatorStates>,
+    mut log: ResMut<LogMessages>,
+) {
+    let hal_sim = hal_sim.as_mut();
+    for actuator in hal_sim.released.drain(..) {
+        if let Some(state) = actuator_states.0.get_mut(actuator) {
+            state.control = 0.;
+        }
+    }
+
+    let Some(connection) = &hal_sim.connection else {
+        return;
+    };
+    let events: Vec<Event> = connection.events.lock().map(|events| events.try_iter().collect()).unwrap_or_default();
+    for event in events {
+        match event {
+            Event::Connected(peer) => {
+                log.info("Robot Code Connected", &peer);
+                hal_sim.peer = Some(peer);
+            }
+            Event::Message(message) => hal_sim.hardware.update(&message),
+            Event::Disconnected(reason) => {
+                if hal_sim.peer.is_some() {
+                    log.info("Robot Code Disconnected", &reason);
+                }
+                hal_sim.disconnected();
+            }
+        }
+    }
+    if hal_sim.peer.is_none() {
+        return;
+    }
+
+    // Motor controllers put out a fraction of the battery's voltage
+    let mut driven = vec![];
+    for controller in &map.controllers {
+        let Some(output) = hal_sim.hardware.output(controller) else {
+            continue;
+        };
+        let Some(actuator) = find_actuator(controller, &assembly) else {
+            continue;
+        };
+        let Some(state) = actuator_states.0.get_mut(actuator) else {
+            continue;
+        };
+        state.control = output.clamp(-1., 1.) * battery.voltage;
+        driven.push(actuator);
+    }
+    hal_sim.released.extend(hal_sim.driven.iter().filter(|actuator| !driven.contains(actuator)));
+    hal_sim.driven = driven;
+}
+
+/// Sends the robot program whatever changed in the driver station, battery, sensors and motor controllers.
+fn send_readings(
+    mut hal_sim: ResMut<HalSim>,
+    assembly: Res<Assembly>,
+    map: Res<ElectricalMap>,
+    readings: Res<SensorReadings>,
+    battery: Res<Battery>,
+    joint_states: Res<JointStates>,
+    actuator_states: Res<ActuatorStates>,
+) {
+    let hal_sim = hal_sim.as_mut();
+    if hal_sim.peer.is_none() {
+        return;
+    }
+    let hardware = &hal_sim.hardware;
+
+    let mut devices: Vec<(&'static str, String, Map<String, Value>)> = vec![];
+    let mut device = |kind: &'static str, name: String, values: Value| {
+        if let Value::Object(values) = values {
+            devices.push((kind, name, values));
+        }
+    };
+    device("DriverStation", String::new(), json!({
+        ">enabled": hal_sim.enabled,
+        ">autonomous": hal_sim.robot_mode == RobotMode::Autonomous,
+        ">test": hal_sim.robot_mode == RobotMode::Test,
+        ">estop": false,
+        ">fms": false,
+        ">ds": true,
+    }));
+    device("RoboRIO", String::new(), json!({ ">vin_voltage": battery.voltage, ">vin_current": battery.current }));
+
+    let mut imu = None;
+    for (sensor, reading) in map.sensors.iter().zip(&readings.readings) {
+        match (&sensor.port, *reading) {
+            (&Port::DioPair(a, b), SensorReading::Encoder { counts, rate }) => {
+                let index = hardware.encoders.iter().find(|(_, &channels)| channels == (Some(a), Some(b)));
+                if let Some((index, _)) = index {
+                    device("Encoder", index.clone(), json!({ ">count": counts, ">period": encoder_period(rate) }));
+                }
+            }
+            (&Port::Analog(channel), SensorReading::Encoder { counts, .. }) => {
+                let voltage = (counts as f64 / sensor.encoder.cpr).rem_euclid(1.) * ANALOG_VOLTAGE;
+                device("AI", channel.to_string(), json!({ ">voltage": voltage }));
+            }
+            // Normally open switches read low while pressed, against the roboRIO's pull-ups
+            (&Port::Dio(channel), SensorReading::LimitSwitch { pressed }) => device("DIO", channel.to_string(), json!({ "<>value": !pressed })),
+            (_, SensorReading::Imu(reading)) if imu.is_none() => imu = Some(reading),
+            _ => (),
+        }
+    }
+
+    for controller in &map.controllers {
+        let Port::Can(id) = controller.port else {
+            continue;
+        };
+        let (Some((name, reported)), Some(actuator)) = (hardware.controller_device(id), find_actuator(controller, &assembly)) else {
+            continue;
+        };
+        let (Some(joint), Some(state)) = (joint_states.0.get(assembly.actuators[actuator].joint), actuator_states.0.get(actuator)) else {
+            continue;
+        };
+        // The controller's encoder is on the motor, so it turns `gear` times as fast as the joint
+        let gear = assembly.actuators[actuator].gear;
+        let count = assembly.actuators[actuator].motor.map_or(1, |motor| motor.count).max(1);
+        let values = sim_device_inputs(reported, &[
+            ("Position", joint.position * gear / (2. * PI)),
+            ("Velocity", joint.velocity * gear * 60. / (2. * PI)),
+            ("Motor Current", state.current / count as f64),
+            ("Bus Voltage", battery.voltage),
+        ]);
+        device("SimDevice", name.clone(), Value::Object(values));
+    }
+    if let Some(imu) = imu {
+        for (name, reported) in &hardware.sim_devices {
+            if can_id(name).is_some_and(|id| hardware.controller_device(id).is_some()) {
+                continue;
+            }
+            device("SimDevice", name.clone(), Value::Object(sim_device_inputs(reported, &gyro_values(&imu))));
+        }
+    }
+
+    let Some(connection) = &hal_sim.connection else {
+        return;
+    };
+    for (kind, name, values) in devices {
+        let sent = hal_sim.sent.entry((kind, name.clone())).or_default();
+        let mut changed: Map<String, Value> = values.into_iter()
+            .filter(|(key, value)| sent.get(key) != Some(value))
+            .collect();
+        if changed.is_empty() {
+            continue;
+        }
+        sent.extend(changed.clone());
+        if kind == "DriverStation" {
+            changed.insert(">new_data".to_owned(), json!(true));
+        }
+        connection.outgoing.send(json!({ "type": kind, "device": name, "data": changed })).ok();
+    }
+}
+
+pub struct HalSimPlugin;
+
+impl Plugin for HalSimPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HalSim>()
+        .add_system(receive_messages.in_base_set(CoreSet::PreUpdate))
+        .add_system(send_readings.in_schedule(SimStep).after(SimSet::Sensors));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::electrical::ControllerKind;
+
+    fn controller(port: Port) -> MotorController {
+        MotorController { name: "Arm".to_owned(), kind: ControllerKind::SparkMax, port, actuator: None }
+    }
+
+    #[test]
+    fn tracks_pwm_outputs() {
+        let mut hardware = RobotHardware::default();
+        hardware.update(&json!({ "type": "PWM", "device": "2", "data": { "<init": true, "<speed": 0.5 } }));
+        assert_eq!(hardware.output(&controller(Port::Pwm(2))), Some(0.5));
+
+        hardware.update(&json!({ "type": "PWM", "device": "2", "data": { "<speed": -0.25 } }));
+        assert_eq!(hardware.output(&controller(Port::Pwm(2))), Some(-0.25));
+
+        hardware.update(&json!({ "type": "PWM", "device": "2", "data": { "<init": false } }));
+        assert_eq!(hardware.output(&controller(Port::Pwm(2))), None);
+    }
+
+    #[test]
+    fn tracks_encoder_channels_across_messages() {
+        let mut hardware = RobotHardware::default();
+        hardware.update(&json!({ "type": "Encoder", "device": "0", "data": { "<init": true, "<channel_a": 4 } }));
+        hardware.update(&json!({ "type": "Encoder", "device": "0", "data": { "<channel_b": 5 } }));
+        assert_eq!(hardware.encoders["0"], (Some(4), Some(5)));
+
+        hardware.update(&json!({ "type": "Encoder", "device": "0", "data": { "<init": false } }));
+        assert!(hardware.encoders.is_empty());
+    }
+
+    #[test]
+    fn reads_can_controllers_from_sim_devices() {
+        let mut hardware = RobotHardware::default();
+        hardware.update(&json!({ "type": "SimDevice", "device": "SPARK MAX [3]", "data": { "<Applied Output": 0.75, ">Position": 0. } }));
+        hardware.update(&json!({ "type": "SimDevice", "device": "navX-Sensor[3]", "data": { ">Yaw": 0. } }));
+        // Messages missing a type, device or data are ignored
+        hardware.update(&json!({ "type": "SimDevice", "data": { "<Applied Output": 1. } }));
+
+        assert_eq!(hardware.controller_device(3).map(|(name, _)| name.as_str()), Some("SPARK MAX [3]"));
+        assert_eq!(hardware.output(&controller(Port::Can(3))), Some(0.75));
+        assert_eq!(hardware.output(&controller(Port::Can(4))), None);
+    }
+
+    #[test]
+    fn reads_can_ids_from_sim_device_names() {
+        assert_eq!(can_id("SPARK MAX [3]"), Some(3));
+        assert_eq!(can_id("Talon FX[12] "), Some(12));
+        assert_eq!(can_id("navX-Sensor"), None);
+        assert_eq!(can_id("SPARK MAX [300]"), None);
+        assert_eq!(can_id("SPARK MAX [three]"), None);
+    }
+
+    #[test]
+    fn only_sets_sim_device_inputs_the_robot_program_has() {
+        let reported = json!({ ">Position": 0., "<>Velocity": 0., "<Applied Output": 0. });
+        let values = sim_device_inputs(reported.as_object().unwrap(), &[("Position", 1.), ("Velocity", 2.), ("Applied Output", 3.), ("Bus Voltage", 12.)]);
+        assert_eq!(Value::Object(values), json!({ ">Position": 1., "<>Velocity": 2. }));
+    }
+
+    #[test]
+    fn encoder_periods_are_signed() {
+        assert_eq!(encoder_period(4.), 0.25);
+        assert_eq!(encoder_period(-4.), -0.25);
+        assert_eq!(encoder_period(0.), f64::MAX);
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+
+use bevy::prelude::*;
+
+use crate::mrr::{Assembly, Part};
+
+/// Why a part was classified as hardware.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HardwareReason {
+    /// The name contains a word matching this pattern
+    Name(String),
+    /// The name looks like an Onshape Standard Content part, with a standard or thread size in it
+    StandardContent,
+    /// The part fits in the maximum size
+    Small,
+    /// The part is small and has many triangles, like a threaded fastener
+    Detailed,
+}
+
+impl fmt::Display for HardwareReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HardwareReason::Name(pattern) => write!(f, "name matches \"{pattern}\""),
+            HardwareReason::StandardContent => f.write_str("Standard Content name"),
+            HardwareReason::Small => f.write_str("small"),
+            HardwareReason::Detailed => f.write_str("small with many triangles"),
+        }
+    }
+}
+
+/// Picks out nuts, bolts, washers and other small parts, which slow down rendering and collisions
+/// without changing how the robot moves.
+#[derive(Resource, Debug, Clone)]
+pub struct HardwareFilter {
+    pub enabled: bool,
+    /// Words in part names that mark hardware, ignoring case. A trailing `*` matches any ending.
+    pub name_patterns: Vec<String>,
+    /// Treat names with a standard such as "ANSI B18.3" or a thread size such as "10-32" as hardware
+    pub standard_content: bool,
+    /// Parts with a bounding box diagonal shorter than this, in inches, are hardware
+    pub max_size: f64,
+    /// Parts up to twice `max_size` with more triangles than this are hardware
+    pub max_triangles: usize,
+}
+
+impl Default for HardwareFilter {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            name_patterns: [
+                "screw*", "bolt*", "nut*", "locknut*", "nylock", "keps", "washer*", "rivet*",
+                "standoff*", "shcs", "bhcs", "fhcs", "setscrew*",
+            ].map(str::to_owned).to_vec(),
+            standard_content: true,
+            max_size: 1.5,
+            max_triangles: 2000,
+        }
+    }
+}
+
+/// Standards Standard Content parts are named after, as in "Hex Nut (ANSI B18.2.2)".
+const STANDARDS: [&str; 5] = ["ansi", "asme", "iso", "din", "jis"];
+
+/// Returns whether `word` is a thread size such as `10-32`, `1/4-20` or `m5x0.8`.
+fn is_thread_size(word: &str) -> bool {
+    let word = word.trim_start_matches('#');
+    if let Some(metric) = word.strip_prefix('m') {
+        let diameter = metric.split('x').next().unwrap_or_default();
+        return !diameter.is_empty() && diameter.parse::<f64>().is_ok();
+    }
+
+    match word.split_once('-') {
+        Some((diameter, pitch)) => {
+            let diameter_valid = diameter.split('/').all(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()));
+            diameter_valid && pitch.len() >= 2 && pitch.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+fn matches_pattern(word: &str, pattern: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    match pattern.strip_suffix('*') {
+        Some(prefix) => word.starts_with(prefix),
+        None => word == pattern,
+    }
+}
+
+impl HardwareFilter {
+    /// Returns why `part` is hardware, or `None` if it isn't.
+    pub fn classify(&self, part: &Part) -> Option<HardwareReason> {
+        let name = part.name.to_ascii_lowercase();
+        let words: Vec<&str> = name
+            .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '_' | ',' | '[' | ']'))
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        for pattern in &self.name_patterns {
+            if words.iter().any(|word| matches_pattern(word, pattern)) {
+                return Some(HardwareReason::Name(pattern.clone()));
+            }
+        }
+        if self.standard_content && words.iter().any(|word| STANDARDS.contains(word) || word.starts_with("b18.") || is_thread_size(word)) {
+            return Some(HardwareReason::StandardContent);
+        }
+
+        let vertices = part.bodies.iter().flat_map(|body| (0..body.vertex_count()).map(|i| body.vertex(i)));
+        let (min, max) = vertices.fold((Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)), |(min, max), vertex| (min.min(vertex), max.max(vertex)));
+        if min.x > max.x {
+            return None;
+        }
+
+        let size = min.distance(max) as f64;
+        let triangles: usize = part.bodies.iter().map(|body| body.indicies.len() / 3).sum();
+        if size < self.max_size {
+            Some(HardwareReason::Small)
+        } else if size < self.max_size * 2. && triangles > self.max_triangles {
+            Some(HardwareReason::Detailed)
+        } else {
+            None
+        }
+    }
+
+    /// Marks the parts of `assembly` that are hardware, and returns how many there are.
+    /// Does nothing when the filter is disabled.
+    pub fn apply(&self, assembly: &mut Assembly) -> usize {
+        if !self.enabled {
+            return assembly.parts.iter().filter(|part| part.hardware).count();
+        }
+
+        for part in &mut assembly.parts {
+            part.hardware = self.classify(part).is_some();
+        }
+        assembly.parts.iter().filter(|part| part.hardware).count()
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use bevy::math::{DMat3, DQuat, DVec3};
+use bevy::prelude::Component;
+
+use crate::interference::BodyRef;
+use crate::mrr::{Assembly, Body, Pose};
+
+/// How far apart matching vertices of two copies of a shape can be, in assembly units.
+const TOLERANCE: f64 = 1e-3;
+
+/// A body drawn as a copy of a shape shared with identical bodies.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BodyInstance {
+    /// Index into [`MeshInstances::shapes`]
+    pub shape: usize,
+    /// Moves the shape's vertices onto the body's, since bodies are stored in assembly coordinates
+    pub offset: Pose,
+}
+
+/// Bodies of an assembly grouped by shape, so repeated parts such as wheels, bearings and gussets can share a mesh.
+#[derive(Debug, Clone, Default)]
+pub struct MeshInstances {
+    /// The first body with each shape
+    pub shapes: Vec<BodyRef>,
+    /// The instance each body is drawn as, by part and then by body
+    pub bodies: Vec<Vec<BodyInstance>>,
+}
+
+/// A shape found so far, with the vertices used to line copies of it up.
+struct Shape {
+    vertices: Vec<DVec3>,
+    anchors: Option<[usize; 3]>,
+}
+
+/// Hashes what stays the same when a body is moved: its triangles and how far each vertex is from the middle.
+fn shape_hash(body: &Body, vertices: &[DVec3]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    body.indicies.hash(&mut hasher);
+    let center = vertices.iter().sum::<DVec3>() / vertices.len().max(1) as f64;
+    for vertex in vertices {
+        ((*vertex - center).length() / TOLERANCE).round().to_bits().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Picks three vertices spread across the shape, which pin down where a copy of it is. Returns `None` for shapes
+/// too thin to tell how they're turned.
+fn anchors(vertices: &[DVec3]) -> Option<[usize; 3]> {
+    let first = *vertices.first()?;
+    let farthest = |distance: &dyn Fn(DVec3) -> f64| (0..vertices.len())
+        .max_by(|&a, &b| distance(vertices[a]).total_cmp(&distance(vertices[b])))
+        .unwrap();
+
+    let second = farthest(&|vertex| vertex.distance_squared(first));
+    let edge = vertices[second] - first;
+    let third = farthest(&|vertex| edge.cross(vertex - first).length_squared());
+    (edge.cross(vertices[third] - first).length() > TOLERANCE * TOLERANCE).then_some([0, second, third])
+}
+
+fn frame(vertices: &[DVec3], [a, b, c]: [usize; 3]) -> DMat3 {
+    let x = (vertices[b] - vertices[a]).normalize();
+    let z = x.cross(vertices[c] - vertices[a]).normalize();
+    DMat3::from_cols(x, z.cross(x), z)
+}
+
+/// Returns the pose that moves `shape` onto `vertices`, if they are the same shape.
+fn placement(shape: &Shape, vertices: &[DVec3]) -> Option<Pose> {
+    let anchors = shape.anchors?;
+    if shape.vertices.len() != vertices.len() {
+        return None;
+    }
+
+    let rotation = DQuat::from_mat3(&(frame(vertices, anchors) * frame(&shape.vertices, anchors).transpose())).normalize();
+    let offset = Pose(vertices[anchors[0]] - rotation * shape.vertices[anchors[0]], rotation);
+    shape.vertices.iter()
+        .zip(vertices)
+        .all(|(&shape_vertex, &vertex)| offset.transform_point(shape_vertex).distance(vertex) <= TOLERANCE)
+        .then_some(offset)
+}
+
+impl MeshInstances {
+    /// Finds bodies that are moved copies of earlier ones, with the same triangles in the same order,
+    /// as CAD programs export each instance of a part.
+    pub fn new(assembly: &Assembly) -> Self {
+        let mut instances = Self::default();
+        let mut shapes: Vec<Shape> = vec![];
+        let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+
+        for (part, part_data) in assembly.parts.iter().enumerate() {
+            let mut bodies = vec![];
+            for (body, body_data) in part_data.bodies.iter().enumerate() {
+                let vertices: Vec<DVec3> = (0..body_data.vertex_count()).map(|i| body_data.vertex(i).as_dvec3()).collect();
+                let candidates = by_hash.entry(shape_hash(body_data, &vertices)).or_default();
+                let found = candidates.iter().find_map(|&shape| {
+                    let first = instances.shapes[shape];
+                    let same_triangles = assembly.parts[first.part].bodies[first.body].indicies == body_data.indicies;
+                    same_triangles.then(|| placement(&shapes[shape], &vertices)).flatten()
+                        .map(|offset| BodyInstance { shape, offset })
+                });
+
+                bodies.push(found.unwrap_or_else(|| {
+                    let shape = shapes.len();
+                    candidates.push(shape);
+                    instances.shapes.push(BodyRef { part, body });
+                    shapes.push(Shape { anchors: anchors(&vertices), vertices });
+                    BodyInstance { shape, offset: Pose::IDENTITY }
+                }));
+            }
+            instances.bodies.push(bodies);
+        }
+
+        instances
+    }
+
+    /// Returns the body each shape is drawn with.
+    pub fn shape_body<'a>(&self, assembly: &'a Assembly, shape: usize) -> &'a Body {
+        let body = self.shapes[shape];
+        &assembly.parts[body.part].bodies[body.body]
+    }
+}
@@ -0,0 +1,283 @@
+use std::f64::consts::PI;
+
+use anyhow::{Result, bail};
+use bevy::math::Vec3;
+
+use crate::kinematics::KinematicTree;
+use crate::mrr::{Assembly, Body, JointType, Pose};
+
+/// Most joint positions a sweep will check, so a large grid doesn't lock up the app.
+pub const MAX_SAMPLES: usize = 100_000;
+
+/// A body of the assembly, by the index of its part and its index within the part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BodyRef {
+    pub part: usize,
+    pub body: usize,
+}
+
+/// Two bodies that intersect somewhere in the range of motion.
+#[derive(Debug, Clone)]
+pub struct Interference {
+    pub bodies: [BodyRef; 2],
+    /// Positions of the swept joints, in the order they were given, everywhere the bodies intersect
+    pub positions: Vec<Vec<f64>>,
+}
+
+impl Interference {
+    /// Describes the bodies and the range of each joint they intersect over, such as
+    /// "Arm intersects Bumper with Shoulder from 1.20 to 1.57 rad".
+    pub fn describe(&self, assembly: &Assembly, joints: &[usize]) -> String {
+        let name = |body: BodyRef| {
+            let part = &assembly.parts[body.part];
+            if part.bodies.len() > 1 {
+                format!("{} (body {})", part.name, body.body + 1)
+            } else {
+                part.name.clone()
+            }
+        };
+
+        let ranges: Vec<String> = joints.iter()
+            .enumerate()
+            .map(|(i, &joint)| {
+                let (lower, upper) = self.positions.iter()
+                    .map(|positions| positions[i])
+                    .fold((f64::MAX, f64::MIN), |(lower, upper), position| (lower.min(position), upper.max(position)));
+                let joint = &assembly.joints[joint];
+                let unit = if joint.joint_type == JointType::Slider { "m" } else { "rad" };
+                if lower == upper {
+                    format!("{} at {lower:.2} {unit}", joint.name)
+                } else {
+                    format!("{} from {lower:.2} to {upper:.2} {unit}", joint.name)
+                }
+            })
+            .collect();
+
+        format!("{} intersects {} with {}", name(self.bodies[0]), name(self.bodies[1]), ranges.join(", "))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SweepOptions {
+    /// Positions checked along the range of each joint, including both ends
+    pub steps: usize,
+    /// Also report bodies joined to each other that already intersect at the starting positions,
+    /// which are usually touching where they are joined
+    pub include_adjacent: bool,
+}
+
+impl Default for SweepOptions {
+    fn default() -> Self {
+        Self {
+            steps: 10,
+            include_adjacent: false,
+        }
+    }
+}
+
+/// Returns the range a joint is swept through: its limits, or a full turn for revolute joints without any.
+pub fn sweep_range(assembly: &Assembly, joint: usize) -> Result<(f64, f64)> {
+    let joint = &assembly.joints[joint];
+    match (joint.joint_type, joint.limits) {
+        (JointType::Rigid, _) => bail!("{} is rigid and can't be swept", joint.name),
+        (_, Some(limits)) => Ok(limits),
+        (JointType::Revolute, None) => Ok((-PI, PI)),
+        (JointType::Slider, None) => bail!("{} has no limits, so its range is unknown", joint.name),
+    }
+}
+
+/// A body in assembly units with its bounding box, moved as a whole for each sample.
+struct SweptBody {
+    body: BodyRef,
+    link: usize,
+    triangles: Vec<[Vec3; 3]>,
+    min: Vec3,
+    max: Vec3,
+}
+
+impl SweptBody {
+    fn new(body_ref: BodyRef, link: usize, body: &Body) -> Self {
+        let triangles: Vec<[Vec3; 3]> = body.indicies.chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|i| body.vertex(triangle[i] as usize)))
+            .collect();
+        let (min, max) = bounds(triangles.iter().flatten().copied());
+        Self { body: body_ref, link, triangles, min, max }
+    }
+
+    /// Returns the corners of the bounding box moved by `pose`.
+    fn moved_bounds(&self, pose: &Pose) -> (Vec3, Vec3) {
+        let corners = (0..8).map(|i| {
+            let corner = Vec3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            );
+            pose.transform_point(corner.as_dvec3()).as_vec3()
+        });
+        bounds(corners)
+    }
+
+    /// Returns the triangles moved by `pose` that overlap the box from `min` to `max`.
+    fn triangles_in(&self, pose: &Pose, min: Vec3, max: Vec3) -> Vec<[Vec3; 3]> {
+        self.triangles.iter()
+            .map(|triangle| triangle.map(|vertex| pose.transform_point(vertex.as_dvec3()).as_vec3()))
+            .filter(|triangle| {
+                let (triangle_min, triangle_max) = bounds(triangle.iter().copied());
+                overlaps((triangle_min, triangle_max), (min, max))
+            })
+            .collect()
+    }
+}
+
+fn bounds(points: impl Iterator<Item = Vec3>) -> (Vec3, Vec3) {
+    points.fold((Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)), |(min, max), point| (min.min(point), max.max(point)))
+}
+
+fn overlaps(a: (Vec3, Vec3), b: (Vec3, Vec3)) -> bool {
+    a.0.cmple(b.1).all() && b.0.cmple(a.1).all()
+}
+
+/// Returns whether the segment from `start` to `end` crosses the triangle.
+fn segment_hits_triangle(start: Vec3, end: Vec3, [a, b, c]: &[Vec3; 3]) -> bool {
+    let direction = end - start;
+    let edge1 = *b - *a;
+    let edge2 = *c - *a;
+    let p = direction.cross(edge2);
+    let determinant = edge1.dot(p);
+    if determinant.abs() < 1e-12 {
+        return false;
+    }
+
+    let inverse = 1. / determinant;
+    let t = start - *a;
+    let u = t.dot(p) * inverse;
+    if !(0. ..=1.).contains(&u) {
+        return false;
+    }
+    let q = t.cross(edge1);
+    let v = direction.dot(q) * inverse;
+    if v < 0. || u + v > 1. {
+        return false;
+    }
+    (0. ..=1.).contains(&(edge2.dot(q) * inverse))
+}
+
+/// Triangles intersect when an edge of one crosses the other. Triangles lying in the same plane are not counted.
+fn triangles_intersect(a: &[Vec3; 3], b: &[Vec3; 3]) -> bool {
+    let crosses = |edges: &[Vec3; 3], triangle: &[Vec3; 3]| {
+        (0..3).any(|i| segment_hits_triangle(edges[i], edges[(i + 1) % 3], triangle))
+    };
+    crosses(a, b) || crosses(b, a)
+}
+
+/// Returns whether two bodies intersect with their links at `link_poses`.
+fn intersect(a: &SweptBody, b: &SweptBody, link_poses: &[Pose]) -> bool {
+    let (pose_a, pose_b) = (&link_poses[a.link], &link_poses[b.link]);
+    let (bounds_a, bounds_b) = (a.moved_bounds(pose_a), b.moved_bounds(pose_b));
+    if !overlaps(bounds_a, bounds_b) {
+        return false;
+    }
+
+    let triangles_a = a.triangles_in(pose_a, bounds_b.0, bounds_b.1);
+    let triangles_b = b.triangles_in(pose_b, bounds_a.0, bounds_a.1);
+    triangles_a.iter().any(|triangle_a| triangles_b.iter().any(|triangle_b| triangles_intersect(triangle_a, triangle_b)))
+}
+
+/// The bodies of two parts, for checking whether the parts touch as their links move.
+pub struct PartContact {
+    bodies: [Vec<SweptBody>; 2],
+}
+
+impl PartContact {
+    pub fn new(assembly: &Assembly, tree: &KinematicTree, parts: [usize; 2]) -> Self {
+        let bodies = parts.map(|part| assembly.parts[part].bodies.iter()
+            .enumerate()
+            .map(|(body, data)| SweptBody::new(BodyRef { part, body }, tree.part_links[part], data))
+            .collect());
+        Self { bodies }
+    }
+
+    /// Returns whether a body of one part intersects a body of the other with their links at `link_poses`.
+    pub fn touching(&self, link_poses: &[Pose]) -> bool {
+        self.bodies[0].iter().any(|a| self.bodies[1].iter().any(|b| intersect(a, b, link_poses)))
+    }
+}
+
+/// Moves `joints` through their ranges on a grid of `options.steps` positions each, with every other joint at
+/// `positions`, and returns the bodies of different links that intersect. Hardware is left out.
+pub fn sweep(assembly: &Assembly, joints: &[usize], positions: &[f64], options: &SweepOptions) -> Result<Vec<Interference>> {
+    if joints.is_empty() {
+        bail!("No joints to sweep")
+    }
+    let steps = options.steps.max(2);
+    let ranges = joints.iter()
+        .map(|&joint| sweep_range(assembly, joint))
+        .collect::<Result<Vec<_>>>()?;
+    let sample_count = steps.checked_pow(joints.len() as u32).filter(|&count| count <= MAX_SAMPLES);
+    let Some(sample_count) = sample_count else {
+        bail!("Sweeping {} joints with {steps} steps each checks more than {MAX_SAMPLES} positions", joints.len())
+    };
+
+    let tree = KinematicTree::new(assembly);
+    let bodies: Vec<SweptBody> = assembly.parts.iter()
+        .enumerate()
+        .filter(|(_, part)| !part.hardware)
+        .flat_map(|(part, part_data)| part_data.bodies.iter()
+            .enumerate()
+            .map(move |(body, data)| (BodyRef { part, body }, data)))
+        .map(|(body_ref, body)| SweptBody::new(body_ref, tree.part_links[body_ref.part], body))
+        .collect();
+
+    let mut sample_positions = positions.to_vec();
+    sample_positions.resize(assembly.joints.len(), 0.);
+
+    // Bodies joined to each other often touch where they are joined, such as a shaft in a bearing,
+    // so skip the ones that already intersect at the starting positions
+    let adjacent = |a: usize, b: usize| {
+        tree.links[a].parent.is_some_and(|(parent, _)| parent == b) || tree.links[b].parent.is_some_and(|(parent, _)| parent == a)
+    };
+    let start_poses = tree.link_poses(assembly, &sample_positions);
+    let mut pairs = vec![];
+    for i in 0..bodies.len() {
+        for j in i + 1..bodies.len() {
+            let (a, b) = (&bodies[i], &bodies[j]);
+            if a.link == b.link {
+                continue;
+            }
+            if options.include_adjacent || !adjacent(a.link, b.link) || !intersect(a, b, &start_poses) {
+                pairs.push((i, j));
+            }
+        }
+    }
+
+    let mut interferences: Vec<Interference> = vec![];
+    for sample in 0..sample_count {
+        let mut index = sample;
+        let swept: Vec<f64> = ranges.iter()
+            .map(|&(lower, upper)| {
+                let step = index % steps;
+                index /= steps;
+                lower + (upper - lower) * step as f64 / (steps - 1) as f64
+            })
+            .collect();
+        for (&joint, &position) in joints.iter().zip(&swept) {
+            sample_positions[joint] = position;
+        }
+
+        let link_poses = tree.link_poses(assembly, &sample_positions);
+        for &(i, j) in &pairs {
+            let (a, b) = (&bodies[i], &bodies[j]);
+            if !intersect(a, b, &link_poses) {
+                continue;
+            }
+
+            let pair = [a.body, b.body];
+            match interferences.iter_mut().find(|interference| interference.bodies == pair) {
+                Some(interference) => interference.positions.push(swept.clone()),
+                None => interferences.push(Interference { bodies: pair, positions: vec![swept.clone()] }),
+            }
+        }
+    }
+
+    Ok(interferences)
+}
@@ -0,0 +1,180 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use bevy::math::{DQuat, DVec3};
+
+use crate::mrr::{Assembly, Joint, JointType, Pose, METERS_PER_UNIT};
+
+/// A set of parts that move together, such as a rigid group.
+#[derive(Debug, Clone)]
+pub struct Link {
+    /// Indices into [`Assembly::parts`]
+    pub parts: Vec<usize>,
+    /// Index of the parent link and the joint connecting this link to it. `None` for roots.
+    pub parent: Option<(usize, usize)>,
+}
+
+/// The parts of an assembly grouped into links, connected by joints into a tree.
+#[derive(Debug, Clone, Default)]
+pub struct KinematicTree {
+    /// Links ordered so every parent comes before its children
+    pub links: Vec<Link>,
+    /// Index into [`KinematicTree::links`] of every part
+    pub part_links: Vec<usize>,
+    /// Joints that don't fit in a tree, such as ones closing a loop
+    pub warnings: Vec<String>,
+}
+
+impl KinematicTree {
+    /// Groups the parts of `assembly` by their first rigid group and connects the groups through
+    /// the joints the parts share. The link containing the first part is the root.
+    pub fn new(assembly: &Assembly) -> Self {
+        let mut groups: BTreeMap<Option<u32>, Vec<usize>> = BTreeMap::new();
+        let mut ungrouped = vec![];
+        for (index, part) in assembly.parts.iter().enumerate() {
+            match part.rigid_group_references.first() {
+                Some(&group) => groups.entry(Some(group)).or_default().push(index),
+                None => ungrouped.push(vec![index]),
+            }
+        }
+
+        // Put the group of the first part first so it becomes the root
+        let mut groups: Vec<Vec<usize>> = groups.into_values().chain(ungrouped).collect();
+        if let Some(first) = groups.iter().position(|parts| parts.contains(&0)) {
+            groups.swap(0, first);
+        }
+
+        let mut group_of_part = vec![0; assembly.parts.len()];
+        for (group, parts) in groups.iter().enumerate() {
+            for &part in parts {
+                group_of_part[part] = group;
+            }
+        }
+
+        let mut warnings = vec![];
+        let mut connections = vec![vec![]; groups.len()];
+        for (joint_index, joint) in assembly.joints.iter().enumerate() {
+            let mut joint_groups: Vec<usize> = assembly.parts.iter()
+                .enumerate()
+                .filter(|(_, part)| part.joint_references.contains(&(joint_index as u32)))
+                .map(|(part, _)| group_of_part[part])
+                .collect();
+            joint_groups.sort();
+            joint_groups.dedup();
+
+            match joint_groups[..] {
+                [a, b] => {
+                    connections[a].push((b, joint_index));
+                    connections[b].push((a, joint_index));
+                }
+                [_] if joint.joint_type == JointType::Rigid => (),
+                _ => warnings.push(format!("{} connects {} rigid groups instead of 2", joint.name, joint_groups.len())),
+            }
+        }
+
+        let mut links: Vec<Link> = vec![];
+        let mut link_of_group = vec![None; groups.len()];
+        let mut queue = VecDeque::new();
+        for root in 0..groups.len() {
+            if link_of_group[root].is_some() {
+                continue;
+            }
+
+            link_of_group[root] = Some(links.len());
+            links.push(Link { parts: groups[root].clone(), parent: None });
+            queue.push_back(root);
+
+            while let Some(group) = queue.pop_front() {
+                let link = link_of_group[group].unwrap();
+                for &(child, joint) in &connections[group] {
+                    if links[link].parent.is_some_and(|(_, parent_joint)| parent_joint == joint) {
+                        continue;
+                    }
+                    if link_of_group[child].is_some() {
+                        // Each joint in a loop is seen from both ends, only report it once
+                        if link_of_group[child].unwrap() > link {
+                            warnings.push(format!("{} closes a loop and is not part of the tree", assembly.joints[joint].name));
+                        }
+                        continue;
+                    }
+
+                    link_of_group[child] = Some(links.len());
+                    links.push(Link { parts: groups[child].clone(), parent: Some((link, joint)) });
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        let part_links = group_of_part.iter()
+            .map(|&group| link_of_group[group].unwrap())
+            .collect();
+
+        Self { links, part_links, warnings }
+    }
+
+    /// Returns the joint connecting `link` to its parent.
+    pub fn parent_joint(&self, link: usize) -> Option<usize> {
+        self.links[link].parent.map(|(_, joint)| joint)
+    }
+
+    /// Returns the frame of `link` with every joint at rest: the pose of the joint to its parent,
+    /// or the origin for roots.
+    pub fn rest_frame(&self, assembly: &Assembly, link: usize) -> Pose {
+        match self.parent_joint(link) {
+            Some(joint) => assembly.joints[joint].pose,
+            None => Pose::IDENTITY,
+        }
+    }
+
+    /// Returns the frame of `link` relative to the frame of its parent, with its joint at `position`.
+    pub fn local_frame(&self, assembly: &Assembly, link: usize, position: f64) -> Pose {
+        let Some((parent, joint)) = self.links[link].parent else {
+            return Pose::IDENTITY;
+        };
+
+        self.rest_frame(assembly, parent).inverse()
+            .then(&assembly.joints[joint].pose)
+            .then(&joint_motion(&assembly.joints[joint], position))
+    }
+
+    /// Returns the frame of every link with the joints at `positions`, which is indexed like
+    /// [`Assembly::joints`]. Missing positions are treated as 0.
+    pub fn frames(&self, assembly: &Assembly, positions: &[f64]) -> Vec<Pose> {
+        let mut frames: Vec<Pose> = Vec::with_capacity(self.links.len());
+        for (index, link) in self.links.iter().enumerate() {
+            let frame = match link.parent {
+                Some((parent, joint)) => {
+                    let position = positions.get(joint).copied().unwrap_or(0.);
+                    frames[parent].then(&self.local_frame(assembly, index, position))
+                }
+                None => Pose::IDENTITY,
+            };
+            frames.push(frame);
+        }
+        frames
+    }
+
+    /// Returns how far every link has moved from where it is in the assembly, with the joints at `positions`.
+    pub fn link_poses(&self, assembly: &Assembly, positions: &[f64]) -> Vec<Pose> {
+        self.frames(assembly, positions).iter()
+            .enumerate()
+            .map(|(link, frame)| frame.then(&self.rest_frame(assembly, link).inverse()))
+            .collect()
+    }
+
+    /// Returns how far every part has moved from where it is in the assembly, with the joints at `positions`.
+    pub fn part_poses(&self, assembly: &Assembly, positions: &[f64]) -> Vec<Pose> {
+        let link_poses = self.link_poses(assembly, positions);
+        self.part_links.iter()
+            .map(|&link| link_poses[link])
+            .collect()
+    }
+}
+
+/// Returns the motion of a joint at `position`, in the joint's frame and assembly units.
+pub fn joint_motion(joint: &Joint, position: f64) -> Pose {
+    match joint.joint_type {
+        JointType::Rigid => Pose::IDENTITY,
+        JointType::Revolute => Pose(DVec3::ZERO, DQuat::from_rotation_z(position)),
+        JointType::Slider => Pose(DVec3::Z * position / METERS_PER_UNIT, DQuat::IDENTITY),
+    }
+}
@@ -0,0 +1,37 @@
+//! Robot model formats, simulation and viewer behind the MechSim app.
+//!
+//! The `gui` feature, on by default, enables the viewer and editor UI.
+
+pub mod appearance;
+pub mod battery;
+pub mod deserialize;
+#[cfg(feature = "gui")]
+pub mod editor;
+pub mod electrical;
+pub mod gltf;
+pub mod halsim;
+pub mod hardware;
+pub mod instances;
+pub mod interference;
+pub mod kinematics;
+pub mod lod;
+pub mod log;
+pub mod materials;
+pub mod meshes;
+pub mod mjcf;
+pub mod motors;
+pub mod mrr;
+pub mod networktables;
+pub mod obj;
+pub mod paths;
+pub mod physics;
+pub mod primitives;
+pub mod sensors;
+pub mod serialize;
+pub mod sim;
+pub mod stl;
+#[cfg(feature = "gui")]
+pub mod ui;
+pub mod urdf;
+#[cfg(feature = "gui")]
+pub mod viewer;
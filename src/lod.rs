@@ -0,0 +1,378 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Add;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use bevy::math::{DMat3, DVec3};
+use bevy::prelude::Resource;
+
+use crate::deserialize::{Deserializer, impl_deserialize};
+use crate::mrr::Body;
+use crate::serialize::{Serializer, impl_serialize};
+
+/// A simplified version of a body, drawn once it is small on screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodLevel {
+    /// Fraction of the full detail triangles to keep
+    pub ratio: f32,
+    /// Drawn once the body covers less than this fraction of the view's height
+    pub screen_size: f32,
+}
+
+/// How heavy CAD meshes are simplified for bodies far from the camera.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct LodSettings {
+    pub enabled: bool,
+    /// Levels after full detail, from the most to the least detailed
+    pub levels: Vec<LodLevel>,
+    /// Bodies with fewer triangles than this are always drawn in full detail
+    pub min_triangles: usize,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            levels: vec![
+                LodLevel { ratio: 0.25, screen_size: 0.25 },
+                LodLevel { ratio: 0.05, screen_size: 0.08 },
+            ],
+            min_triangles: 1000,
+        }
+    }
+}
+
+impl LodSettings {
+    /// Returns the level to draw a body covering `screen_size` of the view's height at, where 0 is full detail.
+    pub fn level(&self, screen_size: f32) -> usize {
+        if !self.enabled {
+            return 0;
+        }
+        self.levels.iter().take_while(|level| screen_size < level.screen_size).count()
+    }
+}
+
+/// Hashes the geometry of a body, so identical bodies share their simplified versions.
+pub fn body_hash(body: &Body) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for value in &body.verticies {
+        value.to_bits().hash(&mut hasher);
+    }
+    body.indicies.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Sum of squared distances to a set of planes, as the upper triangle of a symmetric 4x4 matrix.
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(normal: DVec3, point: DVec3, weight: f64) -> Self {
+        let [a, b, c] = normal.to_array();
+        let d = -normal.dot(point);
+        Self([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d].map(|value| value * weight))
+    }
+
+    fn error(&self, point: DVec3) -> f64 {
+        let q = &self.0;
+        let DVec3 { x, y, z } = point;
+        q[0] * x * x + 2. * q[1] * x * y + 2. * q[2] * x * z + 2. * q[3] * x
+            + q[4] * y * y + 2. * q[5] * y * z + 2. * q[6] * y
+            + q[7] * z * z + 2. * q[8] * z
+            + q[9]
+    }
+
+    /// Returns the point with the least error, unless the planes don't meet at a single point.
+    fn minimum(&self) -> Option<DVec3> {
+        let q = &self.0;
+        let matrix = DMat3::from_cols_array(&[q[0], q[1], q[2], q[1], q[4], q[5], q[2], q[5], q[7]]);
+        let scale = q[0] + q[4] + q[7];
+        if matrix.determinant().abs() <= 1e-9 * scale * scale * scale {
+            return None;
+        }
+        Some(matrix.inverse() * -DVec3::new(q[3], q[6], q[8]))
+    }
+}
+
+impl Add for Quadric {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        for (value, other) in self.0.iter_mut().zip(other.0) {
+            *value += other;
+        }
+        self
+    }
+}
+
+/// Weight of the planes holding open edges in place, relative to the faces, so holes don't grow.
+const BOUNDARY_WEIGHT: f64 = 100.;
+
+/// Merging two vertices of an edge into one at `position`.
+struct Collapse {
+    cost: f64,
+    vertices: [usize; 2],
+    /// Versions of the vertices when the cost was worked out, to skip collapses made stale by others
+    versions: [u32; 2],
+    position: DVec3,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    // Reversed, so the heap pops the cheapest collapse first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+/// Merges vertices at the same position, since CAD tessellations split them along every face.
+fn weld(body: &Body) -> (Vec<DVec3>, Vec<[usize; 3]>) {
+    let vertices: Vec<DVec3> = (0..body.vertex_count()).map(|i| body.vertex(i).as_dvec3()).collect();
+    let (min, max) = vertices.iter().fold((DVec3::splat(f64::MAX), DVec3::splat(f64::MIN)), |(min, max), &vertex| (min.min(vertex), max.max(vertex)));
+    let tolerance = (min.distance(max) * 1e-6).max(1e-9);
+
+    let mut positions = vec![];
+    let mut welded = HashMap::new();
+    let indices: Vec<usize> = vertices.iter()
+        .map(|&vertex| {
+            let key = (vertex / tolerance).round().to_array().map(|value| value as i64);
+            *welded.entry(key).or_insert_with(|| {
+                positions.push(vertex);
+                positions.len() - 1
+            })
+        })
+        .collect();
+
+    let faces = body.indicies.chunks_exact(3)
+        .map(|triangle| [0, 1, 2].map(|i| indices[triangle[i] as usize]))
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+        .collect();
+    (positions, faces)
+}
+
+fn face_normal(positions: &[DVec3], [a, b, c]: [usize; 3]) -> DVec3 {
+    (positions[b] - positions[a]).cross(positions[c] - positions[a])
+}
+
+/// Simplifies `body` to about `ratio` of its triangles by collapsing the edges that change its shape the least,
+/// measured by the quadric error of the planes around each vertex.
+pub fn decimate(body: &Body, ratio: f32) -> Body {
+    let (mut positions, mut faces) = weld(body);
+    let target = (faces.len() as f64 * ratio.clamp(0., 1.) as f64).ceil() as usize;
+
+    let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    let mut vertex_faces = vec![vec![]; positions.len()];
+    let mut quadrics = vec![Quadric::default(); positions.len()];
+    for (index, &face) in faces.iter().enumerate() {
+        let normal = face_normal(&positions, face);
+        let area = normal.length() / 2.;
+        for i in 0..3 {
+            let (a, b) = (face[i], face[(i + 1) % 3]);
+            edge_faces.entry((a.min(b), a.max(b))).or_default().push(index);
+            vertex_faces[a].push(index);
+            if area > 0. {
+                quadrics[a] = quadrics[a] + Quadric::plane(normal / (2. * area), positions[a], area);
+            }
+        }
+    }
+    for (&(a, b), adjacent) in &edge_faces {
+        if adjacent.len() != 1 {
+            continue;
+        }
+        let edge = positions[b] - positions[a];
+        let normal = edge.cross(face_normal(&positions, faces[adjacent[0]])).normalize_or_zero();
+        let boundary = Quadric::plane(normal, positions[a], edge.length_squared() * BOUNDARY_WEIGHT);
+        quadrics[a] = quadrics[a] + boundary;
+        quadrics[b] = quadrics[b] + boundary;
+    }
+
+    let mut versions = vec![0u32; positions.len()];
+    let mut vertex_alive = vec![true; positions.len()];
+    let mut face_alive = vec![true; faces.len()];
+    let mut face_count = faces.len();
+
+    let collapse = |positions: &[DVec3], quadrics: &[Quadric], versions: &[u32], a: usize, b: usize| {
+        let quadric = quadrics[a] + quadrics[b];
+        let (start, end) = (positions[a], positions[b]);
+        let mut candidates = vec![start, end, (start + end) / 2.];
+        // Only trust the optimal point near the edge, since nearly flat areas put it far away
+        if let Some(minimum) = quadric.minimum().filter(|point| point.distance(start) <= 2. * start.distance(end)) {
+            candidates.push(minimum);
+        }
+        let (cost, position) = candidates.into_iter()
+            .map(|point| (quadric.error(point), point))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap();
+        Collapse { cost, vertices: [a, b], versions: [versions[a], versions[b]], position }
+    };
+
+    let mut heap: BinaryHeap<Collapse> = edge_faces.keys()
+        .map(|&(a, b)| collapse(&positions, &quadrics, &versions, a, b))
+        .collect();
+
+    // Returns whether moving `vertex` to `position` flips or squashes any of its faces that don't include `other`
+    let flips = |positions: &[DVec3], faces: &[[usize; 3]], face_alive: &[bool], vertex_faces: &[usize], vertex: usize, other: usize, position: DVec3| {
+        vertex_faces.iter()
+            .filter(|&&face| face_alive[face] && !faces[face].contains(&other))
+            .any(|&face| {
+                let before = face_normal(positions, faces[face]).normalize_or_zero();
+                let moved = faces[face].map(|v| if v == vertex { position } else { positions[v] });
+                let after = (moved[1] - moved[0]).cross(moved[2] - moved[0]).normalize_or_zero();
+                before.dot(after) < 0.2
+            })
+    };
+
+    while face_count > target {
+        let Some(Collapse { vertices: [a, b], versions: collapse_versions, position, .. }) = heap.pop() else {
+            break;
+        };
+        if !vertex_alive[a] || !vertex_alive[b] || collapse_versions != [versions[a], versions[b]] {
+            continue;
+        }
+        if flips(&positions, &faces, &face_alive, &vertex_faces[a], a, b, position)
+            || flips(&positions, &faces, &face_alive, &vertex_faces[b], b, a, position) {
+            continue;
+        }
+
+        positions[a] = position;
+        quadrics[a] = quadrics[a] + quadrics[b];
+        vertex_alive[b] = false;
+        for face in std::mem::take(&mut vertex_faces[b]) {
+            if !face_alive[face] {
+                continue;
+            }
+            if faces[face].contains(&a) {
+                face_alive[face] = false;
+                face_count -= 1;
+            } else {
+                for vertex in &mut faces[face] {
+                    if *vertex == b {
+                        *vertex = a;
+                    }
+                }
+                vertex_faces[a].push(face);
+            }
+        }
+        vertex_faces[a].retain(|&face| face_alive[face]);
+        versions[a] += 1;
+
+        let mut neighbors: Vec<usize> = vertex_faces[a].iter()
+            .flat_map(|&face| faces[face])
+            .filter(|&vertex| vertex != a)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        for neighbor in neighbors {
+            heap.push(collapse(&positions, &quadrics, &versions, a, neighbor));
+        }
+    }
+
+    let mut remap = vec![None; positions.len()];
+    let mut verticies = vec![];
+    let mut indicies = vec![];
+    for (face, _) in faces.iter().zip(&face_alive).filter(|(_, &alive)| alive) {
+        for &vertex in face {
+            let index = *remap[vertex].get_or_insert_with(|| {
+                verticies.extend(positions[vertex].as_vec3().to_array());
+                verticies.len() as i32 / 3 - 1
+            });
+            indicies.push(index);
+        }
+    }
+    Body::from_triangles(verticies, indicies)
+}
+
+const CACHE_SIG: &str = "MECHSIM-LOD-1";
+
+/// A body with `hash` simplified to `ratio`.
+#[derive(Debug, Clone)]
+struct CachedLod {
+    hash: u64,
+    ratio: f32,
+    body: Body,
+}
+
+impl_deserialize!(CachedLod { hash, ratio, body });
+impl_serialize!(CachedLod { hash, ratio, body });
+
+/// Simplified bodies saved next to the robot file, so they're only generated once.
+#[derive(Default)]
+pub struct LodCache {
+    path: Option<PathBuf>,
+    lods: HashMap<(u64, u32), Body>,
+    used: HashSet<(u64, u32)>,
+    changed: bool,
+}
+
+/// Returns where the cache for the robot at `assembly_path` is kept, as `robot.lod` next to `robot.mrr`.
+pub fn cache_path(assembly_path: &Path) -> Option<PathBuf> {
+    (!assembly_path.as_os_str().is_empty()).then(|| assembly_path.with_extension("lod"))
+}
+
+impl LodCache {
+    /// Opens the cache for the robot at `assembly_path`, starting empty if there isn't one yet or it can't be read.
+    pub fn open(assembly_path: &Path) -> Self {
+        let path = cache_path(assembly_path);
+        let lods = path.as_deref()
+            .filter(|path| path.exists())
+            .and_then(|path| Self::read(path).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|lod| ((lod.hash, lod.ratio.to_bits()), lod.body))
+            .collect();
+        Self { path, lods, ..Default::default() }
+    }
+
+    fn read(path: &Path) -> Result<Vec<CachedLod>> {
+        let mut deserializer = Deserializer::from_file(path)?;
+        if !deserializer.remaining().starts_with(CACHE_SIG.as_bytes()) {
+            bail!("{} is not a level of detail cache", path.display())
+        }
+        deserializer.skip(CACHE_SIG.len())?;
+        Ok(deserializer.read()?)
+    }
+
+    /// Returns `body` simplified to `ratio`, from the cache or by decimating it.
+    pub fn get(&mut self, body: &Body, hash: u64, ratio: f32) -> &Body {
+        let key = (hash, ratio.to_bits());
+        self.used.insert(key);
+        self.lods.entry(key).or_insert_with(|| {
+            self.changed = true;
+            decimate(body, ratio)
+        })
+    }
+
+    /// Writes the cache if anything was added to it, leaving out bodies that are no longer used.
+    pub fn save(&mut self) -> Result<()> {
+        let Some(path) = self.path.as_deref().filter(|_| self.changed) else {
+            return Ok(());
+        };
+
+        self.lods.retain(|key, _| self.used.contains(key));
+        let lods: Vec<CachedLod> = self.lods.iter()
+            .map(|(&(hash, ratio), body)| CachedLod { hash, ratio: f32::from_bits(ratio), body: body.clone() })
+            .collect();
+        let mut serializer = Serializer::new();
+        serializer.write_bytes(CACHE_SIG.as_bytes());
+        serializer.write(&lods);
+        serializer.to_file(path).with_context(|| format!("Failed to write {}", path.display()))?;
+        self.changed = false;
+        Ok(())
+    }
+}
@@ -1,9 +1,12 @@
 use std::{fs::File, io::Write, fmt};
 
 use bevy::prelude::*;
+#[cfg(feature = "gui")]
 use bevy_egui::egui::Color32;
 use chrono::prelude::*;
 
+use crate::paths;
+
 #[derive(Default, Debug, Clone, Copy)]
 pub enum LogMessageType {
     #[default]
@@ -24,6 +27,7 @@ impl fmt::Display for LogMessageType {
     }
 }
 
+#[cfg(feature = "gui")]
 impl LogMessageType {
     pub fn color(&self) -> Color32 {
         match self {
@@ -49,17 +53,17 @@ pub struct LogMessage {
 
 impl Default for LogMessages {
     fn default() -> Self {
-        let mut log_file_path = "C:\\Users\\Public\\MechSim\\log\\".to_owned();
-        log_file_path.push_str({
+        let log_dir = paths::get().logs();
+        let log_file_path = log_dir.join({
             let current_date = chrono::Local::now();
             format!("{}-{}-{}.txt", 
                 current_date.day(),
                 current_date.month(),
                 current_date.year()
-            ).as_str() 
+            )
         });
 
-        match std::fs::create_dir_all("C:\\Users\\Public\\MechSim\\log") {
+        match std::fs::create_dir_all(&log_dir) {
             Ok(()) => (),
             Err(e) => eprintln!("{e}")
         };
@@ -0,0 +1,222 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use bevy::prelude::Resource;
+use serde_json::{Value, json};
+
+use crate::mrr::Part;
+use crate::paths;
+
+/// How the coefficients of two touching materials are combined, using the same modes as PhysX. When the two
+/// materials use different modes, the one later in this list is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum CombineMode {
+    #[default]
+    Average,
+    Min,
+    Multiply,
+    Max,
+}
+
+impl CombineMode {
+    pub const ALL: [CombineMode; 4] = [CombineMode::Average, CombineMode::Min, CombineMode::Multiply, CombineMode::Max];
+
+    /// Name used in the library file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CombineMode::Average => "average",
+            CombineMode::Min => "min",
+            CombineMode::Multiply => "multiply",
+            CombineMode::Max => "max",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    pub fn combine(&self, a: f32, b: f32) -> f32 {
+        match self {
+            CombineMode::Average => (a + b) / 2.,
+            CombineMode::Min => a.min(b),
+            CombineMode::Multiply => a * b,
+            CombineMode::Max => a.max(b),
+        }
+    }
+}
+
+/// A named material with the friction and bounce it has against others.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhysicalMaterial {
+    pub name: String,
+    /// Words in CAD material names that mean this material, ignoring case
+    pub keywords: Vec<String>,
+    pub static_friction: f32,
+    pub dynamic_friction: f32,
+    /// 0 for no bounce, 1 for a perfectly elastic bounce
+    pub restitution: f32,
+    pub friction_combine: CombineMode,
+    pub restitution_combine: CombineMode,
+}
+
+impl PhysicalMaterial {
+    pub fn new(name: &str, keywords: &[&str], static_friction: f32, dynamic_friction: f32, restitution: f32) -> Self {
+        Self {
+            name: name.to_owned(),
+            keywords: keywords.iter().map(|&keyword| keyword.to_owned()).collect(),
+            static_friction,
+            dynamic_friction,
+            restitution,
+            friction_combine: CombineMode::default(),
+            restitution_combine: CombineMode::default(),
+        }
+    }
+
+    /// Returns whether a word of `cad_material` is one of the keywords.
+    pub fn matches(&self, cad_material: &str) -> bool {
+        let cad_material = cad_material.to_ascii_lowercase();
+        cad_material.split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|word| self.keywords.iter().any(|keyword| keyword.eq_ignore_ascii_case(word)))
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "keywords": self.keywords,
+            "static_friction": self.static_friction,
+            "dynamic_friction": self.dynamic_friction,
+            "restitution": self.restitution,
+            "friction_combine": self.friction_combine.name(),
+            "restitution_combine": self.restitution_combine.name(),
+        })
+    }
+
+    fn from_json(value: &Value) -> Result<Self> {
+        let name = value["name"].as_str().context("Material without a name")?;
+        let number = |key: &str| value[key].as_f64()
+            .map(|number| number as f32)
+            .with_context(|| format!("Material \"{name}\" has no {key}"));
+        let combine_mode = |key: &str| match value[key].as_str() {
+            Some(mode) => CombineMode::from_name(mode)
+                .with_context(|| format!("Material \"{name}\" has an unknown {key} \"{mode}\", expected average, min, multiply or max")),
+            None => Ok(CombineMode::default()),
+        };
+        let keywords = match value["keywords"].as_array() {
+            Some(keywords) => keywords.iter().filter_map(Value::as_str).map(str::to_owned).collect(),
+            None => vec![],
+        };
+
+        Ok(Self {
+            name: name.to_owned(),
+            keywords,
+            static_friction: number("static_friction")?,
+            dynamic_friction: number("dynamic_friction")?,
+            restitution: number("restitution")?,
+            friction_combine: combine_mode("friction_combine")?,
+            restitution_combine: combine_mode("restitution_combine")?,
+        })
+    }
+}
+
+/// The physical materials parts and the field can be made of, kept in a library file in the settings directory.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct MaterialLibrary {
+    pub materials: Vec<PhysicalMaterial>,
+    /// Material of the field floor
+    pub field: String,
+    /// Material of parts whose CAD material doesn't match any other
+    pub default: String,
+}
+
+impl Default for MaterialLibrary {
+    fn default() -> Self {
+        Self {
+            materials: vec![
+                PhysicalMaterial::new("Aluminum", &["aluminum", "aluminium"], 0.61, 0.47, 0.3),
+                PhysicalMaterial::new("Steel", &["steel", "stainless", "iron"], 0.74, 0.57, 0.3),
+                PhysicalMaterial::new("Polycarbonate", &["polycarbonate", "lexan"], 0.45, 0.35, 0.4),
+                PhysicalMaterial::new("Low Friction Plastic", &["delrin", "acetal", "nylon", "hdpe", "uhmw", "polyethylene", "ptfe", "teflon"], 0.25, 0.2, 0.3),
+                PhysicalMaterial::new("3D Printed Plastic", &["pla", "petg", "abs", "onyx", "asa"], 0.5, 0.4, 0.3),
+                PhysicalMaterial::new("Carbon Fiber", &["carbon"], 0.4, 0.3, 0.3),
+                PhysicalMaterial::new("Wood", &["wood", "plywood", "birch", "mdf"], 0.5, 0.4, 0.3),
+                PhysicalMaterial::new("Rubber", &["rubber", "neoprene", "silicone"], 1.1, 0.9, 0.6),
+                PhysicalMaterial::new("Wheel Tread", &["tread", "nitrile", "colson", "urethane", "polyurethane", "tpu"], 1.2, 1.0, 0.2),
+                PhysicalMaterial::new("Carpet", &["carpet"], 1.0, 0.85, 0.05),
+            ],
+            field: "Carpet".to_owned(),
+            default: "Aluminum".to_owned(),
+        }
+    }
+}
+
+impl MaterialLibrary {
+    /// Where the library is kept unless another file is opened.
+    pub fn default_path() -> PathBuf {
+        paths::get().settings().join("materials.json")
+    }
+
+    /// Reads the library at `path`, or the built in materials if there's no file there yet.
+    pub fn load_or_default(path: &Path) -> Result<Self> {
+        if path.exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(text)?;
+        let materials = value["materials"].as_array()
+            .context("No materials list")?
+            .iter()
+            .map(PhysicalMaterial::from_json)
+            .collect::<Result<Vec<_>>>()?;
+
+        let library = Self {
+            materials,
+            field: value["field"].as_str().context("No field material")?.to_owned(),
+            default: value["default"].as_str().context("No default material")?.to_owned(),
+        };
+        for name in [&library.field, &library.default] {
+            if library.get(name).is_none() {
+                bail!("Material \"{name}\" is used but not in the library")
+            }
+        }
+        Ok(library)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let value = json!({
+            "field": self.field,
+            "default": self.default,
+            "materials": self.materials.iter().map(PhysicalMaterial::to_json).collect::<Vec<_>>(),
+        });
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&value)?).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PhysicalMaterial> {
+        self.materials.iter().find(|material| material.name == name)
+    }
+
+    /// Returns the material a part's CAD material matches, if any.
+    pub fn cad_material(&self, part: &Part) -> Option<&PhysicalMaterial> {
+        let cad_material = part.material.as_deref()?;
+        self.materials.iter().find(|material| material.matches(cad_material))
+    }
+
+    /// Returns the material of `part`: the one picked for it, the one its CAD material matches, or the default.
+    pub fn part_material(&self, part: &Part) -> Option<&PhysicalMaterial> {
+        part.physical_material.as_deref()
+            .and_then(|name| self.get(name))
+            .or_else(|| self.cad_material(part))
+            .or_else(|| self.get(&self.default))
+    }
+}
@@ -0,0 +1,121 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+
+use crate::mrr::{Assembly, Body, Part, Pose, METERS_PER_UNIT};
+use crate::{obj, stl};
+
+/// Units a mesh file can be in. Mesh files don't say which units they use, so the user has to pick one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LengthUnit {
+    #[default]
+    Millimeters,
+    Centimeters,
+    Meters,
+    Inches,
+    Feet,
+}
+
+impl LengthUnit {
+    pub const ALL: [LengthUnit; 5] = [
+        LengthUnit::Millimeters,
+        LengthUnit::Centimeters,
+        LengthUnit::Meters,
+        LengthUnit::Inches,
+        LengthUnit::Feet,
+    ];
+
+    pub fn meters(self) -> f64 {
+        match self {
+            LengthUnit::Millimeters => 0.001,
+            LengthUnit::Centimeters => 0.01,
+            LengthUnit::Meters => 1.,
+            LengthUnit::Inches => 0.0254,
+            LengthUnit::Feet => 0.3048,
+        }
+    }
+}
+
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LengthUnit::Millimeters => "Millimeters",
+            LengthUnit::Centimeters => "Centimeters",
+            LengthUnit::Meters => "Meters",
+            LengthUnit::Inches => "Inches",
+            LengthUnit::Feet => "Feet",
+        })
+    }
+}
+
+/// Returns whether `path` is a mesh file that can be imported as parts.
+pub fn is_mesh_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(OsStr::to_str).map(str::to_ascii_lowercase).as_deref(),
+        Some("stl") | Some("obj")
+    )
+}
+
+/// Reads the objects of an STL or OBJ file as bodies with their names, in the units of the file.
+pub fn load_file(path: &Path) -> Result<Vec<(Option<String>, Body)>> {
+    match path.extension().and_then(OsStr::to_str).map(str::to_ascii_lowercase).as_deref() {
+        Some("stl") => Ok(vec![(None, stl::load(path)?)]),
+        Some("obj") => obj::load(path),
+        _ => bail!("{} is not an STL or OBJ file", path.display()),
+    }
+}
+
+/// Creates an assembly with a part for every mesh file in `paths` and every object in them.
+pub fn load(paths: &[PathBuf], unit: LengthUnit) -> Result<Assembly> {
+    let mut assembly = Assembly::default();
+    for path in paths {
+        add(&mut assembly, path, unit)?;
+    }
+    Ok(assembly)
+}
+
+/// Adds the meshes in the file at `path` to `assembly`. Each becomes its own part and rigid group,
+/// so joints can be added between them.
+pub fn add(assembly: &mut Assembly, path: &Path, unit: LengthUnit) -> Result<()> {
+    let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("Mesh");
+
+    let meshes = load_file(path)?;
+    if meshes.is_empty() {
+        bail!("{} has no faces", path.display())
+    }
+
+    let first_rigid_group = assembly.parts.iter()
+        .flat_map(|part| part.rigid_group_references.iter())
+        .max()
+        .map_or(0, |group| group + 1);
+    let mesh_count = meshes.len();
+
+    for (index, (name, mut body)) in meshes.into_iter().enumerate() {
+        body.transform(&Pose::IDENTITY, unit.meters() / METERS_PER_UNIT);
+
+        let name = match name {
+            Some(name) => name,
+            None if mesh_count == 1 => stem.to_owned(),
+            None => format!("{stem} {}", index + 1),
+        };
+        assembly.parts.push(Part {
+            name,
+            pose: Pose::IDENTITY,
+            joint_references: vec![],
+            rigid_group_references: vec![first_rigid_group + index as u32],
+            bodies: vec![body],
+            inertial: None,
+            hardware: false,
+            appearance: None,
+            material: None,
+            physical_material: None,
+        });
+    }
+
+    if assembly.file_path.as_os_str().is_empty() {
+        assembly.file_path = path.to_owned();
+    }
+    Ok(())
+}
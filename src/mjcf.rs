@@ -0,0 +1,631 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use bevy::math::{DMat3, DQuat, DVec3, Vec3};
+use roxmltree::{Document, Node};
+
+use crate::appearance::Appearance;
+use crate::mrr::{Actuator, Assembly, Body, Inertial, Joint, JointType, Part, Pose, METERS_PER_UNIT, Z_UP};
+use crate::{meshes, primitives};
+
+/// Rigid group of everything attached directly to the world.
+const WORLD_RIGID_GROUP: u32 = 0;
+
+/// Imports a MuJoCo MJCF model, such as the ones made by the Onshape MJCF exporter.
+pub fn load(path: &Path) -> Result<Assembly> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    parse(&text, path.parent().unwrap_or(Path::new("")))
+}
+
+/// Imports an MJCF model from `text`, resolving mesh files relative to `dir`.
+pub fn parse(text: &str, dir: &Path) -> Result<Assembly> {
+    let document = Document::parse(text)?;
+    let root = document.root_element();
+    if !root.has_tag_name("mujoco") {
+        bail!("Expected a <mujoco> root element, found <{}>", root.tag_name().name())
+    }
+
+    let mut importer = Importer::new(dir);
+    importer.import(root)?;
+    Ok(importer.assembly)
+}
+
+type Attributes = HashMap<String, String>;
+
+/// Attributes set by a `<default>` class, by element name.
+#[derive(Debug, Default, Clone)]
+struct DefaultClass(HashMap<String, Attributes>);
+
+struct MeshAsset {
+    path: PathBuf,
+    scale: DVec3,
+}
+
+struct Importer {
+    dir: PathBuf,
+    degrees: bool,
+    euler_seq: Vec<u8>,
+    mesh_dir: PathBuf,
+    defaults: HashMap<String, DefaultClass>,
+    mesh_assets: HashMap<String, MeshAsset>,
+    materials: HashMap<String, Appearance>,
+    loaded_meshes: HashMap<String, Vec<Body>>,
+    joint_indices: HashMap<String, usize>,
+    next_rigid_group: u32,
+    assembly: Assembly,
+}
+
+fn parse_numbers(text: &str) -> Result<Vec<f64>> {
+    text.split_whitespace()
+        .map(|number| number.parse::<f64>().with_context(|| format!("Invalid number \"{number}\"")))
+        .collect()
+}
+
+fn parse_fixed<const N: usize>(text: &str) -> Result<[f64; N]> {
+    let numbers = parse_numbers(text)?;
+    numbers.try_into().map_err(|numbers: Vec<f64>| anyhow::anyhow!("Expected {N} numbers, found {}", numbers.len()))
+}
+
+fn parse_range(text: &str) -> Result<(f64, f64)> {
+    let [lower, upper] = parse_fixed(text)?;
+    Ok((lower, upper))
+}
+
+fn to_units(pose: &Pose) -> Pose {
+    Pose(pose.0 / METERS_PER_UNIT, pose.1)
+}
+
+impl Importer {
+    fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_owned(),
+            degrees: true,
+            euler_seq: b"xyz".to_vec(),
+            mesh_dir: dir.to_owned(),
+            defaults: HashMap::new(),
+            mesh_assets: HashMap::new(),
+            materials: HashMap::new(),
+            loaded_meshes: HashMap::new(),
+            joint_indices: HashMap::new(),
+            next_rigid_group: WORLD_RIGID_GROUP + 1,
+            assembly: Assembly::default(),
+        }
+    }
+
+    fn import(&mut self, root: Node) -> Result<()> {
+        let sections = |name: &'static str| root.children().filter(move |node| node.has_tag_name(name));
+
+        for compiler in sections("compiler") {
+            self.read_compiler(compiler);
+        }
+        for default in sections("default") {
+            self.read_default(default, &DefaultClass::default(), "main");
+        }
+        for asset in sections("asset") {
+            self.read_assets(asset)?;
+        }
+        for include in sections("include") {
+            self.warn(format!("Included file {} was not imported", include.attribute("file").unwrap_or_default()));
+        }
+        for worldbody in sections("worldbody") {
+            self.read_worldbody(worldbody)?;
+        }
+        for actuator in sections("actuator") {
+            self.read_actuators(actuator)?;
+        }
+
+        Ok(())
+    }
+
+    fn warn(&mut self, warning: String) {
+        self.assembly.warnings.push(warning);
+    }
+
+    /// Looks up an attribute on `node`, falling back to the default class that applies to it.
+    fn attribute(&self, node: Node, class: &str, name: &str) -> Option<String> {
+        if let Some(value) = node.attribute(name) {
+            return Some(value.to_owned());
+        }
+
+        let class = node.attribute("class").unwrap_or(class);
+        self.defaults.get(class)?
+            .0.get(node.tag_name().name())?
+            .get(name)
+            .cloned()
+    }
+
+    fn angle(&self, angle: f64) -> f64 {
+        if self.degrees {
+            angle.to_radians()
+        } else {
+            angle
+        }
+    }
+
+    fn read_compiler(&mut self, node: Node) {
+        if let Some(angle) = node.attribute("angle") {
+            self.degrees = angle != "radian";
+        }
+        if let Some(euler_seq) = node.attribute("eulerseq") {
+            self.euler_seq = euler_seq.as_bytes().to_vec();
+        }
+        if let Some(dir) = node.attribute("meshdir").or_else(|| node.attribute("assetdir")) {
+            self.mesh_dir = self.dir.join(dir);
+        }
+    }
+
+    /// Reads a `<default>` and the ones nested in it. One without a class adds to the class it's nested in,
+    /// which is `main` at the top level.
+    fn read_default(&mut self, node: Node, parent: &DefaultClass, parent_name: &str) {
+        let mut class = parent.clone();
+        for element in node.children().filter(|child| child.is_element() && !child.has_tag_name("default")) {
+            let attributes = class.0.entry(element.tag_name().name().to_owned()).or_default();
+            for attribute in element.attributes() {
+                attributes.insert(attribute.name().to_owned(), attribute.value().to_owned());
+            }
+        }
+
+        let name = node.attribute("class").unwrap_or(parent_name).to_owned();
+        self.defaults.insert(name.clone(), class.clone());
+        for child in node.children().filter(|child| child.has_tag_name("default")) {
+            self.read_default(child, &class, &name);
+        }
+    }
+
+    fn read_assets(&mut self, node: Node) -> Result<()> {
+        for mesh in node.children().filter(|child| child.has_tag_name("mesh")) {
+            let Some(file) = self.attribute(mesh, "main", "file") else {
+                continue;
+            };
+            let name = match mesh.attribute("name") {
+                Some(name) => name.to_owned(),
+                // Unnamed meshes are referred to by their file name without the extension
+                None => Path::new(&file).file_stem().and_then(OsStr::to_str).unwrap_or_default().to_owned(),
+            };
+            let scale = match self.attribute(mesh, "main", "scale") {
+                Some(scale) => DVec3::from_array(parse_fixed(&scale).context("In mesh scale")?),
+                None => DVec3::ONE,
+            };
+
+            self.mesh_assets.insert(name, MeshAsset { path: self.mesh_dir.join(file), scale });
+        }
+
+        for material in node.children().filter(|child| child.has_tag_name("material")) {
+            let Some(name) = material.attribute("name") else {
+                continue;
+            };
+            let number = |attribute: &str, default: f64| -> Result<f64> {
+                match self.attribute(material, "main", attribute) {
+                    Some(value) => value.trim().parse().with_context(|| format!("Invalid {attribute} \"{value}\" in material \"{name}\"")),
+                    None => Ok(default),
+                }
+            };
+            let [red, green, blue, alpha] = match self.attribute(material, "main", "rgba") {
+                Some(rgba) => parse_fixed(&rgba).with_context(|| format!("In the rgba of material \"{name}\""))?,
+                None => [1.; 4],
+            };
+            let appearance = Appearance {
+                color: [red as f32, green as f32, blue as f32],
+                metallic: number("reflectance", 0.)? as f32,
+                roughness: 1. - number("shininess", 0.5)? as f32,
+                opacity: alpha as f32,
+            };
+            self.materials.insert(name.to_owned(), appearance);
+        }
+        Ok(())
+    }
+
+    /// Reads the position and orientation of a body, geom or inertial element.
+    fn frame(&self, node: Node, class: &str) -> Result<Pose> {
+        let attribute = |name: &str| self.attribute(node, class, name);
+
+        let position = match attribute("pos") {
+            Some(pos) => DVec3::from_array(parse_fixed(&pos).context("In pos")?),
+            None => DVec3::ZERO,
+        };
+
+        let rotation = if let Some(quat) = attribute("quat") {
+            let [w, x, y, z] = parse_fixed(&quat).context("In quat")?;
+            DQuat::from_xyzw(x, y, z, w).normalize()
+        } else if let Some(axis_angle) = attribute("axisangle") {
+            let [x, y, z, angle] = parse_fixed(&axis_angle).context("In axisangle")?;
+            DQuat::from_axis_angle(DVec3::new(x, y, z).normalize(), self.angle(angle))
+        } else if let Some(euler) = attribute("euler") {
+            let angles: [f64; 3] = parse_fixed(&euler).context("In euler")?;
+            self.euler_seq.iter().zip(angles).fold(DQuat::IDENTITY, |rotation, (&axis, angle)| {
+                let step = match axis.to_ascii_lowercase() {
+                    b'x' => DQuat::from_rotation_x(self.angle(angle)),
+                    b'y' => DQuat::from_rotation_y(self.angle(angle)),
+                    _ => DQuat::from_rotation_z(self.angle(angle)),
+                };
+                // Lowercase axes rotate with the frame, uppercase ones are fixed
+                if axis.is_ascii_lowercase() {
+                    rotation * step
+                } else {
+                    step * rotation
+                }
+            })
+        } else if let Some(xy_axes) = attribute("xyaxes") {
+            let [x0, x1, x2, y0, y1, y2] = parse_fixed(&xy_axes).context("In xyaxes")?;
+            let x = DVec3::new(x0, x1, x2).normalize();
+            let y = DVec3::new(y0, y1, y2).reject_from(x).normalize();
+            DQuat::from_mat3(&DMat3::from_cols(x, y, x.cross(y)))
+        } else if let Some(z_axis) = attribute("zaxis") {
+            DQuat::from_rotation_arc(DVec3::Z, DVec3::from_array(parse_fixed(&z_axis).context("In zaxis")?).normalize())
+        } else {
+            DQuat::IDENTITY
+        };
+
+        Ok(Pose(position, rotation))
+    }
+
+    fn read_worldbody(&mut self, node: Node) -> Result<()> {
+        // MJCF is Z-up, so the world is turned to stand the robot up in the assembly
+        let world_pose = Pose(DVec3::ZERO, Z_UP);
+        let mut world = Part {
+            name: "world".to_owned(),
+            pose: world_pose,
+            joint_references: vec![],
+            rigid_group_references: vec![WORLD_RIGID_GROUP],
+            bodies: vec![],
+            inertial: None,
+            hardware: false,
+            appearance: None,
+            material: None,
+            physical_material: None,
+        };
+        for geom in node.children().filter(|child| child.has_tag_name("geom")) {
+            world.bodies.extend(self.read_geom(geom, "main", &world_pose)?);
+        }
+
+        let world_part = if world.bodies.is_empty() {
+            None
+        } else {
+            self.assembly.parts.push(world);
+            Some(self.assembly.parts.len() - 1)
+        };
+
+        for body in node.children().filter(|child| child.has_tag_name("body")) {
+            self.read_body(body, "main", &world_pose, world_part, WORLD_RIGID_GROUP)?;
+        }
+        Ok(())
+    }
+
+    fn read_body(&mut self, node: Node, class: &str, parent_pose: &Pose, parent_part: Option<usize>, parent_rigid_group: u32) -> Result<()> {
+        let class = node.attribute("childclass").unwrap_or(class);
+        let name = node.attribute("name")
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("body{}", self.assembly.parts.len()));
+        let pose = parent_pose.then(&self.frame(node, class).with_context(|| format!("In body \"{name}\""))?);
+        let part = self.assembly.parts.len();
+
+        let mut joint_references = vec![];
+        let mut moves = false;
+        for joint in node.children().filter(|child| child.has_tag_name("joint") || child.has_tag_name("freejoint")) {
+            moves = true;
+            if let Some(index) = self.read_joint(joint, class, &pose).with_context(|| format!("In a joint of body \"{name}\""))? {
+                joint_references.push(index as u32);
+                if let Some(parent_part) = parent_part {
+                    self.assembly.parts[parent_part].joint_references.push(index as u32);
+                }
+            }
+        }
+
+        let rigid_group = if moves {
+            self.next_rigid_group += 1;
+            self.next_rigid_group - 1
+        } else {
+            parent_rigid_group
+        };
+
+        let mut bodies = vec![];
+        let mut appearance = None;
+        for geom in node.children().filter(|child| child.has_tag_name("geom")) {
+            bodies.extend(self.read_geom(geom, class, &pose).with_context(|| format!("In a geom of body \"{name}\""))?);
+            if appearance.is_none() {
+                appearance = self.geom_appearance(geom, class).with_context(|| format!("In a geom of body \"{name}\""))?;
+            }
+        }
+        let (appearance, material) = appearance.unzip();
+
+        let inertial = match node.children().find(|child| child.has_tag_name("inertial")) {
+            Some(inertial) => Some(self.read_inertial(inertial, &pose).with_context(|| format!("In the inertial of body \"{name}\""))?),
+            None => None,
+        };
+
+        self.assembly.parts.push(Part {
+            name,
+            pose: to_units(&pose),
+            joint_references,
+            rigid_group_references: vec![rigid_group],
+            bodies,
+            inertial,
+            hardware: false,
+            appearance,
+            material: material.flatten(),
+            physical_material: None,
+        });
+
+        for child in node.children().filter(|child| child.has_tag_name("body")) {
+            self.read_body(child, class, &pose, Some(part), rigid_group)?;
+        }
+        Ok(())
+    }
+
+    /// Adds the joint to the assembly, returning its index, or `None` if the joint type isn't supported.
+    fn read_joint(&mut self, node: Node, class: &str, body_pose: &Pose) -> Result<Option<usize>> {
+        let attribute = |name: &str| self.attribute(node, class, name);
+
+        let joint_type = match attribute("type").as_deref() {
+            _ if node.has_tag_name("freejoint") => return Ok(None),
+            None | Some("hinge") => JointType::Revolute,
+            Some("slide") => JointType::Slider,
+            Some("free") => return Ok(None),
+            Some(other) => {
+                self.warn(format!("{other} joint \"{}\" was not imported, only hinge and slide joints are supported", node.attribute("name").unwrap_or("unnamed")));
+                return Ok(None);
+            }
+        };
+
+        let position = match attribute("pos") {
+            Some(pos) => DVec3::from_array(parse_fixed(&pos).context("In pos")?),
+            None => DVec3::ZERO,
+        };
+        let axis = match attribute("axis") {
+            Some(axis) => DVec3::from_array(parse_fixed(&axis).context("In axis")?).normalize(),
+            None => DVec3::Z,
+        };
+
+        let range = attribute("range").map(|range| parse_range(&range)).transpose().context("In range")?;
+        let limited = match attribute("limited").as_deref() {
+            Some("true") => true,
+            Some("false") => false,
+            // "auto": limited if a range was given
+            _ => range.is_some_and(|(lower, upper)| lower != upper),
+        };
+        let limits = range.filter(|_| limited).map(|(lower, upper)| match joint_type {
+            JointType::Revolute => (self.angle(lower), self.angle(upper)),
+            _ => (lower, upper),
+        });
+
+        let index = self.assembly.joints.len();
+        let name = node.attribute("name").map(str::to_owned).unwrap_or_else(|| format!("joint{index}"));
+        let pose = body_pose.then(&Pose(position, DQuat::from_rotation_arc(DVec3::Z, axis)));
+
+        self.joint_indices.insert(name.clone(), index);
+        self.assembly.joints.push(Joint {
+            name,
+            joint_type,
+            pose: to_units(&pose),
+            limits,
+        });
+        Ok(Some(index))
+    }
+
+    /// Returns the appearance of a geom with its material name, if it has a material or color of its own.
+    fn geom_appearance(&self, node: Node, class: &str) -> Result<Option<(Appearance, Option<String>)>> {
+        if let Some(material) = self.attribute(node, class, "material") {
+            if let Some(appearance) = self.materials.get(&material) {
+                return Ok(Some((*appearance, Some(material))));
+            }
+        }
+        match self.attribute(node, class, "rgba") {
+            Some(rgba) => {
+                let [red, green, blue, alpha] = parse_fixed(&rgba).context("In rgba")?;
+                let appearance = Appearance { opacity: alpha as f32, ..Appearance::plastic([red as f32, green as f32, blue as f32]) };
+                Ok(Some((appearance, None)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn read_geom(&mut self, node: Node, class: &str, body_pose: &Pose) -> Result<Vec<Body>> {
+        let attribute = |name: &str| self.attribute(node, class, name);
+
+        let geom_type = attribute("type").unwrap_or_else(|| "sphere".to_owned());
+        let size = match attribute("size") {
+            Some(size) => parse_numbers(&size).context("In size")?,
+            None => vec![],
+        };
+        let size = |index: usize| size.get(index).copied().unwrap_or(0.) as f32;
+
+        let mut pose = self.frame(node, class)?;
+        let mut half_length = size(1);
+        if let Some(from_to) = attribute("fromto") {
+            let [x0, y0, z0, x1, y1, z1] = parse_fixed(&from_to).context("In fromto")?;
+            let (from, to) = (DVec3::new(x0, y0, z0), DVec3::new(x1, y1, z1));
+            pose = Pose((from + to) / 2., DQuat::from_rotation_arc(DVec3::Z, (to - from).normalize()));
+            half_length = (from.distance(to) / 2.) as f32;
+        }
+
+        let mut bodies = match geom_type.as_str() {
+            "sphere" => vec![primitives::ellipsoid(Vec3::splat(size(0)))],
+            "ellipsoid" => vec![primitives::ellipsoid(Vec3::new(size(0), size(1), size(2)))],
+            "box" => vec![primitives::cuboid(Vec3::new(size(0), size(1), size(2)))],
+            "cylinder" => vec![primitives::cylinder(size(0), half_length)],
+            "capsule" => primitives::capsule(size(0), half_length),
+            "mesh" => {
+                let mesh = attribute("mesh").context("Mesh geom without a mesh")?;
+                self.load_mesh(&mesh)
+            }
+            // Ground planes and height fields aren't part of the robot
+            "plane" | "hfield" => vec![],
+            other => {
+                self.warn(format!("Geom type {other} is not supported"));
+                vec![]
+            }
+        };
+
+        let pose = to_units(&body_pose.then(&pose));
+        for body in &mut bodies {
+            body.transform(&pose, 1. / METERS_PER_UNIT);
+        }
+        Ok(bodies)
+    }
+
+    /// Loads the bodies of a mesh asset, warning and returning none if it can't be loaded.
+    fn load_mesh(&mut self, name: &str) -> Vec<Body> {
+        if let Some(mesh) = self.loaded_meshes.get(name) {
+            return mesh.clone();
+        }
+
+        let mesh = match self.mesh_assets.get(name) {
+            None => Err(anyhow::anyhow!("Mesh \"{name}\" is not defined")),
+            Some(asset) => meshes::load_file(&asset.path).map(|objects| {
+                objects.into_iter()
+                    .map(|(_, mut body)| {
+                        body.scale(asset.scale.as_vec3());
+                        body
+                    })
+                    .collect()
+            }),
+        };
+
+        let mesh = match mesh {
+            Ok(mesh) => mesh,
+            Err(e) => {
+                self.warn(format!("{e:#}"));
+                vec![]
+            }
+        };
+        self.loaded_meshes.insert(name.to_owned(), mesh.clone());
+        mesh
+    }
+
+    fn read_inertial(&self, node: Node, body_pose: &Pose) -> Result<Inertial> {
+        let pose = body_pose.then(&self.frame(node, "main")?);
+        let mass = node.attribute("mass").context("Inertial without a mass")?;
+
+        let inertia = if let Some(diagonal) = node.attribute("diaginertia") {
+            DVec3::from_array(parse_fixed(diagonal).context("In diaginertia")?)
+        } else if let Some(full) = node.attribute("fullinertia") {
+            // Only the moments are kept, the products of inertia are dropped
+            let [xx, yy, zz, _, _, _] = parse_fixed(full).context("In fullinertia")?;
+            DVec3::new(xx, yy, zz)
+        } else {
+            DVec3::ZERO
+        };
+
+        Ok(Inertial {
+            mass: mass.parse().with_context(|| format!("Invalid mass \"{mass}\""))?,
+            center_of_mass: pose.0 / METERS_PER_UNIT,
+            inertia,
+        })
+    }
+
+    fn read_actuators(&mut self, node: Node) -> Result<()> {
+        for actuator in node.children().filter(Node::is_element) {
+            let attribute = |name: &str| self.attribute(actuator, "main", name);
+            let name = actuator.attribute("name")
+                .map(str::to_owned)
+                .unwrap_or_else(|| format!("actuator{}", self.assembly.actuators.len()));
+
+            let Some(joint) = attribute("joint") else {
+                self.warn(format!("Actuator \"{name}\" was not imported, only actuators on joints are supported"));
+                continue;
+            };
+            let Some(&joint) = self.joint_indices.get(&joint) else {
+                self.warn(format!("Actuator \"{name}\" drives joint \"{joint}\", which was not imported"));
+                continue;
+            };
+
+            let gear = match attribute("gear") {
+                Some(gear) => *parse_numbers(&gear).context("In gear")?.first().unwrap_or(&1.),
+                None => 1.,
+            };
+            let range = |range: &str, limited: &str| -> Result<Option<(f64, f64)>> {
+                let range = attribute(range).map(|range| parse_range(&range)).transpose()?;
+                Ok(range.filter(|_| attribute(limited).as_deref() != Some("false")))
+            };
+
+            let actuator = Actuator {
+                control_range: range("ctrlrange", "ctrllimited").with_context(|| format!("In actuator \"{name}\""))?,
+                force_range: range("forcerange", "forcelimited").with_context(|| format!("In actuator \"{name}\""))?,
+                name,
+                joint,
+                gear,
+                motor: None,
+            };
+            self.assembly.actuators.push(actuator);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mrr::UP;
+
+    fn parse_model(body: &str) -> Assembly {
+        parse(&format!("<mujoco>{body}</mujoco>"), Path::new("")).unwrap()
+    }
+
+    #[test]
+    fn stands_z_up_models_up() {
+        let assembly = parse_model(r#"
+            <worldbody>
+                <body name="arm" pos="0 0 1">
+                    <joint name="turret" axis="0 0 1"/>
+                    <inertial mass="2" pos="1 0 0" diaginertia="1 2 3"/>
+                    <geom type="sphere" size="0.1"/>
+                </body>
+            </worldbody>
+        "#);
+        let arm = &assembly.parts[0];
+        assert!(arm.pose.0.abs_diff_eq(UP / METERS_PER_UNIT, 1e-9));
+        assert!(assembly.joints[0].axis().abs_diff_eq(UP, 1e-9));
+        let inertial = arm.inertial.as_ref().unwrap();
+        assert!(inertial.center_of_mass.abs_diff_eq(DVec3::new(1., 1., 0.) / METERS_PER_UNIT, 1e-9));
+        let lowest = arm.bodies[0].verticies.chunks(3).map(|vertex| vertex[1]).fold(f32::INFINITY, f32::min);
+        assert!((lowest as f64 - 0.9 / METERS_PER_UNIT).abs() < 1e-3);
+    }
+
+    #[test]
+    fn rejects_malformed_models() {
+        assert!(parse("<robot/>", Path::new("")).is_err());
+        assert!(parse("<mujoco><worldbody>", Path::new("")).is_err());
+        assert!(parse("<mujoco><worldbody><body pos=\"0 1\"/></worldbody></mujoco>", Path::new("")).is_err());
+        assert!(parse("<mujoco><worldbody><body><inertial/></body></worldbody></mujoco>", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn warns_about_missing_meshes() {
+        let assembly = parse_model(r#"<worldbody><body><geom type="mesh" mesh="missing"/></body></worldbody>"#);
+        assert!(assembly.parts[0].bodies.is_empty());
+        assert_eq!(assembly.warnings.len(), 1);
+    }
+
+    #[test]
+    fn nested_defaults_without_a_class_add_to_their_parent() {
+        let assembly = parse_model(r#"
+            <default>
+                <joint type="slide"/>
+                <default><geom rgba="1 0 0 1"/></default>
+            </default>
+            <worldbody>
+                <body name="arm"><joint name="lift"/><geom type="box" size="1 1 1"/></body>
+            </worldbody>
+        "#);
+        assert_eq!(assembly.joints[0].joint_type, JointType::Slider);
+        assert_eq!(assembly.parts[0].appearance.map(|appearance| appearance.color), Some([1., 0., 0.]));
+    }
+
+    #[test]
+    fn loads_obj_meshes() {
+        let dir = std::env::temp_dir().join(format!("mechsim-mjcf-obj-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("arm.obj"), "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let model = r#"<mujoco><asset><mesh file="arm.obj" scale="2 2 2"/></asset>
+            <worldbody><body><geom type="mesh" mesh="arm"/></body></worldbody></mujoco>"#;
+        let assembly = parse(model, &dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let assembly = assembly.unwrap();
+        assert!(assembly.warnings.is_empty(), "{:?}", assembly.warnings);
+        let body = &assembly.parts[0].bodies[0];
+        assert!((body.vertex(1).x as f64 - 2. / METERS_PER_UNIT).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,90 @@
+use std::f64::consts::PI;
+
+/// Voltage motor datasheets are measured at.
+pub const NOMINAL_VOLTAGE: f64 = 12.;
+
+/// A DC motor, from its datasheet at [`NOMINAL_VOLTAGE`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotorModel {
+    pub name: &'static str,
+    /// N·m
+    pub stall_torque: f64,
+    /// RPM with no load
+    pub free_speed_rpm: f64,
+    /// Amps
+    pub stall_current: f64,
+    /// Amps drawn with no load
+    pub free_current: f64,
+}
+
+/// Motors commonly used in FRC.
+pub const MOTORS: [MotorModel; 9] = [
+    MotorModel { name: "NEO", stall_torque: 2.6, free_speed_rpm: 5676., stall_current: 105., free_current: 1.8 },
+    MotorModel { name: "NEO Vortex", stall_torque: 3.6, free_speed_rpm: 6784., stall_current: 211., free_current: 3.6 },
+    MotorModel { name: "NEO 550", stall_torque: 0.97, free_speed_rpm: 11000., stall_current: 100., free_current: 1.4 },
+    MotorModel { name: "Falcon 500", stall_torque: 4.69, free_speed_rpm: 6380., stall_current: 257., free_current: 1.5 },
+    MotorModel { name: "Kraken X60", stall_torque: 7.09, free_speed_rpm: 6000., stall_current: 366., free_current: 2. },
+    MotorModel { name: "CIM", stall_torque: 2.42, free_speed_rpm: 5310., stall_current: 133., free_current: 2.7 },
+    MotorModel { name: "Mini CIM", stall_torque: 1.41, free_speed_rpm: 5840., stall_current: 89., free_current: 3. },
+    MotorModel { name: "775pro", stall_torque: 0.71, free_speed_rpm: 18730., stall_current: 134., free_current: 0.7 },
+    MotorModel { name: "BAG", stall_torque: 0.43, free_speed_rpm: 13180., stall_current: 53., free_current: 1.8 },
+];
+
+impl MotorModel {
+    pub fn get(name: &str) -> Option<MotorModel> {
+        MOTORS.into_iter().find(|motor| motor.name == name)
+    }
+
+    /// Speed with no load in rad/s
+    pub fn free_speed(&self) -> f64 {
+        self.free_speed_rpm * 2. * PI / 60.
+    }
+
+    /// Resistance of the windings in ohms
+    pub fn resistance(&self) -> f64 {
+        NOMINAL_VOLTAGE / self.stall_current
+    }
+
+    /// Speed in rad/s for each volt of back EMF
+    pub fn kv(&self) -> f64 {
+        self.free_speed() / (NOMINAL_VOLTAGE - self.resistance() * self.free_current)
+    }
+
+    /// Torque in N·m for each amp
+    pub fn kt(&self) -> f64 {
+        self.stall_torque / self.stall_current
+    }
+}
+
+/// One or more identical motors geared together.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Motor {
+    pub model: MotorModel,
+    pub count: u32,
+}
+
+impl Motor {
+    pub fn new(model: MotorModel, count: u32) -> Self {
+        Self { model, count }
+    }
+
+    /// Current drawn by all the motors at `voltage` while turning at `speed` rad/s.
+    pub fn current(&self, voltage: f64, speed: f64) -> f64 {
+        (voltage - speed / self.model.kv()) / self.model.resistance() * self.count as f64
+    }
+
+    /// Torque of all the motors at `voltage` while turning at `speed` rad/s.
+    pub fn torque(&self, voltage: f64, speed: f64) -> f64 {
+        self.model.kt() * self.current(voltage, speed)
+    }
+
+    /// Torque lost for each rad/s of speed to back EMF. The torque at any speed is the torque at
+    /// zero speed minus this times the speed.
+    pub fn damping(&self) -> f64 {
+        self.model.kt() / (self.model.resistance() * self.model.kv()) * self.count as f64
+    }
+
+    pub fn stall_torque(&self) -> f64 {
+        self.model.stall_torque * self.count as f64
+    }
+}
@@ -1,21 +1,41 @@
 use bevy::render::mesh;
 use bevy::render::render_resource::PrimitiveTopology;
-use serde::Deserialize;
 use std::ffi::OsStr;
 use std::path::PathBuf;
-use std::{error::Error, path::Path};
-use std::fmt;
-use std::mem::size_of;
-use std::fmt::Display;
-use std::sync::Arc;
+use std::path::Path;
 
 use anyhow::{Result, bail, Context};
 use thiserror::Error;
 
 use bevy::{math::{DQuat, DVec3}, prelude::*};
-use bincode::{self, deserialize};
 
-#[derive(Debug, Deserialize)]
+use crate::appearance::Appearance;
+use crate::deserialize::{Deserialize, DeserializeError, Deserializer, impl_deserialize};
+use crate::serialize::{Serialize, Serializer, impl_serialize};
+use crate::gltf::{self, GlbOptions};
+use crate::instances::MeshInstances;
+use crate::meshes::{self, LengthUnit};
+use crate::motors::{Motor, MotorModel};
+use crate::{mjcf, urdf};
+
+const FORMAT_SIG: &str = "MRR (MechSim Robot Representation)";
+
+/// Length of one assembly unit in meters. Lengths from formats in other units are converted to this.
+pub const METERS_PER_UNIT: f64 = 0.0254;
+
+/// Size of one assembly unit in the viewer.
+pub const DISPLAY_SCALE: f32 = 1. / 6.;
+
+/// The assembly's up axis. Assemblies are Y-up, like the viewer and glTF.
+pub const UP: DVec3 = DVec3::Y;
+
+/// Acceleration of gravity in m/s², down the [`UP`] axis.
+pub const GRAVITY: f64 = 9.81;
+
+/// Rotation from a Z-up frame, such as MJCF's, URDF's and WPILib's, into the assembly's, keeping X where it is.
+pub const Z_UP: DQuat = DQuat::from_xyzw(-std::f64::consts::FRAC_1_SQRT_2, 0., 0., std::f64::consts::FRAC_1_SQRT_2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 pub enum JointType {
     Rigid,
@@ -23,34 +43,50 @@ pub enum JointType {
     Slider,
 }
 
-#[derive(Debug, Deserialize, Copy, Clone)]
-#[repr(C, packed)]
-struct NativeDVec3 {
-    x: f64,
-    y: f64,
-    z: f64,
-}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pose(pub DVec3, pub DQuat);
 
-#[derive(Debug, Deserialize, Copy, Clone)]
-#[repr(C, packed)]
-struct NativeDQuat {
-    x: f64,
-    y: f64,
-    z: f64,
-    w: f64,
-}
+impl Pose {
+    pub const IDENTITY: Self = Pose(DVec3::ZERO, DQuat::IDENTITY);
 
-#[derive(Debug, Deserialize)]
-#[repr(C, packed)]
-struct NativePose(NativeDVec3, NativeDQuat);
+    /// Returns `local`, given relative to this pose, in the frame this pose is relative to.
+    pub fn then(&self, local: &Pose) -> Pose {
+        Pose(self.transform_point(local.0), self.1 * local.1)
+    }
 
-#[derive(Debug)]
-pub struct Pose(pub DVec3, pub DQuat);
+    pub fn transform_point(&self, point: DVec3) -> DVec3 {
+        self.0 + self.1 * point
+    }
 
-#[derive(Debug)]
-pub struct Joint(pub JointType, pub Pose);
+    pub fn inverse(&self) -> Pose {
+        let rotation = self.1.inverse();
+        Pose(rotation * -self.0, rotation)
+    }
 
-#[derive(Debug)]
+    /// Returns this pose, or motion, with its axes changed from the assembly's to [`Z_UP`]'s.
+    pub fn to_z_up(&self) -> Pose {
+        let rotation = Z_UP.inverse();
+        Pose(rotation * self.0, rotation * self.1 * Z_UP)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Joint {
+    pub name: String,
+    pub joint_type: JointType,
+    /// Location of the joint, rotated so its Z axis is the axis of rotation or travel
+    pub pose: Pose,
+    /// Lower and upper limit of the joint position, in radians for revolute joints and meters for sliders
+    pub limits: Option<(f64, f64)>,
+}
+
+impl Joint {
+    pub fn axis(&self) -> DVec3 {
+        self.pose.1 * DVec3::Z
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Body {
     pub triangle_count: i32,
     pub verticies: Vec<f32>,
@@ -59,6 +95,100 @@ pub struct Body {
     pub uvs: Vec<f32>,
 }
 
+impl Body {
+    /// Creates a body from a triangle list, computing its normals.
+    pub fn from_triangles(verticies: Vec<f32>, indicies: Vec<i32>) -> Self {
+        let mut body = Body {
+            triangle_count: (indicies.len() / 3) as i32,
+            verticies,
+            indicies,
+            normals: vec![],
+            uvs: vec![],
+        };
+        body.compute_normals();
+        body
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.verticies.len() / 3
+    }
+
+    pub fn vertex(&self, index: usize) -> Vec3 {
+        Vec3::from_slice(&self.verticies[index * 3..index * 3 + 3])
+    }
+
+    /// Sets the normal of each vertex to the area weighted average of the faces around it.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vec3::ZERO; self.vertex_count()];
+        for triangle in self.indicies.chunks_exact(3) {
+            let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+            let face_normal = (self.vertex(b) - self.vertex(a)).cross(self.vertex(c) - self.vertex(a));
+            for index in [a, b, c] {
+                normals[index] += face_normal;
+            }
+        }
+
+        self.normals = normals.iter()
+            .flat_map(|normal| normal.normalize_or_zero().to_array())
+            .collect();
+    }
+
+    /// Moves the body by `pose`, after scaling it by `scale`.
+    pub fn transform(&mut self, pose: &Pose, scale: f64) {
+        for vertex in self.verticies.chunks_exact_mut(3) {
+            let point = pose.transform_point(DVec3::new(vertex[0] as f64, vertex[1] as f64, vertex[2] as f64) * scale);
+            vertex.copy_from_slice(&point.as_vec3().to_array());
+        }
+
+        let rotation = pose.1.as_f32();
+        for normal in self.normals.chunks_exact_mut(3) {
+            normal.copy_from_slice(&(rotation * Vec3::from_slice(normal)).to_array());
+        }
+    }
+
+    /// Scales the body along each axis, recomputing its normals.
+    pub fn scale(&mut self, scale: Vec3) {
+        if scale == Vec3::ONE {
+            return;
+        }
+
+        for vertex in self.verticies.chunks_exact_mut(3) {
+            vertex.copy_from_slice(&(Vec3::from_slice(vertex) * scale).to_array());
+        }
+        self.compute_normals();
+    }
+
+    pub fn to_mesh(&self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.verticies.chunks(3).map(|v| [v[0] * DISPLAY_SCALE, v[1] * DISPLAY_SCALE, v[2] * DISPLAY_SCALE]).collect::<Vec<[f32; 3]>>());
+        if self.normals.len() == self.verticies.len() {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals.chunks(3).map(|v| [v[0], v[1], v[2]]).collect::<Vec<[f32; 3]>>());
+        } else {
+            let mut body = self.clone();
+            body.compute_normals();
+            mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, body.normals.chunks(3).map(|v| [v[0], v[1], v[2]]).collect::<Vec<[f32; 3]>>());
+        }
+        if self.uvs.len() / 2 == self.vertex_count() {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs.chunks(2).map(|v| [v[0], v[1]]).collect::<Vec<[f32; 2]>>());
+        }
+        mesh.set_indices(Some(mesh::Indices::U32(self.indicies.iter().map(|&x| x as u32).collect())));
+
+        mesh
+    }
+}
+
+/// Mass properties of a part.
+#[derive(Debug, Clone, Copy)]
+pub struct Inertial {
+    /// Mass in kilograms
+    pub mass: f64,
+    /// Center of mass in assembly units, in the same frame as the part's bodies
+    pub center_of_mass: DVec3,
+    /// Principal moments of inertia in kg·m²
+    pub inertia: DVec3,
+}
+
 #[derive(Debug)]
 pub struct Part {
     pub name: String,
@@ -66,17 +196,166 @@ pub struct Part {
     pub joint_references: Vec<u32>,
     pub rigid_group_references: Vec<u32>,
     pub bodies: Vec<Body>,
+    pub inertial: Option<Inertial>,
+    /// Small part such as a fastener, hidden in the viewer and left out of collisions. Its mass
+    /// still counts toward its rigid group.
+    pub hardware: bool,
+    pub appearance: Option<Appearance>,
+    /// Name of the CAD material, such as "Aluminum - 6061"
+    pub material: Option<String>,
+    /// Name of the physical material picked for the part, instead of the one its CAD material matches
+    pub physical_material: Option<String>,
+}
+
+/// Something that drives a joint, such as a motor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Actuator {
+    pub name: String,
+    /// Index of the driven joint in [`Assembly::joints`]
+    pub joint: usize,
+    /// Ratio between the actuator's output and the joint's torque or force. For motors, the reduction from
+    /// the motor to the joint, in motor radians per radian or per meter of travel.
+    pub gear: f64,
+    pub control_range: Option<(f64, f64)>,
+    pub force_range: Option<(f64, f64)>,
+    /// Motors driven by a voltage as the control, instead of a control that is the actuator's force
+    pub motor: Option<Motor>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CouplingKind {
+    Gear,
+    #[default]
+    Belt,
+    Chain,
+}
+
+impl CouplingKind {
+    pub const ALL: [CouplingKind; 3] = [CouplingKind::Gear, CouplingKind::Belt, CouplingKind::Chain];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CouplingKind::Gear => "Gear",
+            CouplingKind::Belt => "Belt",
+            CouplingKind::Chain => "Chain",
+        }
+    }
+
+    /// Typical fraction of the power passed through.
+    pub fn efficiency(&self) -> f64 {
+        match self {
+            CouplingKind::Gear => 0.95,
+            CouplingKind::Belt => 0.98,
+            CouplingKind::Chain => 0.97,
+        }
+    }
+}
+
+/// Keeps two joints moving together, as a gear train, belt or chain does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coupling {
+    pub name: String,
+    pub kind: CouplingKind,
+    /// Indices in [`Assembly::joints`] of the driving joint and the joint that follows it
+    pub joints: [usize; 2],
+    /// How far the second joint moves for each radian or meter the first moves, negative if it turns the other way
+    pub ratio: f64,
+    /// How far the second joint can move without the first, in its radians or meters
+    pub backlash: f64,
+    /// Fraction of the power passed from one joint to the other, from 0 to 1
+    pub efficiency: f64,
+}
+
+impl Coupling {
+    pub fn new(name: &str, kind: CouplingKind, joints: [usize; 2]) -> Self {
+        Self {
+            name: name.to_owned(),
+            kind,
+            joints,
+            ratio: if kind == CouplingKind::Gear { -1. } else { 1. },
+            backlash: 0.,
+            efficiency: kind.efficiency(),
+        }
+    }
 }
 
 #[derive(Debug, Resource, Default)]
 pub struct Assembly {
     pub joints: Vec<Joint>,
     pub parts: Vec<Part>,
+    pub actuators: Vec<Actuator>,
+    pub couplings: Vec<Coupling>,
+    /// One mesh for each distinct shape of body, filled by [`Assembly::load_meshes`]
     pub meshes: Vec<Mesh>,
+    /// Which of `meshes` each body is drawn with
+    pub mesh_instances: MeshInstances,
     pub file_path: PathBuf,
+    /// Problems found while importing that didn't stop the import
+    pub warnings: Vec<String>,
 }
 
 impl Assembly {
+    /// Adds a joint between two parts, returning its index.
+    pub fn add_joint(&mut self, joint: Joint, parts: [usize; 2]) -> usize {
+        let index = self.joints.len();
+        self.joints.push(joint);
+        for part in parts {
+            self.parts[part].joint_references.push(index as u32);
+        }
+        index
+    }
+
+    /// Removes a joint, along with the references to it and the actuators and couplings driving it.
+    pub fn remove_joint(&mut self, index: usize) {
+        self.joints.remove(index);
+
+        let index = index as u32;
+        for part in &mut self.parts {
+            part.joint_references.retain(|&joint| joint != index);
+            for joint in &mut part.joint_references {
+                if *joint > index {
+                    *joint -= 1;
+                }
+            }
+        }
+
+        self.actuators.retain(|actuator| actuator.joint != index as usize);
+        for actuator in &mut self.actuators {
+            if actuator.joint > index as usize {
+                actuator.joint -= 1;
+            }
+        }
+
+        self.couplings.retain(|coupling| !coupling.joints.contains(&(index as usize)));
+        for joint in self.couplings.iter_mut().flat_map(|coupling| coupling.joints.iter_mut()) {
+            if *joint > index as usize {
+                *joint -= 1;
+            }
+        }
+    }
+
+    /// Returns the parts that reference the joint at `index`.
+    pub fn joint_parts(&self, index: usize) -> Vec<usize> {
+        self.parts.iter()
+            .enumerate()
+            .filter(|(_, part)| part.joint_references.contains(&(index as u32)))
+            .map(|(part, _)| part)
+            .collect()
+    }
+
+    /// Makes the joint at `index` connect `parts` instead of the parts it connected before. A joint with
+    /// one part attaches it to the world.
+    pub fn set_joint_parts(&mut self, index: usize, parts: &[usize]) {
+        for (part_index, part) in self.parts.iter_mut().enumerate() {
+            let references = part.joint_references.contains(&(index as u32));
+            if parts.contains(&part_index) && !references {
+                part.joint_references.push(index as u32);
+            } else if !parts.contains(&part_index) && references {
+                part.joint_references.retain(|&joint| joint != index as u32);
+            }
+        }
+    }
+
     pub fn body_count(&self) -> usize {
         self.parts.iter()
         .map(|parts| parts.bodies.iter().count())
@@ -85,8 +364,7 @@ impl Assembly {
 }
 
 pub struct MrrDeserializer {
-    input: Vec<u8>,
-    position: usize,
+    deserializer: Deserializer,
 }
 
 #[derive(Error, Debug)]
@@ -95,125 +373,585 @@ pub enum MrrError {
     FormatSigNotFound,
 }
 
-impl MrrDeserializer {
-    pub fn load(path: &Path) -> Result<Self> {
-        Ok(Self {
-            input: std::fs::read(path)?,
-            position: 0,
-        })
+#[derive(Error, Debug)]
+pub enum ValidationError {
+    #[error("Joint {joint} is not referenced by any part")]
+    UnusedJoint { joint: usize },
+    #[error("Part \"{part}\" references joint {joint}, but the assembly only has {joint_count} joints")]
+    InvalidJointReference { part: String, joint: u32, joint_count: usize },
+    #[error("Joint \"{joint}\" has a lower limit of {lower}, which is above its upper limit of {upper}")]
+    InvalidLimits { joint: String, lower: f64, upper: f64 },
+    #[error("Actuator \"{actuator}\" drives joint {joint}, but the assembly only has {joint_count} joints")]
+    InvalidActuatorJoint { actuator: String, joint: usize, joint_count: usize },
+    #[error("Coupling \"{coupling}\" links joint {joint}, but the assembly only has {joint_count} joints")]
+    InvalidCouplingJoint { coupling: String, joint: usize, joint_count: usize },
+    #[error("Coupling \"{coupling}\" links a joint to itself")]
+    SelfCoupling { coupling: String },
+    #[error("Part \"{part}\" has no bodies")]
+    NoBodies { part: String },
+    #[error("Body {body} of part \"{part}\" has {len} vertex components, which is not a multiple of 3")]
+    MalformedVertices { part: String, body: usize, len: usize },
+    #[error("Body {body} of part \"{part}\" has {len} indices, which is not a multiple of 3")]
+    MalformedIndices { part: String, body: usize, len: usize },
+    #[error("Body {body} of part \"{part}\" indexes vertex {index}, but it only has {vertex_count} vertices")]
+    IndexOutOfRange { part: String, body: usize, index: i32, vertex_count: usize },
+    #[error("Body {body} of part \"{part}\" claims {claimed} triangles, but its indices describe {actual}")]
+    TriangleCountMismatch { part: String, body: usize, claimed: i32, actual: usize },
+}
+
+impl ValidationError {
+    /// Whether the error is a reference to something that doesn't exist, which would panic once the assembly
+    /// is drawn or simulated.
+    pub fn is_out_of_range(&self) -> bool {
+        matches!(
+            self,
+            ValidationError::InvalidJointReference { .. }
+                | ValidationError::InvalidActuatorJoint { .. }
+                | ValidationError::InvalidCouplingJoint { .. }
+                | ValidationError::IndexOutOfRange { .. }
+        )
     }
+}
+
+/// Written after the parts, followed by the sections MechSim adds. Each section is a tag and its length in
+/// bytes, so sections a reader doesn't know are skipped, and holds records written with
+/// [`Serializer::write_records`], so fields can be added to the end of a record.
+const SECTIONS_SIG: &str = "MechSim sections";
+
+const JOINT_SECTION: &[u8; 4] = b"JNTS";
+const PART_SECTION: &[u8; 4] = b"PRTS";
+const ACTUATOR_SECTION: &[u8; 4] = b"ACTS";
+const COUPLING_SECTION: &[u8; 4] = b"CPLS";
+
+/// Joint details that CAD exporters don't write. MechSim stores them after the parts when saving,
+/// so files from exporters without them still load.
+struct JointInfo {
+    name: String,
+    limited: u8,
+    lower: f64,
+    upper: f64,
+}
+
+impl_deserialize!(JointInfo { name, limited, lower, upper });
+impl_serialize!(JointInfo { name, limited, lower, upper });
+
+/// Part appearances and materials, and the physical material picked for each part.
+/// Empty strings and a zero `has_appearance` mean the part has none.
+struct PartInfo {
+    material: String,
+    has_appearance: u8,
+    red: f32,
+    green: f32,
+    blue: f32,
+    metallic: f32,
+    roughness: f32,
+    opacity: f32,
+    physical_material: String,
+}
+
+impl_deserialize!(PartInfo { material, has_appearance, red, green, blue, metallic, roughness, opacity, physical_material });
+impl_serialize!(PartInfo { material, has_appearance, red, green, blue, metallic, roughness, opacity, physical_material });
+
+/// Actuators. An empty `motor` means the actuator has no motors.
+struct ActuatorInfo {
+    name: String,
+    joint: u32,
+    gear: f64,
+    motor: String,
+    motor_count: u32,
+    control_limited: u8,
+    control_lower: f64,
+    control_upper: f64,
+    force_limited: u8,
+    force_lower: f64,
+    force_upper: f64,
+}
+
+impl_deserialize!(ActuatorInfo { name, joint, gear, motor, motor_count, control_limited, control_lower, control_upper, force_limited, force_lower, force_upper });
+impl_serialize!(ActuatorInfo { name, joint, gear, motor, motor_count, control_limited, control_lower, control_upper, force_limited, force_lower, force_upper });
 
-    fn deserialize_value<'a, T: Deserialize<'a>>(&'a mut self) -> Result<T> {
-        let data = &self.input[self.position..self.position + size_of::<T>()];
-        self.position += size_of::<T>();
-        Ok(deserialize(data)?)
+/// Couplings between joints.
+struct CouplingInfo {
+    name: String,
+    kind: CouplingKind,
+    first: u32,
+    second: u32,
+    ratio: f64,
+    backlash: f64,
+    efficiency: f64,
+}
+
+impl_deserialize!(CouplingInfo { name, kind, first, second, ratio, backlash, efficiency });
+impl_serialize!(CouplingInfo { name, kind, first, second, ratio, backlash, efficiency });
+
+impl Deserialize for CouplingKind {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, DeserializeError> {
+        let offset = deserializer.offset();
+        match deserializer.read_u32()? {
+            0 => Ok(CouplingKind::Gear),
+            1 => Ok(CouplingKind::Belt),
+            2 => Ok(CouplingKind::Chain),
+            value => Err(DeserializeError::InvalidValue { type_name: "CouplingKind", value: value.into(), offset }),
+        }
+    }
+}
+
+impl Deserialize for JointType {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, DeserializeError> {
+        let offset = deserializer.offset();
+        match deserializer.read_u32()? {
+            0 => Ok(JointType::Rigid),
+            1 => Ok(JointType::Revolute),
+            2 => Ok(JointType::Slider),
+            value => Err(DeserializeError::InvalidValue { type_name: "JointType", value: value.into(), offset }),
+        }
     }
+}
 
-    fn deserialize_vec<'a, T, E>(&'a mut self) -> Result<T>
-    where
-        T: Deserialize<'a> + AsRef<[E]>,
-        E: Deserialize<'a>,
-    {
-        let length = usize::from_le_bytes(
-            self.input[self.position..self.position + size_of::<usize>()]
-            .try_into()
-            .with_context(|| "In reading length of vec")?,
+impl Deserialize for Pose {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, DeserializeError> {
+        let translation = DVec3::new(deserializer.read_f64()?, deserializer.read_f64()?, deserializer.read_f64()?);
+        let rotation = DQuat::from_xyzw(
+            deserializer.read_f64()?,
+            deserializer.read_f64()?,
+            deserializer.read_f64()?,
+            deserializer.read_f64()?,
         );
-        let vec_size = length * size_of::<E>() + size_of::<usize>();
-        let vec = deserialize(&self.input[self.position..self.position + vec_size])?;
-        self.position += vec_size;
-        Ok(vec)
+        Ok(Pose(translation, rotation))
     }
+}
 
-    pub fn deserialize_assembly(&mut self) -> Result<Assembly> {
-        let format_sig = "MRR (MechSim Robot Representation)";
-        let mut assembly = Assembly {
-            joints: vec![],
-            parts: vec![],
-            ..Default::default()
-        };
+impl Deserialize for Joint {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, DeserializeError> {
+        Ok(Joint {
+            name: String::new(),
+            joint_type: deserializer.read()?,
+            pose: deserializer.read()?,
+            limits: None,
+        })
+    }
+}
 
-        if !self.input.starts_with(format_sig.as_bytes()) {
-            bail!(MrrError::FormatSigNotFound)
+impl_deserialize!(Body { triangle_count, verticies, indicies, normals, uvs });
+impl_serialize!(Body { triangle_count, verticies, indicies, normals, uvs });
+
+impl Serialize for JointType {
+    fn serialize(&self, serializer: &mut Serializer) {
+        serializer.write_u32(match self {
+            JointType::Rigid => 0,
+            JointType::Revolute => 1,
+            JointType::Slider => 2,
+        });
+    }
+}
+
+impl Serialize for CouplingKind {
+    fn serialize(&self, serializer: &mut Serializer) {
+        serializer.write_u32(match self {
+            CouplingKind::Gear => 0,
+            CouplingKind::Belt => 1,
+            CouplingKind::Chain => 2,
+        });
+    }
+}
+
+impl Serialize for Pose {
+    fn serialize(&self, serializer: &mut Serializer) {
+        for value in self.0.to_array() {
+            serializer.write_f64(value);
+        }
+        for value in self.1.to_array() {
+            serializer.write_f64(value);
         }
+    }
+}
+
+impl Serialize for Joint {
+    fn serialize(&self, serializer: &mut Serializer) {
+        serializer.write(&self.joint_type);
+        serializer.write(&self.pose);
+    }
+}
 
-        self.position += format_sig.len();
+impl Serialize for Part {
+    fn serialize(&self, serializer: &mut Serializer) {
+        serializer.write(&self.name);
+        serializer.write(&self.pose);
+        serializer.write(&self.joint_references);
+        serializer.write(&self.rigid_group_references);
+        serializer.write(&self.bodies);
+    }
+}
 
-        let joint_list_size: usize = { self.deserialize_value()? };
+impl Deserialize for Part {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, DeserializeError> {
+        Ok(Part {
+            name: deserializer.read()?,
+            pose: deserializer.read()?,
+            joint_references: deserializer.read()?,
+            rigid_group_references: deserializer.read()?,
+            bodies: deserializer.read()?,
+            inertial: None,
+            hardware: false,
+            appearance: None,
+            material: None,
+            physical_material: None,
+        })
+    }
+}
 
-        for _ in 0..joint_list_size {
-            let joint_type: JointType = { self.deserialize_value()? };
-            let native_joint_pose: NativePose = { self.deserialize_value()? };
+/// Everything MRR files store after the parts.
+#[derive(Default)]
+struct Sections {
+    joints: Vec<JointInfo>,
+    parts: Vec<PartInfo>,
+    actuators: Vec<ActuatorInfo>,
+    couplings: Vec<CouplingInfo>,
+}
 
-            let vec = native_joint_pose.0;
-            let quat = native_joint_pose.1;
-            assembly.joints.push(Joint(
-                joint_type,
-                Pose(
-                    DVec3::new(vec.x, vec.y, vec.z),
-                    DQuat::from_xyzw(quat.x, quat.y, quat.z, quat.w),
-                ),
-            ));
+impl Sections {
+    fn read(deserializer: &mut Deserializer) -> Result<Self, DeserializeError> {
+        let mut sections = Self::default();
+        while !deserializer.is_empty() {
+            let tag: [u8; 4] = deserializer.read_bytes(4)?.try_into().unwrap();
+            let len = deserializer.read_len()?;
+            let mut section = Deserializer::new(deserializer.read_bytes(len)?.to_vec());
+            match &tag {
+                JOINT_SECTION => sections.joints = section.read_records()?,
+                PART_SECTION => sections.parts = section.read_records()?,
+                ACTUATOR_SECTION => sections.actuators = section.read_records()?,
+                COUPLING_SECTION => sections.couplings = section.read_records()?,
+                // Written by a newer version
+                _ => {}
+            }
         }
+        Ok(sections)
+    }
 
-        let parts_list_size: usize = { self.deserialize_value()? };
+    fn write(&self, serializer: &mut Serializer) {
+        serializer.write_bytes(SECTIONS_SIG.as_bytes());
+        write_section(serializer, JOINT_SECTION, &self.joints);
+        write_section(serializer, PART_SECTION, &self.parts);
+        write_section(serializer, ACTUATOR_SECTION, &self.actuators);
+        write_section(serializer, COUPLING_SECTION, &self.couplings);
+    }
+}
 
-        for _ in 0..parts_list_size {
-            let name: Vec<u8> = { self.deserialize_vec()? };
-            let native_part_pose: NativePose = { self.deserialize_value()? };
+fn write_section<T: Serialize>(serializer: &mut Serializer, tag: &[u8; 4], records: &[T]) {
+    let mut section = Serializer::new();
+    section.write_records(records);
+    let section = section.into_bytes();
 
-            let joint_reference_list: Vec<u32> = { self.deserialize_vec()? };
-            let rigid_group_reference_list: Vec<u32> = { self.deserialize_vec()? };
+    serializer.write_bytes(tag);
+    serializer.write_len(section.len());
+    serializer.write_bytes(&section);
+}
 
-            let body_list_size: usize = { self.deserialize_value()? };
-            let mut bodies = vec![];
+impl MrrDeserializer {
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self {
+            deserializer: Deserializer::from_file(path).with_context(|| format!("Failed to read {}", path.display()))?,
+        })
+    }
 
-            for _ in 0..body_list_size {
-                bodies.push(Body {
-                    triangle_count: { self.deserialize_value()? },
-                    verticies: { self.deserialize_vec()? },
-                    indicies: { self.deserialize_vec()? },
-                    normals: { self.deserialize_vec()? },
-                    uvs: { self.deserialize_vec()? },
-                });
-            }
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self { deserializer: Deserializer::new(bytes) }
+    }
 
-            let vec = native_part_pose.0;
-            let quat = native_part_pose.1;
-
-            assembly.parts.push(Part {
-                name: String::from_utf8(name)?,
-                pose: Pose(
-                    DVec3::new(vec.x, vec.y, vec.z),
-                    DQuat::from_xyzw(quat.x, quat.y, quat.z, quat.w),
-                ),
-                joint_references: joint_reference_list,
-                rigid_group_references: rigid_group_reference_list,
-                bodies: bodies,
+    pub fn deserialize_assembly(&mut self) -> Result<Assembly> {
+        if !self.deserializer.remaining().starts_with(FORMAT_SIG.as_bytes()) {
+            bail!(MrrError::FormatSigNotFound)
+        }
+        self.deserializer.skip(FORMAT_SIG.len())?;
+
+        let mut joints: Vec<Joint> = self.deserializer.read()?;
+        let mut parts: Vec<Part> = self.deserializer.read()?;
+
+        let sections = if self.deserializer.is_empty() {
+            Sections::default()
+        } else if self.deserializer.remaining().starts_with(SECTIONS_SIG.as_bytes()) {
+            self.deserializer.skip(SECTIONS_SIG.len())?;
+            Sections::read(&mut self.deserializer)?
+        } else {
+            bail!("The joint, part, actuator and coupling details after the parts are corrupt")
+        };
+        let Sections { joints: infos, parts: part_infos, actuators: actuator_infos, couplings: coupling_infos } = sections;
+
+        let mut actuators = vec![];
+        for info in actuator_infos {
+            let motor = match info.motor.as_str() {
+                "" => None,
+                name => Some(Motor::new(MotorModel::get(name).with_context(|| format!("Actuator \"{}\" has an unknown motor \"{name}\"", info.name))?, info.motor_count)),
+            };
+            actuators.push(Actuator {
+                name: info.name,
+                joint: info.joint as usize,
+                gear: info.gear,
+                control_range: Some((info.control_lower, info.control_upper)).filter(|_| info.control_limited != 0),
+                force_range: Some((info.force_lower, info.force_upper)).filter(|_| info.force_limited != 0),
+                motor,
             });
         }
 
-        Ok(assembly)
+        let couplings = coupling_infos.into_iter()
+            .map(|info| Coupling {
+                name: info.name,
+                kind: info.kind,
+                joints: [info.first as usize, info.second as usize],
+                ratio: info.ratio,
+                backlash: info.backlash,
+                efficiency: info.efficiency,
+            })
+            .collect();
+
+        for (part, info) in parts.iter_mut().zip(part_infos) {
+            part.material = Some(info.material).filter(|material| !material.is_empty());
+            part.physical_material = Some(info.physical_material).filter(|material| !material.is_empty());
+            part.appearance = (info.has_appearance != 0).then_some(Appearance {
+                color: [info.red, info.green, info.blue],
+                metallic: info.metallic,
+                roughness: info.roughness,
+                opacity: info.opacity,
+            });
+        }
+        for (i, joint) in joints.iter_mut().enumerate() {
+            match infos.get(i) {
+                Some(info) => {
+                    joint.name = info.name.clone();
+                    joint.limits = Some((info.lower, info.upper)).filter(|_| info.limited != 0);
+                }
+                None => joint.name = format!("Joint {}", i + 1),
+            }
+        }
+
+        Ok(Assembly {
+            joints,
+            parts,
+            actuators,
+            couplings,
+            ..Default::default()
+        })
     }
 }
 
 impl Assembly {
-    pub fn load_meshes(&mut self) {
-        let mesh_count = self.body_count();
-        self.meshes = Vec::with_capacity(mesh_count);
+    /// Reads the robot at `path`, remembering where it came from. MJCF models (`.xml`) are
+    /// imported, anything else is read as MRR.
+    pub fn open(path: &Path) -> Result<Self> {
+        let extension = path.extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase);
+
+        let mut assembly = match extension.as_deref() {
+            Some("xml") | Some("mjcf") => mjcf::load(path),
+            Some("urdf") => urdf::load(path),
+            Some("stl") | Some("obj") => meshes::load(&[path.to_owned()], LengthUnit::default()),
+            _ => MrrDeserializer::load(path)?.deserialize_assembly(),
+        }.with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        let errors: Vec<String> = assembly.validate().iter()
+            .filter(|error| error.is_out_of_range())
+            .map(ToString::to_string)
+            .collect();
+        if !errors.is_empty() {
+            bail!("{} is corrupt:\n{}", path.display(), errors.join("\n"))
+        }
+
+        assembly.file_path = path.to_owned();
+        Ok(assembly)
+    }
+
+    /// Writes the assembly as an MRR file. Inertials and anything else MRR can't store are left out.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_bytes()).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The assembly as the contents of an MRR file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut serializer = Serializer::new();
+        serializer.write_bytes(FORMAT_SIG.as_bytes());
+        serializer.write(&self.joints);
+        serializer.write(&self.parts);
+
+        let joints: Vec<JointInfo> = self.joints.iter()
+            .map(|joint| {
+                let (lower, upper) = joint.limits.unwrap_or_default();
+                JointInfo { name: joint.name.clone(), limited: joint.limits.is_some() as u8, lower, upper }
+            })
+            .collect();
+
+        let parts: Vec<PartInfo> = self.parts.iter()
+            .map(|part| {
+                let appearance = part.appearance.unwrap_or(Appearance::plastic([0.; 3]));
+                PartInfo {
+                    material: part.material.clone().unwrap_or_default(),
+                    has_appearance: part.appearance.is_some() as u8,
+                    red: appearance.color[0],
+                    green: appearance.color[1],
+                    blue: appearance.color[2],
+                    metallic: appearance.metallic,
+                    roughness: appearance.roughness,
+                    opacity: appearance.opacity,
+                    physical_material: part.physical_material.clone().unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        let actuators: Vec<ActuatorInfo> = self.actuators.iter()
+            .map(|actuator| {
+                let (control_lower, control_upper) = actuator.control_range.unwrap_or_default();
+                let (force_lower, force_upper) = actuator.force_range.unwrap_or_default();
+                ActuatorInfo {
+                    name: actuator.name.clone(),
+                    joint: actuator.joint as u32,
+                    gear: actuator.gear,
+                    motor: actuator.motor.map_or("", |motor| motor.model.name).to_owned(),
+                    motor_count: actuator.motor.map_or(0, |motor| motor.count),
+                    control_limited: actuator.control_range.is_some() as u8,
+                    control_lower,
+                    control_upper,
+                    force_limited: actuator.force_range.is_some() as u8,
+                    force_lower,
+                    force_upper,
+                }
+            })
+            .collect();
+
+        let couplings: Vec<CouplingInfo> = self.couplings.iter()
+            .map(|coupling| CouplingInfo {
+                name: coupling.name.clone(),
+                kind: coupling.kind,
+                first: coupling.joints[0] as u32,
+                second: coupling.joints[1] as u32,
+                ratio: coupling.ratio,
+                backlash: coupling.backlash,
+                efficiency: coupling.efficiency,
+            })
+            .collect();
+
+        Sections { joints, parts, actuators, couplings }.write(&mut serializer);
+        serializer.into_bytes()
+    }
+
+    /// Writes the assembly to another format, picked by the extension of `path`. Returns anything that
+    /// couldn't be represented in that format.
+    pub fn export(&self, path: &Path) -> Result<Vec<String>> {
+        let extension = path.extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("urdf") => urdf::save(self, path),
+            Some("glb") => gltf::save(self, path, &GlbOptions::default()),
+            Some("mrr") => self.save(path).map(|_| self.mrr_warnings()),
+            _ => bail!("Can't export to {}, the format is not supported", path.display()),
+        }.with_context(|| format!("Failed to export {}", path.display()))
+    }
+
+    fn mrr_warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        if self.parts.iter().any(|part| part.inertial.is_some()) {
+            warnings.push("MRR files don't store mass properties, they were left out".to_owned());
+        }
+        warnings
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.parts.iter()
+        .flat_map(|part| part.bodies.iter())
+        .map(|body| body.indicies.len() / 3)
+        .sum()
+    }
+
+    /// Checks that the references and mesh data of the assembly are consistent.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = vec![];
+        let mut joint_used = vec![false; self.joints.len()];
 
         for part in &self.parts {
-            for body in &part.bodies {
-                let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+            for &joint in &part.joint_references {
+                match joint_used.get_mut(joint as usize) {
+                    Some(used) => *used = true,
+                    None => errors.push(ValidationError::InvalidJointReference {
+                        part: part.name.clone(),
+                        joint,
+                        joint_count: self.joints.len(),
+                    }),
+                }
+            }
 
-                mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, body.verticies.chunks(3).map(|v| [v[0] / 6., v[1] / 6., v[2] / 6.]).collect::<Vec<[f32; 3]>>());
-                mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, body.verticies.chunks(3).map(|v| [v[0], v[1], v[2]]).collect::<Vec<[f32; 3]>>());
-                mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, body.uvs.chunks(2).map(|v| [v[0], v[1]]).collect::<Vec<[f32; 2]>>());
-                mesh.set_indices(Some(mesh::Indices::U32(body.indicies.iter().map(|&x| x as u32).collect())));
+            if part.bodies.is_empty() {
+                errors.push(ValidationError::NoBodies { part: part.name.clone() });
+            }
+
+            for (i, body) in part.bodies.iter().enumerate() {
+                if body.verticies.len() % 3 != 0 {
+                    errors.push(ValidationError::MalformedVertices { part: part.name.clone(), body: i, len: body.verticies.len() });
+                }
+                if body.indicies.len() % 3 != 0 {
+                    errors.push(ValidationError::MalformedIndices { part: part.name.clone(), body: i, len: body.indicies.len() });
+                }
+                if body.triangle_count as usize != body.indicies.len() / 3 {
+                    errors.push(ValidationError::TriangleCountMismatch {
+                        part: part.name.clone(),
+                        body: i,
+                        claimed: body.triangle_count,
+                        actual: body.indicies.len() / 3,
+                    });
+                }
+
+                let vertex_count = body.verticies.len() / 3;
+                if let Some(&index) = body.indicies.iter().find(|&&index| index < 0 || index as usize >= vertex_count) {
+                    errors.push(ValidationError::IndexOutOfRange { part: part.name.clone(), body: i, index, vertex_count });
+                }
+            }
+        }
 
-                self.meshes.push(mesh);
-            } 
+        for joint in &self.joints {
+            if let Some((lower, upper)) = joint.limits {
+                if lower > upper {
+                    errors.push(ValidationError::InvalidLimits { joint: joint.name.clone(), lower, upper });
+                }
+            }
         }
+
+        for actuator in &self.actuators {
+            if actuator.joint >= self.joints.len() {
+                errors.push(ValidationError::InvalidActuatorJoint {
+                    actuator: actuator.name.clone(),
+                    joint: actuator.joint,
+                    joint_count: self.joints.len(),
+                });
+            }
+        }
+
+        for coupling in &self.couplings {
+            if coupling.joints[0] == coupling.joints[1] {
+                errors.push(ValidationError::SelfCoupling { coupling: coupling.name.clone() });
+            }
+            for &joint in coupling.joints.iter().filter(|&&joint| joint >= self.joints.len()) {
+                errors.push(ValidationError::InvalidCouplingJoint {
+                    coupling: coupling.name.clone(),
+                    joint,
+                    joint_count: self.joints.len(),
+                });
+            }
+        }
+
+        errors.extend(joint_used.iter()
+            .enumerate()
+            .filter(|(_, &used)| !used)
+            .map(|(joint, _)| ValidationError::UnusedJoint { joint }));
+
+        errors
+    }
+
+    /// Makes a mesh for each distinct shape of body, shared by the bodies that are copies of it.
+    pub fn load_meshes(&mut self) {
+        self.mesh_instances = MeshInstances::new(self);
+        self.meshes = (0..self.mesh_instances.shapes.len())
+            .map(|shape| self.mesh_instances.shape_body(self, shape).to_mesh())
+            .collect();
     }
 
     pub fn get_name(&self) -> &str {
@@ -227,4 +965,174 @@ impl Plugin for MrrPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Assembly>();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(name: &str, joint_references: Vec<u32>, rigid_group_references: Vec<u32>) -> Part {
+        Part {
+            name: name.to_owned(),
+            pose: Pose(DVec3::new(1., 2., 3.), DQuat::from_rotation_z(0.5)),
+            joint_references,
+            rigid_group_references,
+            bodies: vec![Body::from_triangles(vec![0., 0., 0., 1., 0., 0., 0., 1., 0.], vec![0, 1, 2])],
+            inertial: None,
+            hardware: false,
+            appearance: None,
+            material: None,
+            physical_material: None,
+        }
+    }
+
+    fn read(bytes: Vec<u8>) -> Assembly {
+        MrrDeserializer::from_bytes(bytes).deserialize_assembly().unwrap()
+    }
+
+    #[test]
+    fn open_rejects_references_out_of_range() {
+        let joint = Joint { name: "Shoulder".to_owned(), joint_type: JointType::Revolute, pose: Pose::IDENTITY, limits: None };
+        let mut bad_body = part("Arm", vec![0], vec![0]);
+        bad_body.bodies[0].indicies[2] = 3;
+        let assemblies = [
+            Assembly { parts: vec![part("Arm", vec![1], vec![0])], joints: vec![joint.clone()], ..Default::default() },
+            Assembly { parts: vec![bad_body], joints: vec![joint.clone()], ..Default::default() },
+            Assembly {
+                parts: vec![part("Arm", vec![0], vec![0])],
+                joints: vec![joint.clone()],
+                actuators: vec![Actuator { name: "Motor".to_owned(), joint: 1, gear: 1., control_range: None, force_range: None, motor: None }],
+                ..Default::default()
+            },
+            Assembly {
+                parts: vec![part("Arm", vec![0], vec![0])],
+                joints: vec![joint],
+                couplings: vec![Coupling::new("Belt", CouplingKind::Belt, [0, 2])],
+                ..Default::default()
+            },
+        ];
+
+        let path = std::env::temp_dir().join(format!("mechsim-out-of-range-{}.mrr", std::process::id()));
+        for assembly in assemblies {
+            std::fs::write(&path, assembly.to_bytes()).unwrap();
+            assert!(Assembly::open(&path).unwrap_err().to_string().contains("is corrupt"));
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn z_up_turns_z_into_up() {
+        assert!((Z_UP * DVec3::Z).abs_diff_eq(UP, 1e-12));
+        assert!((Z_UP * DVec3::X).abs_diff_eq(DVec3::X, 1e-12));
+
+        // Turning about the assembly's up axis is turning about Z in a Z-up frame
+        let turn = Pose(UP, DQuat::from_axis_angle(UP, 0.5)).to_z_up();
+        assert!(turn.0.abs_diff_eq(DVec3::Z, 1e-12));
+        assert!(turn.1.abs_diff_eq(DQuat::from_rotation_z(0.5), 1e-12));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut coupling = Coupling::new("Belt", CouplingKind::Chain, [0, 1]);
+        coupling.backlash = 0.01;
+        let assembly = Assembly {
+            parts: vec![
+                part("Base", vec![0], vec![0]),
+                Part {
+                    appearance: Some(Appearance::metal([0.1, 0.2, 0.3], 0.4)),
+                    material: Some("Aluminum - 6061".to_owned()),
+                    physical_material: Some("Steel".to_owned()),
+                    ..part("Arm", vec![0, 1], vec![])
+                },
+                part("Wrist", vec![1], vec![]),
+            ],
+            joints: vec![
+                Joint { name: "Shoulder".to_owned(), joint_type: JointType::Revolute, pose: Pose::IDENTITY, limits: Some((-1., 2.)) },
+                Joint { name: "Slide".to_owned(), joint_type: JointType::Slider, pose: Pose::IDENTITY, limits: None },
+            ],
+            actuators: vec![Actuator {
+                name: "Shoulder Motor".to_owned(),
+                joint: 0,
+                gear: 60.,
+                control_range: Some((-12., 12.)),
+                force_range: None,
+                motor: Some(Motor::new(MotorModel::get("NEO").unwrap(), 2)),
+            }],
+            couplings: vec![coupling],
+            ..Default::default()
+        };
+
+        let loaded = read(assembly.to_bytes());
+
+        assert_eq!(loaded.joints, assembly.joints);
+        assert_eq!(loaded.actuators, assembly.actuators);
+        assert_eq!(loaded.couplings, assembly.couplings);
+        assert_eq!(loaded.parts.len(), assembly.parts.len());
+        for (loaded, part) in loaded.parts.iter().zip(&assembly.parts) {
+            assert_eq!(loaded.name, part.name);
+            assert_eq!(loaded.pose, part.pose);
+            assert_eq!(loaded.joint_references, part.joint_references);
+            assert_eq!(loaded.rigid_group_references, part.rigid_group_references);
+            assert_eq!(loaded.appearance, part.appearance);
+            assert_eq!(loaded.material, part.material);
+            assert_eq!(loaded.physical_material, part.physical_material);
+            assert_eq!(loaded.bodies[0].verticies, part.bodies[0].verticies);
+            assert_eq!(loaded.bodies[0].indicies, part.bodies[0].indicies);
+            assert_eq!(loaded.bodies[0].normals, part.bodies[0].normals);
+        }
+    }
+
+    #[test]
+    fn loads_baseline_format() {
+        // Exporters write the signature, joints and parts, without any of the sections MechSim adds
+        let mut serializer = Serializer::new();
+        serializer.write_bytes(FORMAT_SIG.as_bytes());
+        serializer.write(&vec![Joint { name: String::new(), joint_type: JointType::Revolute, pose: Pose::IDENTITY, limits: None }]);
+        serializer.write(&vec![part("Base", vec![0], vec![0]), part("Arm", vec![0], vec![1])]);
+
+        let loaded = read(serializer.into_bytes());
+
+        assert_eq!(loaded.joints.len(), 1);
+        assert_eq!(loaded.joints[0].name, "Joint 1");
+        assert_eq!(loaded.joints[0].joint_type, JointType::Revolute);
+        assert_eq!(loaded.joints[0].limits, None);
+        assert_eq!(loaded.parts.len(), 2);
+        assert_eq!(loaded.parts[1].name, "Arm");
+        assert_eq!(loaded.parts[1].material, None);
+        assert!(loaded.actuators.is_empty());
+        assert!(loaded.couplings.is_empty());
+        assert!(loaded.validate().is_empty());
+    }
+
+    #[test]
+    fn rejects_details_without_the_sections_signature() {
+        let mut bytes = Assembly {
+            parts: vec![part("Base", vec![], vec![])],
+            ..Default::default()
+        }.to_bytes();
+        let sections = bytes.windows(SECTIONS_SIG.len()).position(|window| window == SECTIONS_SIG.as_bytes()).unwrap();
+        bytes.drain(sections..sections + SECTIONS_SIG.len());
+
+        assert!(MrrDeserializer::from_bytes(bytes).deserialize_assembly().is_err());
+    }
+
+    #[test]
+    fn skips_unknown_sections() {
+        let assembly = Assembly {
+            parts: vec![part("Base", vec![0], vec![]), part("Arm", vec![0], vec![])],
+            joints: vec![Joint { name: "Shoulder".to_owned(), joint_type: JointType::Revolute, pose: Pose::IDENTITY, limits: None }],
+            ..Default::default()
+        };
+
+        let mut bytes = assembly.to_bytes();
+        bytes.extend(b"NEWS");
+        bytes.extend(3u64.to_le_bytes());
+        bytes.extend([1, 2, 3]);
+
+        assert_eq!(read(bytes).joints[0].name, "Shoulder");
+    }
+
+    #[test]
+    fn rejects_missing_signature() {
+        assert!(MrrDeserializer::from_bytes(b"not a robot".to_vec()).deserialize_assembly().is_err());
+    }
+}
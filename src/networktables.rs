@@ -0,0 +1,836 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow};
+use bevy::prelude::*;
+use serde_json::{Map, Value, json};
+use tungstenite::Message;
+use tungstenite::handshake::server::{Request, Response};
+use tungstenite::http::HeaderValue;
+
+use crate::battery::Battery;
+use crate::electrical::ElectricalMap;
+use crate::kinematics::KinematicTree;
+use crate::log::LogMessages;
+use crate::mrr::{Assembly, Pose, METERS_PER_UNIT};
+use crate::sensors::{SensorReading, SensorReadings};
+use crate::sim::{ActuatorStates, JointStates, SimClock};
+
+/// Port dashboards and robot code connect to NetworkTables 4 servers on by default.
+pub const DEFAULT_PORT: u16 = 5810;
+/// Every topic the simulation publishes is under this prefix.
+pub const PREFIX: &str = "/MechSim";
+/// WebSocket subprotocols of NetworkTables 4, from most to least preferred. Version 4.1 clients measure
+/// their round trip time over a second connection with the last one.
+const PROTOCOLS: [&str; 3] = ["v4.1.networktables.first.wpi.edu", "networktables.first.wpi.edu", "rtt.networktables.first.wpi.edu"];
+/// How long a client's connection waits for a message before sending the ones queued up.
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+/// How often the simulation's values are published, as often as robot code runs.
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(20);
+/// Topic ID of the messages clients synchronize their clocks with.
+const TIME_SYNC_ID: i64 = -1;
+
+/// A value of a topic, of one of the types NetworkTables supports.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NtValue {
+    Boolean(bool),
+    Double(f64),
+    Int(i64),
+    Float(f32),
+    String(String),
+    Raw(Vec<u8>),
+    BooleanArray(Vec<bool>),
+    DoubleArray(Vec<f64>),
+    IntArray(Vec<i64>),
+    FloatArray(Vec<f32>),
+    StringArray(Vec<String>),
+}
+
+impl NtValue {
+    /// Name of the type topics are announced with.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            NtValue::Boolean(_) => "boolean",
+            NtValue::Double(_) => "double",
+            NtValue::Int(_) => "int",
+            NtValue::Float(_) => "float",
+            NtValue::String(_) => "string",
+            NtValue::Raw(_) => "raw",
+            NtValue::BooleanArray(_) => "boolean[]",
+            NtValue::DoubleArray(_) => "double[]",
+            NtValue::IntArray(_) => "int[]",
+            NtValue::FloatArray(_) => "float[]",
+            NtValue::StringArray(_) => "string[]",
+        }
+    }
+
+    /// ID of the type in value messages. Types such as `json` and `msgpack` share the IDs of strings and raw values.
+    fn type_id(&self) -> u8 {
+        match self {
+            NtValue::Boolean(_) => 0,
+            NtValue::Double(_) => 1,
+            NtValue::Int(_) => 2,
+            NtValue::Float(_) => 3,
+            NtValue::String(_) => 4,
+            NtValue::Raw(_) => 5,
+            NtValue::BooleanArray(_) => 16,
+            NtValue::DoubleArray(_) => 17,
+            NtValue::IntArray(_) => 18,
+            NtValue::FloatArray(_) => 19,
+            NtValue::StringArray(_) => 20,
+        }
+    }
+
+    fn to_msgpack(&self) -> rmpv::Value {
+        use rmpv::Value as Mp;
+        let array = |values: Vec<Mp>| Mp::Array(values);
+        match self {
+            NtValue::Boolean(value) => Mp::Boolean(*value),
+            NtValue::Double(value) => Mp::F64(*value),
+            NtValue::Int(value) => Mp::from(*value),
+            NtValue::Float(value) => Mp::F32(*value),
+            NtValue::String(value) => Mp::from(value.as_str()),
+            NtValue::Raw(value) => Mp::Binary(value.clone()),
+            NtValue::BooleanArray(values) => array(values.iter().map(|&value| Mp::Boolean(value)).collect()),
+            NtValue::DoubleArray(values) => array(values.iter().map(|&value| Mp::F64(value)).collect()),
+            NtValue::IntArray(values) => array(values.iter().map(|&value| Mp::from(value)).collect()),
+            NtValue::FloatArray(values) => array(values.iter().map(|&value| Mp::F32(value)).collect()),
+            NtValue::StringArray(values) => array(values.iter().map(|value| Mp::from(value.as_str())).collect()),
+        }
+    }
+
+    fn from_msgpack(type_id: u64, value: &rmpv::Value) -> Option<Self> {
+        // Whole numbers may be sent as integers for floating point topics
+        let double = |value: &rmpv::Value| value.as_f64().or_else(|| value.as_i64().map(|value| value as f64));
+        let array = |value: &rmpv::Value| value.as_array().cloned();
+        Some(match type_id {
+            0 => NtValue::Boolean(value.as_bool()?),
+            1 => NtValue::Double(double(value)?),
+            2 => NtValue::Int(value.as_i64()?),
+            3 => NtValue::Float(double(value)? as f32),
+            4 => NtValue::String(value.as_str()?.to_owned()),
+            5 => NtValue::Raw(value.as_slice()?.to_vec()),
+            16 => NtValue::BooleanArray(array(value)?.iter().map(rmpv::Value::as_bool).collect::<Option<_>>()?),
+            17 => NtValue::DoubleArray(array(value)?.iter().map(double).collect::<Option<_>>()?),
+            18 => NtValue::IntArray(array(value)?.iter().map(rmpv::Value::as_i64).collect::<Option<_>>()?),
+            19 => NtValue::FloatArray(array(value)?.iter().map(|value| double(value).map(|value| value as f32)).collect::<Option<_>>()?),
+            20 => NtValue::StringArray(array(value)?.iter().map(|value| value.as_str().map(str::to_owned)).collect::<Option<_>>()?),
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for NtValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NtValue::Boolean(value) => write!(f, "{value}"),
+            NtValue::Double(value) => write!(f, "{value}"),
+            NtValue::Int(value) => write!(f, "{value}"),
+            NtValue::Float(value) => write!(f, "{value}"),
+            NtValue::String(value) => write!(f, "{value:?}"),
+            NtValue::Raw(value) => write!(f, "{} bytes", value.len()),
+            NtValue::BooleanArray(values) => write!(f, "{values:?}"),
+            NtValue::DoubleArray(values) => write!(f, "{values:?}"),
+            NtValue::IntArray(values) => write!(f, "{values:?}"),
+            NtValue::FloatArray(values) => write!(f, "{values:?}"),
+            NtValue::StringArray(values) => write!(f, "{values:?}"),
+        }
+    }
+}
+
+struct Topic {
+    name: String,
+    id: i64,
+    type_name: String,
+    properties: Map<String, Value>,
+    value: Option<NtValue>,
+    /// Server time the value was set at, in microseconds
+    timestamp: i64,
+    /// Clients publishing the topic, with their IDs for it
+    publishers: Vec<(u64, i64)>,
+    /// Published by the simulation, so kept when no client publishes it
+    simulated: bool,
+}
+
+impl Topic {
+    fn announcement(&self, pubuid: Option<i64>) -> Value {
+        let mut params = json!({ "name": self.name, "id": self.id, "type": self.type_name, "properties": self.properties });
+        if let Some(pubuid) = pubuid {
+            params["pubuid"] = json!(pubuid);
+        }
+        json!({ "method": "announce", "params": params })
+    }
+}
+
+struct Subscription {
+    topics: Vec<String>,
+    /// Topics are prefixes of names rather than whole names
+    prefix: bool,
+    /// Only announce topics, without sending their values
+    topics_only: bool,
+}
+
+impl Subscription {
+    fn matches(&self, name: &str) -> bool {
+        self.topics.iter().any(|topic| if self.prefix { name.starts_with(topic.as_str()) } else { name == topic })
+    }
+}
+
+struct Client {
+    name: String,
+    outgoing: Sender<Message>,
+    subscriptions: HashMap<i64, Subscription>,
+    /// Names of the topics the client publishes, by its IDs for them
+    publishers: HashMap<i64, String>,
+    /// IDs of the topics announced to the client
+    announced: HashSet<i64>,
+    /// Control messages waiting to be sent
+    text: Vec<Value>,
+    /// Values waiting to be sent, encoded as MessagePack
+    binary: Vec<u8>,
+}
+
+impl Client {
+    /// Returns whether the client wants the values of the topic called `name`, or `None` if it isn't subscribed to it.
+    fn wants_values(&self, name: &str) -> Option<bool> {
+        let mut matching = self.subscriptions.values().filter(|subscription| subscription.matches(name)).peekable();
+        matching.peek()?;
+        Some(matching.any(|subscription| !subscription.topics_only))
+    }
+
+    fn announce(&mut self, topic: &Topic, pubuid: Option<i64>) {
+        self.text.push(topic.announcement(pubuid));
+        self.announced.insert(topic.id);
+    }
+
+    fn send_value(&mut self, id: i64, timestamp: i64, value: &NtValue) {
+        let message = rmpv::Value::Array(vec![id.into(), timestamp.into(), value.type_id().into(), value.to_msgpack()]);
+        // Writing to a Vec can't fail
+        rmpv::encode::write_value(&mut self.binary, &message).ok();
+    }
+
+    fn flush(&mut self) {
+        if !self.text.is_empty() {
+            let text = Value::Array(std::mem::take(&mut self.text)).to_string();
+            self.outgoing.send(Message::Text(text)).ok();
+        }
+        if !self.binary.is_empty() {
+            self.outgoing.send(Message::Binary(std::mem::take(&mut self.binary))).ok();
+        }
+    }
+}
+
+/// What the threads of the server and its clients report.
+enum Event {
+    Connected(u64, String, Sender<Message>),
+    Text(u64, String),
+    Binary(u64, Vec<u8>),
+    Disconnected(u64),
+}
+
+/// The thread accepting clients, stopped along with every client's thread when dropped.
+struct Server {
+    events: Mutex<Receiver<Event>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A NetworkTables 4 server, publishing the state of the simulation under [`PREFIX`] for dashboards
+/// and passing values between its clients, such as from robot code to dashboards.
+#[derive(Resource)]
+pub struct NetworkTables {
+    pub port: u16,
+    server: Option<Server>,
+    clients: BTreeMap<u64, Client>,
+    topics: BTreeMap<String, Topic>,
+    next_topic_id: i64,
+    /// Server time is counted in microseconds from here
+    start: Instant,
+    last_publish: Option<Instant>,
+    tree: KinematicTree,
+    /// Actuators controlled from a client
+    controlled: Vec<usize>,
+    /// Topics the simulation couldn't publish because they have another type, which were already warned about
+    mismatched: HashSet<String>,
+}
+
+impl Default for NetworkTables {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_PORT,
+            server: None,
+            clients: BTreeMap::new(),
+            topics: BTreeMap::new(),
+            next_topic_id: 0,
+            start: Instant::now(),
+            last_publish: None,
+            tree: KinematicTree::default(),
+            controlled: vec![],
+            mismatched: HashSet::new(),
+        }
+    }
+}
+
+impl NetworkTables {
+    pub fn is_running(&self) -> bool {
+        self.server.is_some()
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        self.stop();
+
+        let listener = TcpListener::bind(("0.0.0.0", self.port)).with_context(|| format!("Failed to listen on port {}", self.port))?;
+        listener.set_nonblocking(true)?;
+        let (event_sender, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        thread::spawn(move || serve(listener, event_sender, thread_stop));
+
+        self.server = Some(Server { events: Mutex::new(events), stop });
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.server = None;
+        self.mismatched.clear();
+        let clients: Vec<u64> = self.clients.keys().copied().collect();
+        for client in clients {
+            self.disconnect(client);
+        }
+    }
+
+    pub fn has_clients(&self) -> bool {
+        !self.clients.is_empty()
+    }
+
+    /// Names of the connected clients, such as `AdvantageScope@1`.
+    pub fn client_names(&self) -> impl Iterator<Item = &str> {
+        self.clients.values().map(|client| client.name.as_str())
+    }
+
+    /// Returns the value of the topic called `name`.
+    pub fn get(&self, name: &str) -> Option<&NtValue> {
+        self.topics.get(name)?.value.as_ref()
+    }
+
+    /// Returns the names and values of the topics clients publish.
+    pub fn client_values(&self) -> impl Iterator<Item = (&str, &NtValue)> {
+        self.topics.values()
+            .filter(|topic| !topic.publishers.is_empty())
+            .filter_map(|topic| Some((topic.name.as_str(), topic.value.as_ref()?)))
+    }
+
+    /// Publishes `value` from the simulation to the topic called `name`. Returns `false` without publishing it if
+    /// the topic already has another type, such as when a client publishes it, since clients decode values by
+    /// the type the topic was announced with.
+    pub fn set(&mut self, name: &str, value: NtValue) -> bool {
+        if self.topics.get(name).is_some_and(|topic| topic.type_name != value.type_name()) {
+            return false;
+        }
+
+        let timestamp = self.now();
+        let NetworkTables { topics, clients, next_topic_id, .. } = self;
+        let topic = topics.entry(name.to_owned()).or_insert_with(|| {
+            *next_topic_id += 1;
+            Topic {
+                name: name.to_owned(),
+                id: *next_topic_id,
+                type_name: value.type_name().to_owned(),
+                properties: Map::new(),
+                value: None,
+                timestamp,
+                publishers: vec![],
+                simulated: true,
+            }
+        });
+        topic.simulated = true;
+        for client in clients.values_mut() {
+            let Some(wants_values) = client.wants_values(name) else {
+                continue;
+            };
+            if !client.announced.contains(&topic.id) {
+                client.announce(topic, None);
+            }
+            if wants_values {
+                client.send_value(topic.id, timestamp, &value);
+            }
+        }
+        topic.value = Some(value);
+        topic.timestamp = timestamp;
+        true
+    }
+
+    /// Returns the type of the topic called `name`, such as `double`.
+    pub fn type_name(&self, name: &str) -> Option<&str> {
+        Some(self.topics.get(name)?.type_name.as_str())
+    }
+
+    /// Microseconds since the server started.
+    fn now(&self) -> i64 {
+        self.start.elapsed().as_micros() as i64
+    }
+
+    fn handle_text(&mut self, client: u64, text: &str) {
+        let Ok(Value::Array(messages)) = serde_json::from_str::<Value>(text) else {
+            return;
+        };
+        for message in messages {
+            let params = &message["params"];
+            match message["method"].as_str() {
+                Some("publish") => self.publish(client, params),
+                Some("unpublish") => {
+                    if let Some(pubuid) = params["pubuid"].as_i64() {
+                        self.unpublish(client, pubuid);
+                    }
+                }
+                Some("setproperties") => self.set_properties(client, params),
+                Some("subscribe") => self.subscribe(client, params),
+                Some("unsubscribe") => {
+                    if let (Some(subuid), Some(client)) = (params["subuid"].as_i64(), self.clients.get_mut(&client)) {
+                        client.subscriptions.remove(&subuid);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn publish(&mut self, client_id: u64, params: &Value) {
+        let (Some(name), Some(pubuid), Some(type_name)) = (params["name"].as_str(), params["pubuid"].as_i64(), params["type"].as_str()) else {
+            return;
+        };
+        let NetworkTables { topics, clients, next_topic_id, .. } = self;
+        let Some(client) = clients.get_mut(&client_id) else {
+            return;
+        };
+        client.publishers.insert(pubuid, name.to_owned());
+
+        let topic = topics.entry(name.to_owned()).or_insert_with(|| {
+            *next_topic_id += 1;
+            Topic {
+                name: name.to_owned(),
+                id: *next_topic_id,
+                type_name: type_name.to_owned(),
+                properties: params["properties"].as_object().cloned().unwrap_or_default(),
+                value: None,
+                timestamp: 0,
+                publishers: vec![],
+                simulated: false,
+            }
+        });
+        topic.publishers.push((client_id, pubuid));
+
+        // Publishers are always told the topic's ID, subscribers only the first time
+        for (&id, client) in clients.iter_mut() {
+            if id == client_id {
+                client.announce(topic, Some(pubuid));
+            } else if !client.announced.contains(&topic.id) && client.wants_values(name).is_some() {
+                client.announce(topic, None);
+            }
+        }
+    }
+
+    fn unpublish(&mut self, client_id: u64, pubuid: i64) {
+        let Some(name) = self.clients.get_mut(&client_id).and_then(|client| client.publishers.remove(&pubuid)) else {
+            return;
+        };
+        let Some(topic) = self.topics.get_mut(&name) else {
+            return;
+        };
+        topic.publishers.retain(|&publisher| publisher != (client_id, pubuid));
+        if !topic.publishers.is_empty() || topic.simulated {
+            return;
+        }
+
+        let id = topic.id;
+        self.topics.remove(&name);
+        for client in self.clients.values_mut() {
+            if client.announced.remove(&id) {
+                client.text.push(json!({ "method": "unannounce", "params": { "name": name, "id": id } }));
+            }
+        }
+    }
+
+    fn set_properties(&mut self, client_id: u64, params: &Value) {
+        let (Some(name), Some(update)) = (params["name"].as_str(), params["update"].as_object()) else {
+            return;
+        };
+        let Some(topic) = self.topics.get_mut(name) else {
+            return;
+        };
+        for (key, value) in update {
+            if value.is_null() {
+                topic.properties.remove(key);
+            } else {
+                topic.properties.insert(key.clone(), value.clone());
+            }
+        }
+
+        for (&id, client) in self.clients.iter_mut() {
+            if id == client_id {
+                client.text.push(json!({ "method": "properties", "params": { "name": name, "ack": true, "update": update } }));
+            } else if client.announced.contains(&topic.id) {
+                client.text.push(json!({ "method": "properties", "params": { "name": name, "update": update } }));
+            }
+        }
+    }
+
+    fn subscribe(&mut self, client_id: u64, params: &Value) {
+        let Some(subuid) = params["subuid"].as_i64() else {
+            return;
+        };
+        let Some(client) = self.clients.get_mut(&client_id) else {
+            return;
+        };
+        let options = &params["options"];
+        let subscription = Subscription {
+            topics: params["topics"].as_array()
+                .map(|topics| topics.iter().filter_map(Value::as_str).map(str::to_owned).collect())
+                .unwrap_or_default(),
+            prefix: options["prefix"].as_bool().unwrap_or(false),
+            topics_only: options["topicsonly"].as_bool().unwrap_or(false),
+        };
+
+        for topic in self.topics.values().filter(|topic| subscription.matches(&topic.name)) {
+            if !client.announced.contains(&topic.id) {
+                client.announce(topic, None);
+            }
+            if let (Some(value), false) = (&topic.value, subscription.topics_only) {
+                client.send_value(topic.id, topic.timestamp, value);
+            }
+        }
+        client.subscriptions.insert(subuid, subscription);
+    }
+
+    fn handle_binary(&mut self, client_id: u64, data: &[u8]) {
+        let now = self.now();
+        let mut data = data;
+        while !data.is_empty() {
+            let Ok(message) = rmpv::decode::read_value(&mut data) else {
+                return;
+            };
+            let Some([id, timestamp, type_id, value]) = message.as_array().and_then(|message| <&[_; 4]>::try_from(message.as_slice()).ok()) else {
+                continue;
+            };
+            let (Some(id), Some(timestamp), Some(type_id)) = (id.as_i64(), timestamp.as_i64(), type_id.as_u64()) else {
+                continue;
+            };
+
+            if id == TIME_SYNC_ID {
+                // Echo the client's time with the server's, so the client can work out the offset between them
+                if let Some(client) = self.clients.get_mut(&client_id) {
+                    let reply = rmpv::Value::Array(vec![TIME_SYNC_ID.into(), now.into(), type_id.into(), value.clone()]);
+                    rmpv::encode::write_value(&mut client.binary, &reply).ok();
+                }
+                continue;
+            }
+
+            let Some(name) = self.clients.get(&client_id).and_then(|client| client.publishers.get(&id)).cloned() else {
+                continue;
+            };
+            let (Some(topic), Some(value)) = (self.topics.get_mut(&name), NtValue::from_msgpack(type_id, value)) else {
+                continue;
+            };
+            topic.timestamp = if timestamp == 0 { now } else { timestamp };
+            for (&other, client) in self.clients.iter_mut() {
+                if other != client_id && client.announced.contains(&topic.id) && client.wants_values(&name) == Some(true) {
+                    client.send_value(topic.id, topic.timestamp, &value);
+                }
+            }
+            topic.value = Some(value);
+        }
+    }
+
+    fn disconnect(&mut self, client_id: u64) {
+        let Some(client) = self.clients.get(&client_id) else {
+            return;
+        };
+        let publishers: Vec<i64> = client.publishers.keys().copied().collect();
+        for pubuid in publishers {
+            self.unpublish(client_id, pubuid);
+        }
+        self.clients.remove(&client_id);
+    }
+
+    fn flush(&mut self) {
+        for client in self.clients.values_mut() {
+            client.flush();
+        }
+    }
+}
+
+/// Accepts clients, each on a thread of its own.
+fn serve(listener: TcpListener, events: Sender<Event>, stop: Arc<AtomicBool>) {
+    let mut next_client = 0;
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let (id, events, stop) = (next_client, events.clone(), stop.clone());
+                next_client += 1;
+                thread::spawn(move || {
+                    serve_client(stream, id, &events, &stop).ok();
+                    events.send(Event::Disconnected(id)).ok();
+                });
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL * 10),
+            Err(_) => return,
+        }
+    }
+}
+
+/// Passes messages between a client and the app until either side closes the connection.
+// The handshake callback's error type is set by tungstenite
+#[allow(clippy::result_large_err)]
+fn serve_client(stream: TcpStream, id: u64, events: &Sender<Event>, stop: &AtomicBool) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    let mut name = String::new();
+    let mut socket = tungstenite::accept_hdr(stream, |request: &Request, mut response: Response| {
+        // Clients connect to /nt/<name>
+        name = request.uri().path().trim_start_matches("/nt/").to_owned();
+        let offered = request.headers().get("Sec-WebSocket-Protocol").and_then(|protocols| protocols.to_str().ok()).unwrap_or_default();
+        if let Some(protocol) = PROTOCOLS.iter().find(|protocol| offered.split(',').any(|offer| offer.trim() == **protocol)) {
+            response.headers_mut().insert("Sec-WebSocket-Protocol", HeaderValue::from_static(protocol));
+        }
+        Ok(response)
+    }).map_err(|err| anyhow!("{err}"))?;
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let (outgoing, outgoing_receiver) = mpsc::channel();
+    events.send(Event::Connected(id, name, outgoing)).ok();
+
+    while !stop.load(Ordering::Relaxed) {
+        for message in outgoing_receiver.try_iter() {
+            socket.write(message)?;
+        }
+        socket.flush()?;
+
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                events.send(Event::Text(id, text)).ok();
+            }
+            Ok(Message::Binary(data)) => {
+                events.send(Event::Binary(id, data)).ok();
+            }
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => (),
+            Err(tungstenite::Error::Io(err)) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => (),
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    socket.close(None).ok();
+    socket.flush().ok();
+    Ok(())
+}
+
+/// Name of the topic actuators can be controlled with from a client, such as robot code.
+pub fn control_topic(actuator: &str) -> String {
+    format!("{PREFIX}/Actuators/{actuator}/Control")
+}
+
+fn reset_network_tables(assembly: Res<Assembly>, mut network_tables: ResMut<NetworkTables>) {
+    network_tables.tree = KinematicTree::new(&assembly);
+}
+
+/// Handles what clients sent, and applies the controls they publish to the actuators.
+fn receive_messages(mut network_tables: ResMut<NetworkTables>, assembly: Res<Assembly>, mut actuator_states: ResMut<ActuatorStates>) {
+    let network_tables = network_tables.as_mut();
+    let events: Vec<Event> = match &network_tables.server {
+        Some(server) => server.events.lock().map(|events| events.try_iter().collect()).unwrap_or_default(),
+        None => vec![],
+    };
+    for event in events {
+        match event {
+            Event::Connected(id, name, outgoing) => {
+                network_tables.clients.insert(id, Client {
+                    name,
+                    outgoing,
+                    subscriptions: HashMap::new(),
+                    publishers: HashMap::new(),
+                    announced: HashSet::new(),
+                    text: vec![],
+                    binary: vec![],
+                });
+            }
+            Event::Text(id, text) => network_tables.handle_text(id, &text),
+            Event::Binary(id, data) => network_tables.handle_binary(id, &data),
+            Event::Disconnected(id) => network_tables.disconnect(id),
+        }
+    }
+
+    // Actuators stop once no client controls them
+    let mut controlled = vec![];
+    for (index, (actuator, state)) in assembly.actuators.iter().zip(actuator_states.0.iter_mut()).enumerate() {
+        let name = control_topic(&actuator.name);
+        let published = network_tables.topics.get(&name).is_some_and(|topic| !topic.publishers.is_empty());
+        match network_tables.get(&name) {
+            Some(&NtValue::Double(control)) if published => {
+                state.control = control;
+                controlled.push(index);
+            }
+            _ if network_tables.controlled.contains(&index) => state.control = 0.,
+            _ => (),
+        }
+    }
+    network_tables.controlled = controlled;
+
+    network_tables.flush();
+}
+
+/// Adds a link's pose to `values` as a Pose3d, which is Z-up, in meters and a quaternion from W to Z.
+fn push_pose(values: &mut Vec<f64>, pose: &Pose) {
+    let pose = pose.to_z_up();
+    let translation = pose.0 * METERS_PER_UNIT;
+    values.extend([translation.x, translation.y, translation.z, pose.1.w, pose.1.x, pose.1.y, pose.1.z]);
+}
+
+/// Publishes the state of the simulation.
+#[allow(clippy::too_many_arguments)]
+fn publish_sim_values(
+    mut network_tables: ResMut<NetworkTables>,
+    clock: Res<SimClock>,
+    assembly: Res<Assembly>,
+    joint_states: Res<JointStates>,
+    actuator_states: Res<ActuatorStates>,
+    battery: Res<Battery>,
+    map: Res<ElectricalMap>,
+    readings: Res<SensorReadings>,
+    mut log: ResMut<LogMessages>,
+) {
+    let network_tables = network_tables.as_mut();
+    if network_tables.clients.is_empty() || network_tables.last_publish.is_some_and(|last| last.elapsed() < PUBLISH_INTERVAL) {
+        return;
+    }
+    network_tables.last_publish = Some(Instant::now());
+
+    let mut values: Vec<(String, NtValue)> = vec![(format!("{PREFIX}/Time"), NtValue::Double(clock.time))];
+
+    // The first link is the robot, and the rest are its mechanisms, as AdvantageScope's components
+    let link_poses = network_tables.tree.link_poses(&assembly, &joint_states.positions());
+    if let Some(robot) = link_poses.first() {
+        let mut pose = vec![];
+        push_pose(&mut pose, robot);
+        values.push((format!("{PREFIX}/Robot/Pose"), NtValue::DoubleArray(pose)));
+        let mut components = vec![];
+        for link_pose in &link_poses[1..] {
+            push_pose(&mut components, &robot.inverse().then(link_pose));
+        }
+        values.push((format!("{PREFIX}/Robot/Components"), NtValue::DoubleArray(components)));
+    }
+
+    for (joint, state) in assembly.joints.iter().zip(&joint_states.0) {
+        values.push((format!("{PREFIX}/Joints/{}/Position", joint.name), NtValue::Double(state.position)));
+        values.push((format!("{PREFIX}/Joints/{}/Velocity", joint.name), NtValue::Double(state.velocity)));
+    }
+    for (actuator, state) in assembly.actuators.iter().zip(&actuator_states.0) {
+        values.push((format!("{PREFIX}/Actuators/{}/Voltage", actuator.name), NtValue::Double(state.voltage)));
+        values.push((format!("{PREFIX}/Actuators/{}/Current", actuator.name), NtValue::Double(state.current)));
+    }
+
+    values.push((format!("{PREFIX}/Battery/Voltage"), NtValue::Double(battery.voltage)));
+    values.push((format!("{PREFIX}/Battery/Current"), NtValue::Double(battery.current)));
+    values.push((format!("{PREFIX}/Battery/StateOfCharge"), NtValue::Double(battery.state_of_charge)));
+    values.push((format!("{PREFIX}/Battery/BrownedOut"), NtValue::Boolean(battery.browned_out)));
+
+    for (sensor, reading) in map.sensors.iter().zip(&readings.readings) {
+        let topic = |value: &str| format!("{PREFIX}/Sensors/{}/{value}", sensor.name);
+        match reading {
+            SensorReading::None => (),
+            SensorReading::Encoder { counts, rate } => {
+                values.push((topic("Counts"), NtValue::Int(*counts)));
+                values.push((topic("Rate"), NtValue::Double(*rate)));
+            }
+            SensorReading::LimitSwitch { pressed } => values.push((topic("Pressed"), NtValue::Boolean(*pressed))),
+            SensorReading::Imu(imu) => {
+                values.push((topic("Yaw"), NtValue::Double(imu.yaw)));
+                values.push((topic("Pitch"), NtValue::Double(imu.pitch)));
+                values.push((topic("Roll"), NtValue::Double(imu.roll)));
+                values.push((topic("AngularVelocity"), NtValue::DoubleArray(imu.angular_velocity.to_array().to_vec())));
+                values.push((topic("Acceleration"), NtValue::DoubleArray(imu.acceleration.to_array().to_vec())));
+            }
+        }
+    }
+
+    for (name, value) in values {
+        let type_name = value.type_name();
+        if !network_tables.set(&name, value) && network_tables.mismatched.insert(name.clone()) {
+            let published = network_tables.type_name(&name).unwrap_or_default();
+            log.warn("NetworkTables Type Mismatch", &format!("{name} is published as {published} by a client, so the simulation's {type_name} isn't published to it."));
+        }
+    }
+    network_tables.flush();
+}
+
+pub struct NetworkTablesPlugin;
+
+impl Plugin for NetworkTablesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkTables>()
+        .add_system(reset_network_tables
+            .run_if(resource_changed::<Assembly>())
+            .in_base_set(CoreSet::PreUpdate))
+        .add_system(receive_messages.in_base_set(CoreSet::PreUpdate))
+        .add_system(publish_sim_values.in_base_set(CoreSet::PostUpdate));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes `value` in a value message and decodes it the way messages from clients are.
+    fn round_trip(value: &NtValue) -> Option<NtValue> {
+        let message = rmpv::Value::Array(vec![1.into(), 0.into(), value.type_id().into(), value.to_msgpack()]);
+        let mut data = vec![];
+        rmpv::encode::write_value(&mut data, &message).unwrap();
+        let message = rmpv::decode::read_value(&mut data.as_slice()).unwrap();
+        let [_, _, type_id, value] = message.as_array()?.as_slice() else { return None };
+        NtValue::from_msgpack(type_id.as_u64()?, value)
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let values = [
+            NtValue::Boolean(true),
+            NtValue::Double(0.25),
+            NtValue::Int(-3),
+            NtValue::Float(1.5),
+            NtValue::String("arm".to_owned()),
+            NtValue::Raw(vec![1, 2, 3]),
+            NtValue::BooleanArray(vec![true, false]),
+            NtValue::DoubleArray(vec![0.5, -2.]),
+            NtValue::IntArray(vec![1, 1 << 40]),
+            NtValue::FloatArray(vec![0.125]),
+            NtValue::StringArray(vec!["a".to_owned(), "b".to_owned()]),
+        ];
+        for value in values {
+            assert_eq!(round_trip(&value), Some(value));
+        }
+    }
+
+    #[test]
+    fn decodes_whole_numbers_as_doubles() {
+        assert_eq!(NtValue::from_msgpack(1, &rmpv::Value::from(2)), Some(NtValue::Double(2.)));
+        assert_eq!(NtValue::from_msgpack(17, &rmpv::Value::Array(vec![1.into(), 0.5.into()])), Some(NtValue::DoubleArray(vec![1., 0.5])));
+    }
+
+    #[test]
+    fn refuses_values_of_another_type() {
+        let mut network_tables = NetworkTables::default();
+        assert!(network_tables.set("/MechSim/arm", NtValue::Double(1.)));
+        assert!(!network_tables.set("/MechSim/arm", NtValue::String("up".to_owned())));
+        assert_eq!(network_tables.type_name("/MechSim/arm"), Some("double"));
+    }
+}
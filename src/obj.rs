@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+
+use crate::mrr::Body;
+
+/// Reads the objects of a Wavefront OBJ file as bodies with their names, in the units of the file.
+pub fn load(path: &Path) -> Result<Vec<(Option<String>, Body)>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    parse(&text).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Faces of one object, as indices into the positions of the whole file.
+#[derive(Default)]
+struct Object {
+    name: Option<String>,
+    triangles: Vec<usize>,
+}
+
+impl Object {
+    /// Builds a body from the vertices the object uses. Normals are recomputed, since files often leave them out.
+    fn to_body(&self, positions: &[[f32; 3]]) -> Body {
+        let mut local_indices = HashMap::new();
+        let mut verticies = vec![];
+        let indicies = self.triangles.iter()
+            .map(|&index| *local_indices.entry(index).or_insert_with(|| {
+                verticies.extend(positions[index]);
+                (verticies.len() / 3 - 1) as i32
+            }))
+            .collect();
+
+        Body::from_triangles(verticies, indicies)
+    }
+}
+
+/// Objects are started by `o` and `g` lines. Polygons are split into triangle fans.
+pub fn parse(text: &str) -> Result<Vec<(Option<String>, Body)>> {
+    let mut positions: Vec<[f32; 3]> = vec![];
+    let mut objects = vec![Object::default()];
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let context = || format!("On line {}", number + 1);
+
+        match tokens.next() {
+            Some("v") => {
+                let mut position = [0.; 3];
+                for coordinate in &mut position {
+                    let token = tokens.next().context("Vertex is missing a coordinate").with_context(context)?;
+                    *coordinate = token.parse().with_context(|| format!("Invalid coordinate \"{token}\"")).with_context(context)?;
+                }
+                positions.push(position);
+            }
+            Some("f") => {
+                let face = tokens
+                    .map(|token| resolve_index(token, positions.len()))
+                    .collect::<Result<Vec<usize>>>()
+                    .with_context(context)?;
+                if face.len() < 3 {
+                    bail!("Face on line {} has fewer than 3 vertices", number + 1)
+                }
+
+                let object = objects.last_mut().unwrap();
+                for i in 1..face.len() - 1 {
+                    object.triangles.extend([face[0], face[i], face[i + 1]]);
+                }
+            }
+            Some("o") | Some("g") => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                if !objects.last().unwrap().triangles.is_empty() {
+                    objects.push(Object::default());
+                }
+                objects.last_mut().unwrap().name = Some(name).filter(|name| !name.is_empty());
+            }
+            _ => (),
+        }
+    }
+
+    Ok(objects.iter()
+        .filter(|object| !object.triangles.is_empty())
+        .map(|object| (object.name.clone(), object.to_body(&positions)))
+        .collect())
+}
+
+/// Converts a face vertex such as `3`, `3/1` or `-2//4` to an index into the positions read so far.
+fn resolve_index(token: &str, position_count: usize) -> Result<usize> {
+    let index = token.split('/').next().unwrap_or_default();
+    let index: i64 = index.parse().with_context(|| format!("Invalid vertex index \"{index}\""))?;
+
+    // Indices start at 1, negative ones count back from the last vertex
+    let resolved = if index < 0 {
+        position_count as i64 + index
+    } else {
+        index - 1
+    };
+
+    if resolved < 0 || resolved as usize >= position_count {
+        bail!("Vertex index {index} is out of range")
+    }
+    Ok(resolved as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_objects_and_polygons() {
+        let objects = parse("
+            # A quad and a triangle
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            v 0 0 1
+            o Plate
+            f 1/1/1 2/2/1 3/3/1 4/4/1
+            g Fin
+            f -5//1 -4//1 -1//1
+        ").unwrap();
+
+        assert_eq!(objects.len(), 2);
+        let (name, plate) = &objects[0];
+        assert_eq!(name.as_deref(), Some("Plate"));
+        assert_eq!(plate.indicies, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(plate.vertex_count(), 4);
+
+        // Only the vertices the object uses are kept
+        let (name, fin) = &objects[1];
+        assert_eq!(name.as_deref(), Some("Fin"));
+        assert_eq!(fin.verticies, [0., 0., 0., 1., 0., 0., 0., 0., 1.]);
+    }
+
+    #[test]
+    fn faces_before_a_name_are_unnamed() {
+        let objects = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\no Empty\n").unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].0, None);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        for (text, error) in [
+            ("v 0 0\n", "Vertex is missing a coordinate"),
+            ("v 0 zero 0\n", "Invalid coordinate \"zero\""),
+            ("v 0 0 0\nv 1 0 0\nf 1 2\n", "Face on line 3 has fewer than 3 vertices"),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n", "Vertex index 4 is out of range"),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 -4\n", "Vertex index -4 is out of range"),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 x\n", "Invalid vertex index \"x\""),
+        ] {
+            let err = parse(text).unwrap_err();
+            assert!(err.chain().any(|cause| cause.to_string() == error), "{text:?}: {err:#}");
+        }
+    }
+}
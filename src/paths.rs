@@ -0,0 +1,89 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use directories::ProjectDirs;
+
+/// Environment variable that overrides the root directory MechSim stores its data in.
+pub const DATA_DIR_ENV: &str = "MECHSIM_DATA_DIR";
+
+static PATHS: OnceLock<Paths> = OnceLock::new();
+
+/// Locations MechSim reads and writes its files from.
+///
+/// By default these follow the platform conventions (XDG base directories on Linux,
+/// `AppData` on Windows and `Library` on macOS). If a data directory is given on the
+/// command line or through [`DATA_DIR_ENV`], everything is stored under that directory instead.
+#[derive(Debug, Clone)]
+pub struct Paths {
+    data: PathBuf,
+    cache: PathBuf,
+    settings: PathBuf,
+}
+
+impl Paths {
+    /// Resolves the directories, preferring `data_dir` over [`DATA_DIR_ENV`] over the platform defaults.
+    pub fn resolve(data_dir: Option<PathBuf>) -> Self {
+        let data_dir = data_dir.or_else(|| {
+            std::env::var_os(DATA_DIR_ENV)
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+        });
+
+        if let Some(root) = data_dir {
+            return Self::with_root(root);
+        }
+
+        match ProjectDirs::from("", "", "MechSim") {
+            Some(dirs) => Self {
+                data: dirs.data_dir().to_owned(),
+                cache: dirs.cache_dir().to_owned(),
+                settings: dirs.config_dir().to_owned(),
+            },
+            // No home directory could be found, fall back to the working directory
+            None => Self::with_root(PathBuf::from(".mechsim")),
+        }
+    }
+
+    fn with_root(root: PathBuf) -> Self {
+        Self {
+            cache: root.join("cache"),
+            settings: root.join("settings"),
+            data: root,
+        }
+    }
+
+    pub fn data(&self) -> &Path {
+        &self.data
+    }
+
+    pub fn assemblies(&self) -> PathBuf {
+        self.data.join("assemblies")
+    }
+
+    pub fn logs(&self) -> PathBuf {
+        self.data.join("log")
+    }
+
+    pub fn telemetry(&self) -> PathBuf {
+        self.data.join("telemetry")
+    }
+
+    pub fn cache(&self) -> &Path {
+        &self.cache
+    }
+
+    pub fn settings(&self) -> &Path {
+        &self.settings
+    }
+}
+
+/// Sets the directories used for the rest of the program. Must be called before [`get`]
+/// for `data_dir` to have any effect.
+pub fn init(data_dir: Option<PathBuf>) -> &'static Paths {
+    PATHS.get_or_init(|| Paths::resolve(data_dir))
+}
+
+/// Returns the directories MechSim uses, resolving them from the environment if [`init`] was never called.
+pub fn get() -> &'static Paths {
+    init(None)
+}
@@ -0,0 +1,319 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+use crate::kinematics::KinematicTree;
+use crate::mrr::{Assembly, Coupling, JointType, GRAVITY, METERS_PER_UNIT, UP};
+use crate::sim::{joint_position_channel, JointState, JointStates, SimSet, SimSettings, SimStep, Telemetry};
+
+#[derive(Resource, Debug, Clone)]
+pub struct PhysicsSettings {
+    /// Inertia every movable joint has on top of the parts it carries, such as its gearbox
+    pub joint_inertia: f64,
+    /// Viscous friction of each joint
+    pub joint_damping: f64,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            joint_inertia: 0.05,
+            joint_damping: 0.01,
+        }
+    }
+}
+
+/// How hard a joint is to speed up and how gravity pulls on it, from the parts on its child side.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct JointLoad {
+    /// About the joint's axis in kg·m², or along it in kg for sliders
+    inertia: f64,
+    /// Torque in N·m, or force in N for sliders
+    gravity: f64,
+}
+
+/// Adds up the mass properties of the parts each joint carries, with the joints at `positions`. Every joint
+/// between a part and the root carries it.
+fn joint_loads(assembly: &Assembly, tree: &KinematicTree, positions: &[f64]) -> Vec<JointLoad> {
+    let frames = tree.frames(assembly, positions);
+    let mut loads = vec![JointLoad::default(); assembly.joints.len()];
+    for (part, &part_link) in assembly.parts.iter().zip(&tree.part_links) {
+        let Some(inertial) = part.inertial else {
+            continue;
+        };
+        let motion = frames[part_link].then(&tree.rest_frame(assembly, part_link).inverse());
+        let center = motion.transform_point(inertial.center_of_mass);
+        let weight = -UP * GRAVITY * inertial.mass;
+        // The principal axes aren't stored, so the part turns about its center of mass with their mean moment
+        let own_inertia = inertial.inertia.dot(DVec3::ONE) / 3.;
+
+        let mut link = part_link;
+        while let Some((parent, joint)) = tree.links[link].parent {
+            let axis = frames[link].1 * DVec3::Z;
+            let load = &mut loads[joint];
+            match assembly.joints[joint].joint_type {
+                JointType::Revolute => {
+                    let arm = (center - frames[link].0) * METERS_PER_UNIT;
+                    load.inertia += own_inertia + inertial.mass * arm.cross(axis).length_squared();
+                    load.gravity += arm.cross(weight).dot(axis);
+                }
+                JointType::Slider => {
+                    load.inertia += inertial.mass;
+                    load.gravity += weight.dot(axis);
+                }
+                JointType::Rigid => {}
+            }
+            link = parent;
+        }
+    }
+    loads
+}
+
+/// Passes over the couplings each step, so chains of coupled joints settle.
+const COUPLING_ITERATIONS: usize = 4;
+
+/// How far the second joint of `coupling` is from where the first puts it, less the backlash.
+fn coupling_error(coupling: &Coupling, states: &[JointState]) -> f64 {
+    let [first, second] = coupling.joints;
+    let offset = states[second].position - coupling.ratio * states[first].position;
+    let play = coupling.backlash / 2.;
+    offset - offset.clamp(-play, play)
+}
+
+/// Changes the velocities of the joints of `coupling` so the second follows the first, losing the power the
+/// coupling doesn't pass on. `inertias` is indexed like the joints.
+fn couple_velocities(coupling: &Coupling, states: &mut [JointState], inertias: &[f64]) {
+    let [first, second] = coupling.joints;
+    let ratio = coupling.ratio;
+    let relative = states[second].velocity - ratio * states[first].velocity;
+
+    // Within the backlash the joints move freely until the teeth meet again
+    let play = coupling.backlash / 2.;
+    let offset = states[second].position - ratio * states[first].position;
+    if play > 0. && !(offset.abs() >= play - 1e-9 && offset * relative > 0.) {
+        return;
+    }
+
+    // An impulse taken from the first joint reaches the second times `gain`, through the ratio
+    let efficiency = coupling.efficiency.clamp(0.01, 1.);
+    let [first_inertia, second_inertia] = [inertias[first], inertias[second]];
+    let impulse = |gain: f64| -relative / (gain / (ratio * second_inertia) + ratio / first_inertia);
+    let mut gain = efficiency;
+    let mut change_first = impulse(gain) / first_inertia;
+    if change_first * (states[first].velocity - change_first) < 0. {
+        // The second joint drives the first, so the loss is on the first's side
+        gain = 1. / efficiency;
+        change_first = impulse(gain) / first_inertia;
+    }
+    states[first].velocity -= change_first;
+    states[second].velocity += gain * change_first * first_inertia / (ratio * second_inertia);
+}
+
+/// Moves the joints of `coupling` back into step, splitting the correction between them so the lighter one
+/// moves further.
+fn couple_positions(coupling: &Coupling, states: &mut [JointState], inertias: &[f64]) {
+    let [first, second] = coupling.joints;
+    let error = coupling_error(coupling, states);
+    let scale = coupling.ratio * coupling.ratio / inertias[first] + 1. / inertias[second];
+    states[first].position += coupling.ratio * error / (inertias[first] * scale);
+    states[second].position -= error / (inertias[second] * scale);
+}
+
+/// Integrates each movable joint as the inertia of the parts it carries, driven by the efforts applied to it and
+/// gravity. Joints only affect each other through couplings and the parts they share, not through the forces
+/// of an articulation, and contacts are left out.
+fn step_joints(
+    assembly: Res<Assembly>,
+    settings: Res<SimSettings>,
+    physics: Res<PhysicsSettings>,
+    mut tree: Local<KinematicTree>,
+    mut states: ResMut<JointStates>,
+) {
+    if assembly.is_changed() {
+        *tree = KinematicTree::new(&assembly);
+    }
+    let loads = joint_loads(&assembly, &tree, &states.positions());
+    let inertias: Vec<f64> = loads.iter().map(|load| load.inertia + physics.joint_inertia).collect();
+
+    let dt = settings.timestep;
+    let moves = |joint: usize| joint < states.0.len() && assembly.joints.get(joint).is_some_and(|joint| joint.joint_type != JointType::Rigid);
+    let couplings: Vec<&Coupling> = assembly.couplings.iter()
+        .filter(|coupling| coupling.ratio != 0. && coupling.joints[0] != coupling.joints[1] && coupling.joints.into_iter().all(moves))
+        .collect();
+
+    for ((joint, state), (load, inertia)) in assembly.joints.iter().zip(states.0.iter_mut()).zip(loads.iter().zip(&inertias)) {
+        if joint.joint_type != JointType::Rigid {
+            // Damping is integrated implicitly so it stays stable however large it is, as it is for motors
+            // behind large reductions
+            let damping = physics.joint_damping + state.damping;
+            state.velocity = (inertia * state.velocity + (state.effort + load.gravity) * dt) / (inertia + damping * dt);
+        }
+    }
+    for _ in 0..COUPLING_ITERATIONS {
+        for coupling in &couplings {
+            couple_velocities(coupling, &mut states.0, &inertias);
+        }
+    }
+
+    for (joint, state) in assembly.joints.iter().zip(states.0.iter_mut()) {
+        if joint.joint_type != JointType::Rigid {
+            state.position += state.velocity * dt;
+        }
+    }
+    for _ in 0..COUPLING_ITERATIONS {
+        for coupling in &couplings {
+            couple_positions(coupling, &mut states.0, &inertias);
+        }
+    }
+
+    for (joint, state) in assembly.joints.iter().zip(states.0.iter_mut()) {
+        if let Some((lower, upper)) = joint.limits.filter(|_| joint.joint_type != JointType::Rigid) {
+            if state.position < lower || state.position > upper {
+                state.position = state.position.clamp(lower, upper);
+                state.velocity = 0.;
+            }
+        }
+        state.effort = 0.;
+        state.damping = 0.;
+    }
+}
+
+fn record_joint_telemetry(states: Res<JointStates>, mut telemetry: ResMut<Telemetry>) {
+    for (i, state) in states.0.iter().enumerate() {
+        telemetry.record(&joint_position_channel(i), state.position);
+        telemetry.record(&format!("joint{i}.velocity"), state.velocity);
+    }
+}
+
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhysicsSettings>()
+        .add_system(step_joints.in_schedule(SimStep).in_set(SimSet::Physics))
+        .add_system(record_joint_telemetry.in_schedule(SimStep).in_set(SimSet::Telemetry));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use bevy::math::DQuat;
+
+    use super::*;
+    use crate::mrr::{CouplingKind, Inertial, Joint, Part, Pose};
+
+    fn part(name: &str, rigid_group: u32, inertial: Option<Inertial>) -> Part {
+        Part {
+            name: name.to_owned(),
+            pose: Pose::IDENTITY,
+            joint_references: vec![0],
+            rigid_group_references: vec![rigid_group],
+            bodies: vec![],
+            inertial,
+            hardware: false,
+            appearance: None,
+            material: None,
+            physical_material: None,
+        }
+    }
+
+    /// A base and a 2 kg arm with its center of mass 1 m along Z from a joint at the origin, turned by `rotation`.
+    fn arm(joint_type: JointType, rotation: DQuat) -> Assembly {
+        let inertial = Inertial { mass: 2., center_of_mass: DVec3::Z / METERS_PER_UNIT, inertia: DVec3::splat(0.1) };
+        Assembly {
+            parts: vec![part("Base", 0, None), part("Arm", 1, Some(inertial))],
+            joints: vec![Joint { name: "Shoulder".to_owned(), joint_type, pose: Pose(DVec3::ZERO, rotation), limits: None }],
+            ..Default::default()
+        }
+    }
+
+    fn loads(assembly: &Assembly, position: f64) -> JointLoad {
+        joint_loads(assembly, &KinematicTree::new(assembly), &[position])[0]
+    }
+
+    #[test]
+    fn revolute_joints_carry_their_parts() {
+        // About X, with the arm held out level
+        let assembly = arm(JointType::Revolute, DQuat::from_rotation_y(FRAC_PI_2));
+
+        let level = loads(&assembly, 0.);
+        assert!((level.inertia - 2.1).abs() < 1e-9, "{level:?}");
+        assert!((level.gravity - 2. * GRAVITY).abs() < 1e-9, "{level:?}");
+
+        // Turning about +X swings the arm down, where gravity no longer turns it
+        let hanging = loads(&assembly, FRAC_PI_2);
+        assert!((hanging.inertia - 2.1).abs() < 1e-9, "{hanging:?}");
+        assert!(hanging.gravity.abs() < 1e-9, "{hanging:?}");
+    }
+
+    #[test]
+    fn sliders_carry_their_parts() {
+        let vertical = loads(&arm(JointType::Slider, DQuat::from_rotation_x(-FRAC_PI_2)), 0.);
+        assert_eq!(vertical.inertia, 2.);
+        assert!((vertical.gravity + 2. * GRAVITY).abs() < 1e-9, "{vertical:?}");
+
+        let level = loads(&arm(JointType::Slider, DQuat::IDENTITY), 0.);
+        assert!(level.gravity.abs() < 1e-9, "{level:?}");
+    }
+
+    #[test]
+    fn parts_without_mass_properties_add_nothing() {
+        let mut assembly = arm(JointType::Revolute, DQuat::IDENTITY);
+        assembly.parts[1].inertial = None;
+        assert_eq!(loads(&assembly, 0.), JointLoad::default());
+    }
+
+    fn moving(velocities: [f64; 2]) -> Vec<JointState> {
+        velocities.map(|velocity| JointState { velocity, ..default() }).to_vec()
+    }
+
+    #[test]
+    fn couplings_make_the_second_joint_follow() {
+        let gear = Coupling { efficiency: 1., ..Coupling::new("Gears", CouplingKind::Gear, [0, 1]) };
+        let mut states = moving([1., 0.]);
+        couple_velocities(&gear, &mut states, &[1., 1.]);
+        assert!((states[0].velocity - 0.5).abs() < 1e-9, "{states:?}");
+        assert!((states[1].velocity + 0.5).abs() < 1e-9, "{states:?}");
+
+        // A heavy joint hardly slows down bringing a light one along
+        let mut states = moving([1., 0.]);
+        couple_velocities(&gear, &mut states, &[100., 1.]);
+        assert!(states[0].velocity > 0.98, "{states:?}");
+        assert!((states[1].velocity + states[0].velocity).abs() < 1e-9, "{states:?}");
+    }
+
+    #[test]
+    fn inefficient_couplings_lose_speed() {
+        let belt = Coupling { ratio: 1., efficiency: 0.5, ..Coupling::new("Belt", CouplingKind::Belt, [0, 1]) };
+        let mut states = moving([1., 0.]);
+        couple_velocities(&belt, &mut states, &[1., 1.]);
+        assert!((states[0].velocity - 1. / 3.).abs() < 1e-9, "{states:?}");
+        assert!((states[1].velocity - states[0].velocity).abs() < 1e-9, "{states:?}");
+
+        // Driven backwards, the loss is on the first joint's side
+        let mut states = moving([0., 1.]);
+        couple_velocities(&belt, &mut states, &[1., 1.]);
+        assert!((states[1].velocity - 1. / 3.).abs() < 1e-9, "{states:?}");
+        assert!((states[0].velocity - states[1].velocity).abs() < 1e-9, "{states:?}");
+    }
+
+    #[test]
+    fn backlash_lets_joints_move_apart() {
+        let chain = Coupling { ratio: 1., backlash: 0.2, ..Coupling::new("Chain", CouplingKind::Chain, [0, 1]) };
+
+        // Within the play, nothing pushes the joints together
+        let mut states = moving([1., 0.]);
+        couple_velocities(&chain, &mut states, &[1., 1.]);
+        assert_eq!((states[0].velocity, states[1].velocity), (1., 0.));
+        states[1].position = 0.05;
+        couple_positions(&chain, &mut states, &[1., 1.]);
+        assert_eq!((states[0].position, states[1].position), (0., 0.05));
+
+        // Past it, they are pulled back to its edge
+        states[1].position = 0.3;
+        couple_positions(&chain, &mut states, &[1., 1.]);
+        assert!(coupling_error(&chain, &states).abs() < 1e-9, "{states:?}");
+        assert!((states[1].position - states[0].position - 0.1).abs() < 1e-9, "{states:?}");
+    }
+}
@@ -0,0 +1,110 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+
+use crate::mrr::Body;
+
+/// Number of segments around the circumference of round shapes.
+const SEGMENTS: usize = 24;
+
+fn push_vertex(verticies: &mut Vec<f32>, vertex: Vec3) -> i32 {
+    verticies.extend(vertex.to_array());
+    (verticies.len() / 3 - 1) as i32
+}
+
+/// Box centered on the origin.
+pub fn cuboid(half_extents: Vec3) -> Body {
+    let mut verticies = vec![];
+    let mut indicies = vec![];
+
+    // Each face gets its own vertices so the edges stay sharp
+    for axis in 0..3 {
+        for sign in [-1., 1.] {
+            let normal = Vec3::AXES[axis] * sign;
+            let u = Vec3::AXES[(axis + 1) % 3] * sign;
+            let v = Vec3::AXES[(axis + 2) % 3];
+
+            let corners = [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)].map(|(a, b)| {
+                push_vertex(&mut verticies, (normal + u * a + v * b) * half_extents)
+            });
+            indicies.extend([corners[0], corners[1], corners[2], corners[0], corners[2], corners[3]]);
+        }
+    }
+
+    Body::from_triangles(verticies, indicies)
+}
+
+/// Cylinder centered on the origin, along the Z axis.
+pub fn cylinder(radius: f32, half_length: f32) -> Body {
+    let mut verticies = vec![];
+    let mut indicies = vec![];
+
+    let ring = |z: f32| (0..SEGMENTS).map(move |i| {
+        let angle = i as f32 / SEGMENTS as f32 * TAU;
+        Vec3::new(radius * angle.cos(), radius * angle.sin(), z)
+    });
+
+    // Sides
+    let bottom: Vec<i32> = ring(-half_length).map(|vertex| push_vertex(&mut verticies, vertex)).collect();
+    let top: Vec<i32> = ring(half_length).map(|vertex| push_vertex(&mut verticies, vertex)).collect();
+    for i in 0..SEGMENTS {
+        let next = (i + 1) % SEGMENTS;
+        indicies.extend([bottom[i], bottom[next], top[next], bottom[i], top[next], top[i]]);
+    }
+
+    // Caps, with separate vertices so their normals point along the axis
+    for (z, flip) in [(-half_length, true), (half_length, false)] {
+        let center = push_vertex(&mut verticies, Vec3::new(0., 0., z));
+        let rim: Vec<i32> = ring(z).map(|vertex| push_vertex(&mut verticies, vertex)).collect();
+        for i in 0..SEGMENTS {
+            let next = (i + 1) % SEGMENTS;
+            if flip {
+                indicies.extend([center, rim[next], rim[i]]);
+            } else {
+                indicies.extend([center, rim[i], rim[next]]);
+            }
+        }
+    }
+
+    Body::from_triangles(verticies, indicies)
+}
+
+/// Ellipsoid centered on the origin. Equal radii make a sphere.
+pub fn ellipsoid(radii: Vec3) -> Body {
+    let rings = SEGMENTS / 2;
+    let mut verticies = vec![];
+    let mut indicies = vec![];
+
+    for ring in 0..=rings {
+        let polar = ring as f32 / rings as f32 * PI;
+        for segment in 0..=SEGMENTS {
+            let azimuth = segment as f32 / SEGMENTS as f32 * TAU;
+            let direction = Vec3::new(polar.sin() * azimuth.cos(), polar.sin() * azimuth.sin(), polar.cos());
+            push_vertex(&mut verticies, direction * radii);
+        }
+    }
+
+    let stride = (SEGMENTS + 1) as i32;
+    for ring in 0..rings as i32 {
+        for segment in 0..SEGMENTS as i32 {
+            let a = ring * stride + segment;
+            let b = a + stride;
+            indicies.extend([a, b, a + 1, a + 1, b, b + 1]);
+        }
+    }
+
+    Body::from_triangles(verticies, indicies)
+}
+
+/// Capsule centered on the origin, along the Z axis, as a cylinder and two spheres.
+pub fn capsule(radius: f32, half_length: f32) -> Vec<Body> {
+    let mut bodies = vec![cylinder(radius, half_length)];
+    for z in [-half_length, half_length] {
+        let mut cap = ellipsoid(Vec3::splat(radius));
+        for vertex in cap.verticies.chunks_exact_mut(3) {
+            vertex[2] += z;
+        }
+        bodies.push(cap);
+    }
+    bodies
+}
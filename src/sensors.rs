@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use bevy::math::{DQuat, DVec3, EulerRot};
+use bevy::prelude::*;
+
+use crate::electrical::{ElectricalMap, EncoderSettings, LimitTrigger, Sensor, SensorKind};
+use crate::interference::PartContact;
+use crate::kinematics::KinematicTree;
+use crate::mrr::{Assembly, Joint, JointType, Pose, GRAVITY, METERS_PER_UNIT, UP, Z_UP};
+use crate::sim::{JointState, JointStates, SimSet, SimSettings, SimStep, Telemetry};
+
+/// What an IMU measures, in Z-up axes like WPILib's, turned with the part from where it sits in the
+/// assembly, so a part that hasn't moved reads zero yaw, pitch and roll.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ImuReading {
+    /// Radians about Z
+    pub yaw: f64,
+    /// Radians about Y
+    pub pitch: f64,
+    /// Radians about X
+    pub roll: f64,
+    /// Radians per second about each of the IMU's axes
+    pub angular_velocity: DVec3,
+    /// m/s² along each of the IMU's axes, including gravity, as an accelerometer measures it
+    pub acceleration: DVec3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SensorReading {
+    /// The sensor isn't attached to anything the robot has
+    #[default]
+    None,
+    Encoder {
+        counts: i64,
+        /// Counts per second
+        rate: f64,
+    },
+    LimitSwitch {
+        pressed: bool,
+    },
+    Imu(ImuReading),
+}
+
+/// Where a part was during the last steps, to tell how fast it's turning and accelerating.
+#[derive(Debug, Clone, Copy)]
+struct PartMotion {
+    part: usize,
+    orientation: DQuat,
+    /// Meters
+    position: DVec3,
+    velocity: Option<DVec3>,
+}
+
+/// Small xorshift generator, seeded the same every run so noisy simulations can be repeated.
+#[derive(Debug, Clone)]
+struct Noise(u64);
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+impl Noise {
+    /// Uniform in [0, 1)
+    fn uniform(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Normally distributed with a standard deviation of 1
+    fn gaussian(&mut self) -> f64 {
+        let radius = (-2. * (1. - self.uniform()).ln()).sqrt();
+        radius * (2. * PI * self.uniform()).cos()
+    }
+}
+
+/// The last reading of every sensor in the [`ElectricalMap`], indexed the same as [`ElectricalMap::sensors`].
+#[derive(Resource, Default)]
+pub struct SensorReadings {
+    pub readings: Vec<SensorReading>,
+    tree: KinematicTree,
+    contacts: HashMap<[usize; 2], PartContact>,
+    motions: Vec<Option<PartMotion>>,
+    noise: Noise,
+}
+
+/// Converts the position of a joint into encoder counts, before rounding.
+fn encoder_counts(settings: &EncoderSettings, joint_type: JointType, position: f64) -> f64 {
+    match joint_type {
+        JointType::Slider => position * settings.cpr,
+        _ => position / (2. * PI) * settings.cpr,
+    }
+}
+
+impl SensorReadings {
+    fn read_encoder(&mut self, sensor: &Sensor, assembly: &Assembly, joint_states: &JointStates) -> SensorReading {
+        let Some((joint, state)) = find_joint(sensor, assembly, joint_states) else {
+            return SensorReading::None;
+        };
+        let settings = &sensor.encoder;
+        let mut counts = encoder_counts(settings, joint.joint_type, state.position);
+        if settings.noise > 0. {
+            counts += settings.noise * self.noise.gaussian();
+        }
+        let mut counts = counts.round() as i64;
+        if settings.absolute && settings.cpr >= 1. {
+            counts = counts.rem_euclid(settings.cpr.round() as i64);
+        }
+        SensorReading::Encoder { counts, rate: encoder_counts(settings, joint.joint_type, state.velocity) }
+    }
+
+    fn read_limit_switch(&mut self, sensor: &Sensor, assembly: &Assembly, joint_states: &JointStates, link_poses: &[Pose]) -> SensorReading {
+        let pressed = match &sensor.trigger {
+            LimitTrigger::Below(threshold) => find_joint(sensor, assembly, joint_states).map(|(_, state)| state.position < *threshold),
+            LimitTrigger::Above(threshold) => find_joint(sensor, assembly, joint_states).map(|(_, state)| state.position > *threshold),
+            LimitTrigger::Contact(other) => {
+                let part = find_part(sensor.attached_to.as_deref(), assembly);
+                let other = find_part(Some(other), assembly);
+                part.zip(other).map(|(part, other)| {
+                    let tree = &self.tree;
+                    self.contacts.entry([part, other])
+                        .or_insert_with(|| PartContact::new(assembly, tree, [part, other]))
+                        .touching(link_poses)
+                })
+            }
+        };
+        pressed.map_or(SensorReading::None, |pressed| SensorReading::LimitSwitch { pressed })
+    }
+
+    fn read_imu(&mut self, index: usize, sensor: &Sensor, assembly: &Assembly, part_poses: &[Pose], dt: f64) -> SensorReading {
+        let part = find_part(sensor.attached_to.as_deref(), assembly);
+        let Some((part, pose)) = part.and_then(|part| Some((part, part_poses.get(part)?))) else {
+            self.motions[index] = None;
+            return SensorReading::None;
+        };
+        let orientation = pose.1;
+        let position = pose.transform_point(assembly.parts[part].pose.0) * METERS_PER_UNIT;
+
+        // The first step after the IMU is attached has nothing to compare against, so it reads as still
+        let previous = self.motions[index].filter(|motion| motion.part == part);
+        let (angular_velocity, velocity, acceleration) = match previous {
+            Some(previous) => {
+                let velocity = (position - previous.position) / dt;
+                let acceleration = previous.velocity.map_or(DVec3::ZERO, |previous| (velocity - previous) / dt);
+                ((previous.orientation.inverse() * orientation).to_scaled_axis() / dt, Some(velocity), acceleration)
+            }
+            None => (DVec3::ZERO, None, DVec3::ZERO),
+        };
+        self.motions[index] = Some(PartMotion { part, orientation, position, velocity });
+
+        // An accelerometer at rest measures the floor pushing it up
+        let acceleration = orientation.inverse() * (acceleration + UP * GRAVITY);
+        let to_z_up = Z_UP.inverse();
+        let (yaw, pitch, roll) = (to_z_up * orientation * Z_UP).to_euler(EulerRot::ZYX);
+        SensorReading::Imu(ImuReading {
+            yaw,
+            pitch,
+            roll,
+            angular_velocity: to_z_up * angular_velocity,
+            acceleration: to_z_up * acceleration,
+        })
+    }
+}
+
+fn find_joint<'a>(sensor: &Sensor, assembly: &'a Assembly, joint_states: &'a JointStates) -> Option<(&'a Joint, &'a JointState)> {
+    let name = sensor.attached_to.as_deref()?;
+    let index = assembly.joints.iter().position(|joint| joint.name == name)?;
+    Some((&assembly.joints[index], joint_states.0.get(index)?))
+}
+
+fn find_part(name: Option<&str>, assembly: &Assembly) -> Option<usize> {
+    let name = name?;
+    assembly.parts.iter().position(|part| part.name == name)
+}
+
+fn reset_sensor_readings(assembly: Res<Assembly>, mut readings: ResMut<SensorReadings>) {
+    *readings = SensorReadings { tree: KinematicTree::new(&assembly), ..default() };
+}
+
+fn read_sensors(
+    assembly: Res<Assembly>,
+    map: Res<ElectricalMap>,
+    settings: Res<SimSettings>,
+    joint_states: Res<JointStates>,
+    mut readings: ResMut<SensorReadings>,
+) {
+    let readings = readings.as_mut();
+    readings.readings.resize(map.sensors.len(), SensorReading::None);
+    readings.motions.resize(map.sensors.len(), None);
+
+    let link_poses = readings.tree.link_poses(&assembly, &joint_states.positions());
+    let part_poses: Vec<Pose> = readings.tree.part_links.iter().map(|&link| link_poses[link]).collect();
+    for (index, sensor) in map.sensors.iter().enumerate() {
+        readings.readings[index] = match sensor.kind {
+            SensorKind::Encoder => readings.read_encoder(sensor, &assembly, &joint_states),
+            SensorKind::LimitSwitch => readings.read_limit_switch(sensor, &assembly, &joint_states, &link_poses),
+            SensorKind::Imu => readings.read_imu(index, sensor, &assembly, &part_poses, settings.timestep),
+        };
+    }
+}
+
+fn record_sensor_telemetry(readings: Res<SensorReadings>, mut telemetry: ResMut<Telemetry>) {
+    for (i, reading) in readings.readings.iter().enumerate() {
+        match reading {
+            SensorReading::None => (),
+            SensorReading::Encoder { counts, rate } => {
+                telemetry.record(&format!("sensor{i}.counts"), *counts as f64);
+                telemetry.record(&format!("sensor{i}.rate"), *rate);
+            }
+            SensorReading::LimitSwitch { pressed } => telemetry.record(&format!("sensor{i}.pressed"), *pressed as u8 as f64),
+            SensorReading::Imu(imu) => {
+                telemetry.record(&format!("sensor{i}.yaw"), imu.yaw);
+                telemetry.record(&format!("sensor{i}.pitch"), imu.pitch);
+                telemetry.record(&format!("sensor{i}.roll"), imu.roll);
+                for (axis, rate, acceleration) in [("x", imu.angular_velocity.x, imu.acceleration.x), ("y", imu.angular_velocity.y, imu.acceleration.y), ("z", imu.angular_velocity.z, imu.acceleration.z)] {
+                    telemetry.record(&format!("sensor{i}.rate_{axis}"), rate);
+                    telemetry.record(&format!("sensor{i}.accel_{axis}"), acceleration);
+                }
+            }
+        }
+    }
+}
+
+pub struct SensorPlugin;
+
+impl Plugin for SensorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SensorReadings>()
+        .add_system(reset_sensor_readings
+            .run_if(resource_changed::<Assembly>())
+            .in_base_set(CoreSet::PreUpdate))
+        .add_system(read_sensors.in_schedule(SimStep).in_set(SimSet::Sensors))
+        .add_system(record_sensor_telemetry.in_schedule(SimStep).in_set(SimSet::Telemetry));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::electrical::Port;
+    use crate::mrr::Part;
+
+    fn sensor(kind: SensorKind, attached_to: &str) -> Sensor {
+        Sensor {
+            name: "Sensor".to_owned(),
+            kind,
+            port: Port::Dio(0),
+            attached_to: Some(attached_to.to_owned()),
+            encoder: EncoderSettings::default(),
+            trigger: LimitTrigger::default(),
+        }
+    }
+
+    fn assembly() -> Assembly {
+        Assembly {
+            parts: vec![Part {
+                name: "Arm".to_owned(),
+                pose: Pose::IDENTITY,
+                joint_references: vec![0],
+                rigid_group_references: vec![0],
+                bodies: vec![],
+                inertial: None,
+                hardware: false,
+                appearance: None,
+                material: None,
+                physical_material: None,
+            }],
+            joints: vec![Joint { name: "Shoulder".to_owned(), joint_type: JointType::Revolute, pose: Pose::IDENTITY, limits: None }],
+            ..Default::default()
+        }
+    }
+
+    fn read_encoder(encoder: EncoderSettings, position: f64, velocity: f64) -> SensorReading {
+        let sensor = Sensor { encoder, ..sensor(SensorKind::Encoder, "Shoulder") };
+        let states = JointStates(vec![JointState { position, velocity, ..default() }]);
+        SensorReadings::default().read_encoder(&sensor, &assembly(), &states)
+    }
+
+    #[test]
+    fn encoders_count_turns() {
+        let encoder = EncoderSettings { cpr: 100., ..default() };
+        assert_eq!(read_encoder(encoder.clone(), 3. * PI, -PI), SensorReading::Encoder { counts: 150, rate: -50. });
+        assert_eq!(read_encoder(encoder, -PI, 0.), SensorReading::Encoder { counts: -50, rate: 0. });
+    }
+
+    #[test]
+    fn absolute_encoders_wrap_within_a_turn() {
+        let encoder = EncoderSettings { absolute: true, cpr: 100., ..default() };
+        assert!(matches!(read_encoder(encoder.clone(), 3. * PI, 0.), SensorReading::Encoder { counts: 50, .. }));
+        assert!(matches!(read_encoder(encoder, -PI / 2., 0.), SensorReading::Encoder { counts: 75, .. }));
+    }
+
+    #[test]
+    fn encoders_on_missing_joints_read_nothing() {
+        let sensor = sensor(SensorKind::Encoder, "Elbow");
+        let reading = SensorReadings::default().read_encoder(&sensor, &assembly(), &JointStates(vec![JointState::default()]));
+        assert_eq!(reading, SensorReading::None);
+    }
+
+    fn read_imus(orientations: &[DQuat]) -> ImuReading {
+        let sensor = sensor(SensorKind::Imu, "Arm");
+        let mut readings = SensorReadings { motions: vec![None], ..default() };
+        let mut reading = SensorReading::None;
+        for &orientation in orientations {
+            reading = readings.read_imu(0, &sensor, &assembly(), &[Pose(DVec3::ZERO, orientation)], 0.01);
+        }
+        match reading {
+            SensorReading::Imu(imu) => imu,
+            other => panic!("Expected an IMU reading, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn level_imus_measure_gravity_up_their_z_axis() {
+        let imu = read_imus(&[DQuat::IDENTITY, DQuat::IDENTITY, DQuat::IDENTITY]);
+        assert!(imu.acceleration.abs_diff_eq(DVec3::Z * GRAVITY, 1e-9), "{:?}", imu.acceleration);
+        assert_eq!((imu.yaw, imu.pitch, imu.roll), (0., 0., 0.));
+    }
+
+    #[test]
+    fn imus_turning_about_up_read_yaw() {
+        let turned = DQuat::from_axis_angle(UP, 0.01);
+        let imu = read_imus(&[DQuat::IDENTITY, turned]);
+        assert!((imu.yaw - 0.01).abs() < 1e-9);
+        assert!(imu.pitch.abs() < 1e-9 && imu.roll.abs() < 1e-9);
+        assert!(imu.angular_velocity.abs_diff_eq(DVec3::Z, 1e-6), "{:?}", imu.angular_velocity);
+    }
+}
@@ -0,0 +1,139 @@
+use std::path::Path;
+
+/// A type that can be written as little-endian binary data, the reverse of [`crate::deserialize::Deserialize`].
+pub trait Serialize {
+    fn serialize(&self, serializer: &mut Serializer);
+}
+
+/// Writes values one after another into a buffer of little-endian binary data.
+///
+/// Vectors and strings are prefixed with their length as a `u64`, the same way
+/// [`crate::deserialize::Deserializer`] expects them.
+#[derive(Default)]
+pub struct Serializer {
+    output: Vec<u8>,
+}
+
+macro_rules! serializer_fn_write_primitive {
+    ($name: ident, $num_type: ty) => {
+        pub fn $name(&mut self, value: $num_type) {
+            self.output.extend(value.to_le_bytes());
+        }
+    };
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.output
+    }
+
+    pub fn to_file(self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.output)
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.output.extend_from_slice(bytes);
+    }
+
+    serializer_fn_write_primitive!(write_u8, u8);
+    serializer_fn_write_primitive!(write_u16, u16);
+    serializer_fn_write_primitive!(write_u32, u32);
+    serializer_fn_write_primitive!(write_u64, u64);
+
+    serializer_fn_write_primitive!(write_i8, i8);
+    serializer_fn_write_primitive!(write_i16, i16);
+    serializer_fn_write_primitive!(write_i32, i32);
+    serializer_fn_write_primitive!(write_i64, i64);
+
+    serializer_fn_write_primitive!(write_f32, f32);
+    serializer_fn_write_primitive!(write_f64, f64);
+
+    /// Writes the `u64` length prefix of a vector or string.
+    pub fn write_len(&mut self, len: usize) {
+        self.write_u64(len as u64);
+    }
+
+    pub fn write_slice<T: Serialize>(&mut self, values: &[T]) {
+        self.write_len(values.len());
+        for value in values {
+            value.serialize(self);
+        }
+    }
+
+    /// Writes `values` like [`Serializer::write_slice`], but with each value prefixed by its length in bytes, so
+    /// readers can skip fields added to the end of a value after they were written.
+    pub fn write_records<T: Serialize>(&mut self, values: &[T]) {
+        self.write_len(values.len());
+        for value in values {
+            let mut record = Serializer::new();
+            record.write(value);
+            self.write_len(record.output.len());
+            self.write_bytes(&record.output);
+        }
+    }
+
+    pub fn write_string(&mut self, value: &str) {
+        self.write_len(value.len());
+        self.write_bytes(value.as_bytes());
+    }
+
+    pub fn write<T: Serialize + ?Sized>(&mut self, value: &T) {
+        value.serialize(self);
+    }
+}
+
+macro_rules! impl_serialize_primitive {
+    ($num_type: ty, $write_fn: ident) => {
+        impl Serialize for $num_type {
+            fn serialize(&self, serializer: &mut Serializer) {
+                serializer.$write_fn(*self);
+            }
+        }
+    };
+}
+
+impl_serialize_primitive!(u8, write_u8);
+impl_serialize_primitive!(u16, write_u16);
+impl_serialize_primitive!(u32, write_u32);
+impl_serialize_primitive!(u64, write_u64);
+
+impl_serialize_primitive!(i8, write_i8);
+impl_serialize_primitive!(i16, write_i16);
+impl_serialize_primitive!(i32, write_i32);
+impl_serialize_primitive!(i64, write_i64);
+
+impl_serialize_primitive!(f32, write_f32);
+impl_serialize_primitive!(f64, write_f64);
+
+impl<T: Serialize> Serialize for Vec<T> {
+    fn serialize(&self, serializer: &mut Serializer) {
+        serializer.write_slice(self);
+    }
+}
+
+impl Serialize for String {
+    fn serialize(&self, serializer: &mut Serializer) {
+        serializer.write_string(self);
+    }
+}
+
+/// Implements [`Serialize`] for a struct by writing each of its fields in order.
+///
+/// ```ignore
+/// impl_serialize!(Body { triangle_count, verticies, indicies, normals, uvs });
+/// ```
+macro_rules! impl_serialize {
+    ($type: ident { $($field: ident),* $(,)? }) => {
+        impl $crate::serialize::Serialize for $type {
+            fn serialize(&self, serializer: &mut $crate::serialize::Serializer) {
+                $(serializer.write(&self.$field);)*
+            }
+        }
+    };
+}
+
+pub(crate) use impl_serialize;
@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use bevy::app::PluginGroupBuilder;
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::prelude::*;
+
+use crate::battery::{Battery, BatteryPlugin};
+use crate::electrical::ElectricalPlugin;
+use crate::halsim::{HalSim, HalSimPlugin};
+use crate::log::LogPlugin;
+use crate::mrr::{Assembly, MrrPlugin};
+use crate::networktables::{NetworkTables, NetworkTablesPlugin};
+use crate::physics::PhysicsPlugin;
+use crate::sensors::SensorPlugin;
+
+/// Upper bound on the number of steps taken in a single frame, so a long frame
+/// doesn't make the simulation fall further and further behind.
+const MAX_STEPS_PER_FRAME: u32 = 20;
+
+/// How long a headless run sleeps between frames while robot code drives it.
+const HEADLESS_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Schedule run once for every fixed simulation step.
+#[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct SimStep;
+
+/// Stages of a simulation step, run in order.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum SimSet {
+    /// Apply inputs such as motor efforts to the robot
+    Input,
+    /// Advance the physical state of the robot by one step
+    Physics,
+    /// Read sensors from the new physical state
+    Sensors,
+    /// Record values into [`Telemetry`]
+    Telemetry,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct SimSettings {
+    /// Length of a step in seconds
+    pub timestep: f64,
+    /// Stop simulating after this many seconds of simulated time
+    pub duration: Option<f64>,
+    /// Keep the simulation in step with the wall clock. When disabled, one step is taken every frame,
+    /// which runs as fast as possible when headless. Robot code connected through [`HalSim`], and clients of
+    /// [`NetworkTables`], always run in step with the wall clock.
+    pub realtime: bool,
+}
+
+impl Default for SimSettings {
+    fn default() -> Self {
+        Self {
+            timestep: 1. / 200.,
+            duration: None,
+            realtime: true,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct SimClock {
+    /// Simulated time at the end of the current step, in seconds
+    pub time: f64,
+    pub steps: u64,
+    pub paused: bool,
+    /// Set once the configured duration has been simulated
+    pub finished: bool,
+    accumulator: f64,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JointState {
+    /// Angle in radians for revolute joints, distance along the axis for sliders
+    pub position: f64,
+    pub velocity: f64,
+    /// Torque or force applied to the joint during the current step. Cleared after every step.
+    pub effort: f64,
+    /// Torque or force against the joint's velocity for each unit of it, such as the back EMF of motors,
+    /// during the current step. Cleared after every step.
+    pub damping: f64,
+}
+
+/// State of every joint of the [`Assembly`], indexed the same as [`Assembly::joints`].
+#[derive(Resource, Debug, Default)]
+pub struct JointStates(pub Vec<JointState>);
+
+impl JointStates {
+    pub fn positions(&self) -> Vec<f64> {
+        self.0.iter().map(|state| state.position).collect()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ActuatorState {
+    /// Volts for motors, otherwise the actuator's force before gearing. Kept until changed.
+    pub control: f64,
+    /// Volts the motors were given during the last step, which the battery and brownouts can hold below the control
+    pub voltage: f64,
+    /// Amps drawn by the motors during the last step
+    pub current: f64,
+}
+
+/// State of every actuator of the [`Assembly`], indexed the same as [`Assembly::actuators`].
+#[derive(Resource, Debug, Default)]
+pub struct ActuatorStates(pub Vec<ActuatorState>);
+
+/// Name of the telemetry channel the position of the joint at `index` is recorded in.
+pub fn joint_position_channel(index: usize) -> String {
+    format!("joint{index}.position")
+}
+
+/// Named values recorded once per simulation step.
+#[derive(Resource, Debug, Default)]
+pub struct Telemetry {
+    channels: Vec<String>,
+    channel_indices: HashMap<String, usize>,
+    times: Vec<f64>,
+    rows: Vec<Vec<Option<f64>>>,
+    current: Vec<Option<f64>>,
+}
+
+impl Telemetry {
+    /// Records `value` for `channel` in the current step.
+    pub fn record(&mut self, channel: &str, value: f64) {
+        let index = match self.channel_indices.get(channel) {
+            Some(&index) => index,
+            None => {
+                self.channels.push(channel.to_owned());
+                self.channel_indices.insert(channel.to_owned(), self.channels.len() - 1);
+                self.channels.len() - 1
+            }
+        };
+
+        if self.current.len() <= index {
+            self.current.resize(index + 1, None);
+        }
+        self.current[index] = Some(value);
+    }
+
+    fn commit(&mut self, time: f64) {
+        self.times.push(time);
+        self.rows.push(std::mem::take(&mut self.current));
+    }
+
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    /// Returns the samples of `channel` as `(time, value)` pairs.
+    pub fn samples<'a>(&'a self, channel: &str) -> impl Iterator<Item = (f64, f64)> + 'a {
+        let index = self.channel_indices.get(channel).copied();
+        self.times.iter()
+            .zip(&self.rows)
+            .filter_map(move |(&time, row)| Some((time, (*row.get(index?)?)?)))
+    }
+
+    pub fn write_csv(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut file = BufWriter::new(File::create(path)?);
+        write!(file, "time")?;
+        for channel in &self.channels {
+            write!(file, ",{channel}")?;
+        }
+        writeln!(file)?;
+
+        for (time, row) in self.times.iter().zip(&self.rows) {
+            write!(file, "{time}")?;
+            for i in 0..self.channels.len() {
+                match row.get(i).copied().flatten() {
+                    Some(value) => write!(file, ",{value}")?,
+                    None => write!(file, ",")?,
+                }
+            }
+            writeln!(file)?;
+        }
+
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Reads telemetry written by [`Telemetry::write_csv`].
+    pub fn read_csv(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut lines = text.lines();
+        let header = lines.next().context("Telemetry file is empty")?;
+
+        let mut telemetry = Self::default();
+        let channels: Vec<&str> = header.split(',').skip(1).collect();
+        for (number, line) in lines.enumerate().filter(|(_, line)| !line.is_empty()) {
+            let mut values = line.split(',');
+            let time = values.next().unwrap_or_default();
+            let time = time.parse::<f64>().with_context(|| format!("Invalid time \"{time}\" on line {}", number + 2))?;
+
+            for (channel, value) in channels.iter().zip(values).filter(|(_, value)| !value.is_empty()) {
+                let value = value.parse::<f64>().with_context(|| format!("Invalid value \"{value}\" on line {}", number + 2))?;
+                telemetry.record(channel, value);
+            }
+            telemetry.commit(time);
+        }
+
+        Ok(telemetry)
+    }
+}
+
+/// The robot and the joints and actuators the states were last sized for.
+#[derive(Default)]
+struct StateLayout {
+    file_path: PathBuf,
+    joints: Vec<String>,
+    actuators: Vec<String>,
+}
+
+/// Returns the states of `names`, keeping each state from `old` whose name was in `old_names`. When the count
+/// didn't change, the states stay with the same index, so renaming keeps them too.
+fn keep_states<T: Copy + Default>(old: &[T], old_names: &[String], names: &[String]) -> Vec<T> {
+    if names.len() == old_names.len() && old.len() == old_names.len() {
+        return old.to_vec();
+    }
+
+    names.iter()
+        .map(|name| old_names.iter()
+            .position(|old_name| old_name == name)
+            .and_then(|index| old.get(index))
+            .copied()
+            .unwrap_or_default())
+        .collect()
+}
+
+/// Sizes the joint and actuator states to the [`Assembly`]. Edits keep the pose and controls of the joints and
+/// actuators that are still there, and opening another robot starts it from rest.
+fn reset_joint_states(
+    assembly: Res<Assembly>,
+    mut layout: Local<StateLayout>,
+    mut states: ResMut<JointStates>,
+    mut actuator_states: ResMut<ActuatorStates>,
+) {
+    let joints: Vec<String> = assembly.joints.iter().map(|joint| joint.name.clone()).collect();
+    let actuators: Vec<String> = assembly.actuators.iter().map(|actuator| actuator.name.clone()).collect();
+
+    if assembly.file_path != layout.file_path {
+        states.0 = vec![JointState::default(); joints.len()];
+        actuator_states.0 = vec![ActuatorState::default(); actuators.len()];
+    } else {
+        states.0 = keep_states(&states.0, &layout.joints, &joints);
+        actuator_states.0 = keep_states(&actuator_states.0, &layout.actuators, &actuators);
+    }
+
+    *layout = StateLayout { file_path: assembly.file_path.clone(), joints, actuators };
+}
+
+/// Applies the effort of every actuator to the joint it drives.
+fn drive_joints(assembly: Res<Assembly>, battery: Res<Battery>, mut actuator_states: ResMut<ActuatorStates>, mut joint_states: ResMut<JointStates>) {
+    let output_voltage = battery.output_voltage();
+    for (actuator, state) in assembly.actuators.iter().zip(actuator_states.0.iter_mut()) {
+        let Some(joint) = joint_states.0.get_mut(actuator.joint) else {
+            continue;
+        };
+        let control = match actuator.control_range {
+            Some((lower, upper)) => state.control.clamp(lower, upper),
+            None => state.control,
+        };
+
+        match actuator.motor {
+            Some(motor) => {
+                // Motor controllers can't put out more than the battery gives them
+                state.voltage = control.clamp(-output_voltage, output_voltage);
+                // Back EMF is left to the physics as damping instead of being taken out of the torque
+                state.current = motor.current(state.voltage, joint.velocity * actuator.gear);
+                joint.effort += motor.torque(state.voltage, 0.) * actuator.gear;
+                joint.damping += motor.damping() * actuator.gear * actuator.gear;
+            }
+            None => {
+                let force = match actuator.force_range {
+                    Some((lower, upper)) => control.clamp(lower, upper),
+                    None => control,
+                };
+                joint.effort += force * actuator.gear;
+            }
+        }
+    }
+}
+
+fn record_actuator_telemetry(assembly: Res<Assembly>, states: Res<ActuatorStates>, mut telemetry: ResMut<Telemetry>) {
+    for (i, (actuator, state)) in assembly.actuators.iter().zip(&states.0).enumerate() {
+        telemetry.record(&format!("actuator{i}.control"), state.control);
+        if actuator.motor.is_some() {
+            telemetry.record(&format!("actuator{i}.current"), state.current);
+        }
+    }
+}
+
+fn run_sim_steps(world: &mut World) {
+    let settings = world.resource::<SimSettings>().clone();
+    let delta = world.resource::<Time>().delta_seconds_f64();
+    let hal_sim = world.get_resource::<HalSim>();
+    let robot_code = hal_sim.is_some_and(HalSim::is_connected)
+        || world.get_resource::<NetworkTables>().is_some_and(NetworkTables::has_clients);
+    let waiting_for_robot_code = hal_sim.is_some_and(|hal_sim| hal_sim.is_running() && !hal_sim.is_connected());
+
+    let steps = {
+        let mut clock = world.resource_mut::<SimClock>();
+        if clock.paused || clock.finished {
+            return;
+        }
+        // Robot code started alongside a headless run needs a moment to connect before the clock starts
+        if waiting_for_robot_code && !settings.realtime && clock.steps == 0 {
+            return;
+        }
+
+        if settings.realtime || robot_code {
+            clock.accumulator += delta;
+            let steps = (clock.accumulator / settings.timestep) as u32;
+            clock.accumulator -= steps as f64 * settings.timestep;
+            steps.min(MAX_STEPS_PER_FRAME)
+        } else {
+            1
+        }
+    };
+
+    for _ in 0..steps {
+        let time = {
+            let mut clock = world.resource_mut::<SimClock>();
+            clock.steps += 1;
+            clock.time = clock.steps as f64 * settings.timestep;
+            clock.time
+        };
+
+        world.run_schedule(SimStep);
+        world.resource_mut::<Telemetry>().commit(time);
+
+        if settings.duration.is_some_and(|duration| time >= duration) {
+            world.resource_mut::<SimClock>().finished = true;
+            break;
+        }
+    }
+}
+
+pub struct SimPlugin;
+
+impl Plugin for SimPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimSettings>()
+        .init_resource::<SimClock>()
+        .init_resource::<Telemetry>()
+        .init_resource::<JointStates>()
+        .init_resource::<ActuatorStates>()
+        .edit_schedule(SimStep, |schedule| {
+            schedule.configure_sets((SimSet::Input, SimSet::Physics, SimSet::Sensors, SimSet::Telemetry).chain());
+        })
+        .add_system(reset_joint_states
+            .run_if(resource_changed::<Assembly>())
+            .in_base_set(CoreSet::PreUpdate))
+        .add_system(drive_joints.in_schedule(SimStep).in_set(SimSet::Input))
+        .add_system(record_actuator_telemetry.in_schedule(SimStep).in_set(SimSet::Telemetry))
+        .add_system(run_sim_steps);
+    }
+}
+
+/// Everything needed to simulate a robot, without any rendering or UI. Shared by the
+/// windowed app and headless runs so both produce the same results.
+pub struct SimulationPlugins;
+
+impl PluginGroup for SimulationPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(LogPlugin)
+            .add(MrrPlugin)
+            .add(SimPlugin)
+            .add(BatteryPlugin)
+            .add(ElectricalPlugin)
+            .add(SensorPlugin)
+            .add(HalSimPlugin)
+            .add(NetworkTablesPlugin)
+            .add(PhysicsPlugin)
+    }
+}
+
+/// Simulates `assembly` for `settings.duration` seconds as fast as possible, without a window
+/// or GPU, and returns the recorded telemetry. `configure` can add resources to the app, such as a started
+/// [`HalSim`] to run robot code against, which waits for the robot code and then runs in real time. Started
+/// [`NetworkTables`] servers also run in real time while clients are connected, without waiting for them.
+pub fn run_headless(assembly: Assembly, settings: SimSettings, configure: impl FnOnce(&mut App)) -> Result<Telemetry> {
+    match settings.duration {
+        None => bail!("A headless simulation needs a duration"),
+        Some(duration) if !duration.is_finite() || duration <= 0. => {
+            bail!("A headless simulation needs a finite duration greater than zero, not {duration}")
+        }
+        Some(_) => {}
+    }
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(SimulationPlugins)
+        .insert_resource(assembly)
+        .insert_resource(SimSettings {
+            realtime: false,
+            ..settings
+        });
+    configure(&mut app);
+
+    app.setup();
+    while !app.world.resource::<SimClock>().finished {
+        app.update();
+        // Don't spin while waiting for robot code or keeping pace with it
+        if app.world.resource::<HalSim>().is_running() || app.world.resource::<NetworkTables>().has_clients() {
+            std::thread::sleep(HEADLESS_POLL_INTERVAL);
+        }
+    }
+
+    Ok(app.world.remove_resource::<Telemetry>().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_runs_need_a_positive_duration() {
+        for duration in [None, Some(f64::NAN), Some(0.), Some(-1.), Some(f64::INFINITY)] {
+            let settings = SimSettings { duration, ..Default::default() };
+            assert!(run_headless(Assembly::default(), settings, |_| {}).is_err(), "{duration:?}");
+        }
+    }
+
+    #[test]
+    fn headless_runs_stop_after_the_duration() {
+        let settings = SimSettings { duration: Some(0.1), ..Default::default() };
+        let steps = (0.1 / settings.timestep).round() as usize;
+        let telemetry = run_headless(Assembly::default(), settings, |_| {}).unwrap();
+        assert_eq!(telemetry.len(), steps);
+    }
+}
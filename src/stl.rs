@@ -0,0 +1,153 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+
+use crate::deserialize::Deserializer;
+use crate::mrr::Body;
+
+/// Size of the header that starts a binary STL file.
+const BINARY_HEADER_LEN: usize = 80;
+/// Size of a triangle in a binary STL file: a normal, three vertices and an attribute count.
+const BINARY_TRIANGLE_LEN: usize = 50;
+
+/// Reads an ASCII or binary STL file into a body, in the units of the file.
+pub fn load(path: &Path) -> Result<Body> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    parse(data).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+pub fn parse(data: Vec<u8>) -> Result<Body> {
+    // ASCII files start with "solid", but so do some binary files, so check that
+    // the size matches what the binary triangle count says first
+    if is_binary(&data) {
+        parse_binary(data)
+    } else if data.starts_with(b"solid") {
+        parse_ascii(std::str::from_utf8(&data).context("ASCII STL is not valid UTF-8")?)
+    } else {
+        parse_binary(data)
+    }
+}
+
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() < BINARY_HEADER_LEN + 4 {
+        return false;
+    }
+
+    let count_bytes = data[BINARY_HEADER_LEN..BINARY_HEADER_LEN + 4].try_into().unwrap();
+    let triangle_count = u32::from_le_bytes(count_bytes) as usize;
+    data.len() == BINARY_HEADER_LEN + 4 + triangle_count * BINARY_TRIANGLE_LEN
+}
+
+fn parse_binary(data: Vec<u8>) -> Result<Body> {
+    let mut deserializer = Deserializer::new(data);
+    deserializer.skip(BINARY_HEADER_LEN)?;
+    let triangle_count = deserializer.read_u32()? as usize;
+    // The count comes from the file, so check it before allocating for it
+    let available = deserializer.remaining().len() / BINARY_TRIANGLE_LEN;
+    if triangle_count > available {
+        bail!("Binary STL says it has {triangle_count} triangles, but only has room for {available}")
+    }
+
+    let mut verticies = Vec::with_capacity(triangle_count * 9);
+    for _ in 0..triangle_count {
+        // Facet normals are often wrong or missing, so they are recomputed from the vertices
+        deserializer.skip(3 * 4)?;
+        for _ in 0..9 {
+            verticies.push(deserializer.read_f32()?);
+        }
+        deserializer.skip(2)?;
+    }
+
+    let indicies = (0..triangle_count as i32 * 3).collect();
+    Ok(Body::from_triangles(verticies, indicies))
+}
+
+fn parse_ascii(text: &str) -> Result<Body> {
+    let mut verticies = vec![];
+    let mut tokens = text.split_whitespace();
+
+    while let Some(token) = tokens.next() {
+        if token != "vertex" {
+            continue;
+        }
+
+        for _ in 0..3 {
+            let coordinate = tokens.next().context("Vertex is missing a coordinate")?;
+            verticies.push(coordinate.parse::<f32>().with_context(|| format!("Invalid coordinate \"{coordinate}\""))?);
+        }
+    }
+
+    if verticies.len() % 9 != 0 {
+        bail!("Facets must have exactly 3 vertices")
+    }
+
+    let indicies = (0..verticies.len() as i32 / 3).collect();
+    Ok(Body::from_triangles(verticies, indicies))
+}
+
+/// Writes `bodies` to a single binary STL file.
+pub fn save(path: &Path, bodies: &[Body]) -> Result<()> {
+    let triangle_count: usize = bodies.iter().map(|body| body.indicies.len() / 3).sum();
+
+    let mut data = Vec::with_capacity(BINARY_HEADER_LEN + 4 + triangle_count * BINARY_TRIANGLE_LEN);
+    let mut header = b"Exported by MechSim".to_vec();
+    header.resize(BINARY_HEADER_LEN, 0);
+    data.extend(header);
+    data.extend((triangle_count as u32).to_le_bytes());
+
+    for body in bodies {
+        for triangle in body.indicies.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| body.vertex(triangle[i] as usize));
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+            for value in [normal, a, b, c].iter().flat_map(|vector| vector.to_array()) {
+                data.extend(value.to_le_bytes());
+            }
+            data.extend([0, 0]);
+        }
+    }
+
+    std::fs::write(path, data).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(triangle_count: u32, triangles: &[[f32; 9]]) -> Vec<u8> {
+        let mut data = vec![0; BINARY_HEADER_LEN];
+        data.extend(triangle_count.to_le_bytes());
+        for triangle in triangles {
+            data.extend([0u8; 12]);
+            data.extend(triangle.iter().flat_map(|value| value.to_le_bytes()));
+            data.extend([0, 0]);
+        }
+        data
+    }
+
+    #[test]
+    fn parses_ascii() {
+        let text = "solid part\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid part\n";
+        let body = parse(text.as_bytes().to_vec()).unwrap();
+        assert_eq!(body.indicies, vec![0, 1, 2]);
+        assert_eq!(body.vertex(1).to_array(), [1., 0., 0.]);
+    }
+
+    #[test]
+    fn parses_binary() {
+        let body = parse(binary(1, &[[0., 0., 0., 2., 0., 0., 0., 0., 3.]])).unwrap();
+        assert_eq!(body.indicies, vec![0, 1, 2]);
+        assert_eq!(body.vertex(2).to_array(), [0., 0., 3.]);
+    }
+
+    #[test]
+    fn rejects_truncated_binary() {
+        assert!(parse(binary(u32::MAX, &[[0.; 9]])).is_err());
+        assert!(parse(binary(3, &[[0.; 9]])).is_err());
+    }
+
+    #[test]
+    fn rejects_ascii_with_partial_facets() {
+        assert!(parse(b"solid part\nvertex 0 0 0\nvertex 1 0 0\nendsolid part\n".to_vec()).is_err());
+        assert!(parse(b"solid part\nvertex 0 zero 0\nendsolid part\n".to_vec()).is_err());
+    }
+}
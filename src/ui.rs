@@ -1,14 +1,36 @@
 
-use std::collections::{VecDeque, BTreeMap};
+use std::collections::{VecDeque, BTreeMap, BTreeSet};
+use std::f64::consts::PI;
+use std::ffi::OsStr;
+use std::path::PathBuf;
 
 use bevy::{prelude::*};
+use bevy::math::{DQuat, DVec3};
 use bevy_egui::egui::{Context, Ui, RichText, Color32};
+use bevy_egui::egui::plot::{HLine, Legend, Line, Plot, PlotPoints};
 use bevy_egui::{egui, EguiContexts};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use native_dialog::{FileDialog, MessageDialog, MessageType};
 
-use crate::mrr::{Assembly};
+use crate::appearance::ColorBy;
+use crate::battery::Battery;
+use crate::editor::{AssemblyEditor, EditorMode};
+use crate::electrical::{self, ControllerKind, ElectricalMap, EncoderSettings, LimitTrigger, MotorController, Port, Sensor, SensorKind};
+use crate::gltf::{self, GlbOptions};
+use crate::halsim::{HalSim, HalSimMode, RobotMode};
+use crate::hardware::HardwareFilter;
+use crate::interference::{self, Interference, SweepOptions};
+use crate::lod::{LodLevel, LodSettings};
+use crate::materials::{CombineMode, MaterialLibrary, PhysicalMaterial};
+use crate::meshes::{self, LengthUnit};
+use crate::motors::{Motor, MotorModel, MOTORS, NOMINAL_VOLTAGE};
+use crate::mrr::{Actuator, Assembly, Coupling, CouplingKind, Joint, JointType, Pose, METERS_PER_UNIT};
+use crate::networktables::{self, NetworkTables};
 use crate::log::{LogMessages, LogMessageType};
+use crate::paths;
+use crate::sensors::{SensorReading, SensorReadings};
+use crate::sim::{ActuatorStates, JointState, JointStates, Telemetry};
+use crate::viewer::ViewerSettings;
 
 #[derive(Default, PartialEq, Eq)]
 pub enum Tab {
@@ -16,16 +38,20 @@ pub enum Tab {
     Log,
     Model,
     Physics,
+    Electrical,
 }
 
 #[derive(Resource, Default)]
 pub struct BottomPanel {
     log_tab: LogTab,
+    model_tab: ModelTab,
+    physics_tab: PhysicsTab,
+    electrical_tab: ElectricalTab,
     open: Tab,
 }
 
 impl BottomPanel {
-    fn ui(&mut self, ui: &mut Ui, log: ResMut<LogMessages>) { 
+    fn ui(&mut self, ui: &mut Ui, assembly: &mut ResMut<Assembly>, editor: &mut AssemblyEditor, joint_states: &mut ResMut<JointStates>, hardware_filter: &mut HardwareFilter, materials: &mut MaterialLibrary, actuator_states: &mut ActuatorStates, battery: &mut Battery, telemetry: &Telemetry, electrical_map: &mut ElectricalMap, sensor_readings: &SensorReadings, hal_sim: &mut HalSim, network_tables: &mut NetworkTables, mut log: ResMut<LogMessages>) { 
         ui.horizontal(|ui| {
             ui.selectable_value(&mut self.open, Tab::Log, {
                 if log.msgs.is_empty() {
@@ -36,17 +62,526 @@ impl BottomPanel {
             });
             ui.selectable_value(&mut self.open, Tab::Model, "Model");
             ui.selectable_value(&mut self.open, Tab::Physics, "Physics");
+            ui.selectable_value(&mut self.open, Tab::Electrical, "Electrical");
         });
         ui.separator();
 
         match self.open {
             Tab::Log => self.log_tab.ui(ui, log),
-            Tab::Model => (),
-            Tab::Physics => (),
+            Tab::Model => self.model_tab.ui(ui, assembly, editor, joint_states, hardware_filter, materials, log),
+            Tab::Physics => self.physics_tab.ui(ui, assembly, actuator_states, battery, telemetry, electrical_map, sensor_readings, hal_sim, network_tables, log.as_mut()),
+            Tab::Electrical => self.electrical_tab.ui(ui, assembly, electrical_map, log.as_mut()),
         };
     }
 }
 
+/// Seconds of telemetry shown in the Physics tab's plots.
+const PLOT_WINDOW: f64 = 10.;
+
+#[derive(Default)]
+struct PhysicsTab;
+
+impl PhysicsTab {
+    fn ui(&mut self, ui: &mut Ui, assembly: &Assembly, actuator_states: &mut ActuatorStates, battery: &mut Battery, telemetry: &Telemetry, electrical_map: &ElectricalMap, sensor_readings: &SensorReadings, hal_sim: &mut HalSim, network_tables: &mut NetworkTables, log: &mut LogMessages) {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::CollapsingHeader::new("Robot Code").default_open(true).show(ui, |ui| {
+                robot_code_ui(ui, hal_sim, log);
+            });
+            egui::CollapsingHeader::new("NetworkTables").default_open(true).show(ui, |ui| {
+                network_tables_ui(ui, network_tables, log);
+            });
+            egui::CollapsingHeader::new("Actuators").default_open(true).show(ui, |ui| {
+                actuators_ui(ui, assembly, actuator_states);
+            });
+            egui::CollapsingHeader::new("Battery").default_open(true).show(ui, |ui| {
+                battery_ui(ui, battery, telemetry);
+            });
+            egui::CollapsingHeader::new("Sensors").default_open(true).show(ui, |ui| {
+                sensor_readings_ui(ui, electrical_map, sensor_readings);
+            });
+        });
+    }
+}
+
+fn robot_code_ui(ui: &mut Ui, hal_sim: &mut HalSim, log: &mut LogMessages) {
+    ui.horizontal(|ui| {
+        ui.add_enabled_ui(!hal_sim.is_running(), |ui| {
+            egui::ComboBox::from_id_source("halsim_mode")
+            .width(70.)
+            .selected_text(hal_sim.mode.name())
+            .show_ui(ui, |ui| {
+                for mode in HalSimMode::ALL {
+                    ui.selectable_value(&mut hal_sim.mode, mode, mode.name());
+                }
+            })
+            .response
+            .on_hover_text("Server waits for robot programs using the halsim_ws_client extension, Client connects to ones using halsim_ws_server");
+            ui.text_edit_singleline(&mut hal_sim.address);
+        });
+
+        if hal_sim.is_running() {
+            if ui.button("Stop").clicked() {
+                hal_sim.stop();
+            }
+        } else if ui.button("Start").clicked() {
+            if let Err(err) = hal_sim.start() {
+                log.error("Starting Robot Code Connection Failed", &format!("{err:#}"), true);
+            }
+        }
+
+        match (&hal_sim.peer, hal_sim.mode) {
+            (Some(peer), _) => ui.label(format!("Connected to {peer}")),
+            (None, _) if !hal_sim.is_running() => ui.label("Not running"),
+            (None, HalSimMode::Server) => ui.label("Waiting for the robot program"),
+            (None, HalSimMode::Client) => ui.label("Connecting to the robot program"),
+        };
+    });
+
+    ui.horizontal(|ui| {
+        for mode in RobotMode::ALL {
+            ui.selectable_value(&mut hal_sim.robot_mode, mode, mode.name());
+        }
+        ui.separator();
+        if hal_sim.enabled {
+            if ui.button(RichText::new("Disable").color(Color32::RED)).clicked() {
+                hal_sim.enabled = false;
+            }
+        } else if ui.button("Enable").clicked() {
+            hal_sim.enabled = true;
+        }
+    });
+}
+
+fn network_tables_ui(ui: &mut Ui, network_tables: &mut NetworkTables, log: &mut LogMessages) {
+    ui.horizontal(|ui| {
+        ui.label("Port");
+        ui.add_enabled(!network_tables.is_running(), egui::DragValue::new(&mut network_tables.port));
+
+        if network_tables.is_running() {
+            if ui.button("Stop").clicked() {
+                network_tables.stop();
+            }
+        } else if ui.button("Start").clicked() {
+            if let Err(err) = network_tables.start() {
+                log.error("Starting NetworkTables Server Failed", &format!("{err:#}"), true);
+            }
+        }
+
+        let clients: Vec<&str> = network_tables.client_names().collect();
+        if !network_tables.is_running() {
+            ui.label("Not running");
+        } else if clients.is_empty() {
+            ui.label("Waiting for clients");
+        } else {
+            ui.label(format!("Connected: {}", clients.join(", ")));
+        }
+    });
+
+    let values: Vec<(&str, String)> = network_tables.client_values().map(|(name, value)| (name, value.to_string())).collect();
+    if values.is_empty() {
+        ui.label(format!("Publish doubles to {} to drive actuators from robot code.", networktables::control_topic("<actuator>")));
+        return;
+    }
+    egui::Grid::new("network_tables").striped(true).show(ui, |ui| {
+        ui.strong("Topic");
+        ui.strong("Value");
+        ui.end_row();
+        for (name, value) in values {
+            ui.label(name);
+            ui.label(value);
+            ui.end_row();
+        }
+    });
+}
+
+fn actuators_ui(ui: &mut Ui, assembly: &Assembly, actuator_states: &mut ActuatorStates) {
+    if assembly.actuators.is_empty() {
+        ui.label("Add motors to joints in the Model tab to drive them from here.");
+        return;
+    }
+
+    egui::Grid::new("actuators").striped(true).show(ui, |ui| {
+        ui.strong("Actuator");
+        ui.strong("Joint");
+        ui.strong("Control").on_hover_text("Volts for motors, otherwise the actuator's force");
+        ui.strong("Current");
+        ui.end_row();
+
+        for (actuator, state) in assembly.actuators.iter().zip(actuator_states.0.iter_mut()) {
+            ui.label(&actuator.name);
+            ui.label(assembly.joints.get(actuator.joint).map_or("", |joint| joint.name.as_str()));
+            match (actuator.motor, actuator.control_range) {
+                (Some(_), _) => {
+                    let (lower, upper) = actuator.control_range.unwrap_or((-NOMINAL_VOLTAGE, NOMINAL_VOLTAGE));
+                    ui.add(egui::Slider::new(&mut state.control, lower..=upper).suffix(" V"));
+                    ui.label(format!("{:.1} A", state.current));
+                }
+                (None, Some((lower, upper))) => {
+                    ui.add(egui::Slider::new(&mut state.control, lower..=upper));
+                    ui.label("");
+                }
+                (None, None) => {
+                    ui.add(egui::DragValue::new(&mut state.control).speed(0.1));
+                    ui.label("");
+                }
+            }
+            ui.end_row();
+        }
+    });
+
+    if ui.button("Stop All").clicked() {
+        for state in &mut actuator_states.0 {
+            state.control = 0.;
+        }
+    }
+}
+
+fn battery_ui(ui: &mut Ui, battery: &mut Battery, telemetry: &Telemetry) {
+    ui.horizontal(|ui| {
+        ui.label(format!("{:.2} V, {:.1} A, {:.0}% charged", battery.voltage, battery.current, battery.state_of_charge * 100.));
+        if battery.browned_out {
+            ui.label(RichText::new("Browned out").color(Color32::RED))
+            .on_hover_text(format!("Motor controllers are disabled until the voltage is back above {} V", battery.recovery_voltage));
+        }
+    });
+
+    egui::CollapsingHeader::new("Settings").show(ui, |ui| {
+        egui::Grid::new("battery_settings").show(ui, |ui| {
+            ui.label("Nominal voltage").on_hover_text("Volts with no load when fully charged");
+            ui.add(egui::DragValue::new(&mut battery.nominal_voltage).speed(0.01).clamp_range(0.0..=20.0).suffix(" V"));
+            ui.end_row();
+            ui.label("Internal resistance").on_hover_text("Including the main breaker and wiring");
+            ui.add(egui::DragValue::new(&mut battery.internal_resistance).speed(0.001).clamp_range(0.0..=1.0).suffix(" Ω"));
+            ui.end_row();
+            ui.label("Capacity");
+            ui.add(egui::DragValue::new(&mut battery.capacity).speed(0.1).clamp_range(0.1..=100.0).suffix(" Ah"));
+            ui.end_row();
+            ui.label("State of charge");
+            ui.add(egui::Slider::new(&mut battery.state_of_charge, 0.0..=1.0));
+            ui.end_row();
+            ui.label("Brownout voltage").on_hover_text("Motor controllers are disabled below this voltage");
+            ui.add(egui::DragValue::new(&mut battery.brownout_voltage).speed(0.01).clamp_range(0.0..=battery.recovery_voltage).suffix(" V"));
+            ui.end_row();
+            ui.label("Recovery voltage").on_hover_text("Motor controllers are enabled again above this voltage");
+            ui.add(egui::DragValue::new(&mut battery.recovery_voltage).speed(0.01).clamp_range(battery.brownout_voltage..=20.0).suffix(" V"));
+            ui.end_row();
+        });
+        if ui.button("Recharge").clicked() {
+            battery.state_of_charge = 1.;
+        }
+    });
+
+    let recent = |channel: &str| -> PlotPoints {
+        let end = telemetry.samples(channel).last().map_or(0., |(time, _)| time);
+        telemetry.samples(channel)
+            .filter(|&(time, _)| time >= end - PLOT_WINDOW)
+            .map(|(time, value)| [time, value])
+            .collect()
+    };
+    Plot::new("battery_voltage")
+    .height(150.)
+    .legend(Legend::default())
+    .include_y(0.)
+    .include_y(battery.nominal_voltage)
+    .allow_scroll(false)
+    .show(ui, |plot| {
+        plot.line(Line::new(recent("battery.voltage")).name("Voltage (V)"));
+        plot.hline(HLine::new(battery.brownout_voltage).color(Color32::RED).name("Brownout"));
+    });
+    Plot::new("battery_current")
+    .height(150.)
+    .legend(Legend::default())
+    .include_y(0.)
+    .allow_scroll(false)
+    .show(ui, |plot| {
+        plot.line(Line::new(recent("battery.current")).name("Current (A)"));
+    });
+}
+
+fn sensor_readings_ui(ui: &mut Ui, electrical_map: &ElectricalMap, sensor_readings: &SensorReadings) {
+    if electrical_map.sensors.is_empty() {
+        ui.label("Add sensors in the Electrical tab to read them here.");
+        return;
+    }
+
+    egui::Grid::new("sensor_readings").striped(true).show(ui, |ui| {
+        ui.strong("Sensor");
+        ui.strong("Reading");
+        ui.end_row();
+
+        for (sensor, reading) in electrical_map.sensors.iter().zip(&sensor_readings.readings) {
+            ui.label(&sensor.name);
+            match reading {
+                SensorReading::None => ui.label("Not attached"),
+                SensorReading::Encoder { counts, rate } => ui.label(format!("{counts} counts, {rate:.0} counts/s")),
+                SensorReading::LimitSwitch { pressed: true } => ui.label(RichText::new("Pressed").strong()),
+                SensorReading::LimitSwitch { pressed: false } => ui.label("Released"),
+                SensorReading::Imu(imu) => ui.label(format!(
+                    "Yaw {:.1}°, pitch {:.1}°, roll {:.1}°\nRates {:.2} rad/s\nAcceleration {:.2} m/s²",
+                    imu.yaw.to_degrees(), imu.pitch.to_degrees(), imu.roll.to_degrees(), imu.angular_velocity, imu.acceleration,
+                )),
+            };
+            ui.end_row();
+        }
+    });
+}
+
+#[derive(Default)]
+struct ElectricalTab;
+
+impl ElectricalTab {
+    fn ui(&mut self, ui: &mut Ui, assembly: &Assembly, map: &mut ElectricalMap, log: &mut LogMessages) {
+        if assembly.file_path.as_os_str().is_empty() {
+            ui.label("Open a robot to set up its motor controllers and sensors.");
+            return;
+        }
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    match map.save() {
+                        Ok(()) => log.info("Electrical Map Saved", &format!("Saved to {}.", ElectricalMap::path_for(&map.robot).display())),
+                        Err(err) => log.error("Saving Electrical Map Failed", &format!("{err:#}"), true),
+                    }
+                }
+                if ui.button("Reload").on_hover_text("Throw away unsaved changes to the map").clicked() {
+                    match ElectricalMap::load_for(&assembly.file_path) {
+                        Ok(loaded) => *map = loaded,
+                        Err(err) => log.error("Loading Electrical Map Failed", &format!("{err:#}"), true),
+                    }
+                }
+                if ui.button("Check Wiring").clicked() {
+                    electrical::log_problems(map, assembly, log);
+                }
+                ui.label(ElectricalMap::path_for(&map.robot).display().to_string());
+            });
+
+            for problem in map.check(assembly) {
+                ui.label(RichText::new(problem).color(LogMessageType::Warning.color()));
+            }
+
+            egui::CollapsingHeader::new(format!("Motor Controllers ({})", map.controllers.len())).default_open(true).show(ui, |ui| {
+                controllers_ui(ui, assembly, map);
+            });
+            egui::CollapsingHeader::new(format!("Sensors ({})", map.sensors.len())).default_open(true).show(ui, |ui| {
+                sensors_ui(ui, assembly, map);
+            });
+        });
+    }
+}
+
+fn controllers_ui(ui: &mut Ui, assembly: &Assembly, map: &mut ElectricalMap) {
+    let mut remove = None;
+    egui::Grid::new("controllers").striped(true).show(ui, |ui| {
+        ui.strong("Name");
+        ui.strong("Type");
+        ui.strong("Port");
+        ui.strong("Drives");
+        ui.end_row();
+
+        for (index, controller) in map.controllers.iter_mut().enumerate() {
+            ui.text_edit_singleline(&mut controller.name);
+            egui::ComboBox::from_id_source(("controller_kind", index))
+            .selected_text(controller.kind.name())
+            .show_ui(ui, |ui| {
+                for kind in ControllerKind::ALL {
+                    ui.selectable_value(&mut controller.kind, kind, kind.name());
+                }
+            });
+            port_ui(ui, ("controller_port", index), &mut controller.port, &[Port::Can(0), Port::Pwm(0)]);
+            egui::ComboBox::from_id_source(("controller_actuator", index))
+            .selected_text(controller.actuator.as_deref().unwrap_or("Nothing"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut controller.actuator, None, "Nothing");
+                for actuator in assembly.actuators.iter().filter(|actuator| actuator.motor.is_some()) {
+                    ui.selectable_value(&mut controller.actuator, Some(actuator.name.clone()), &actuator.name);
+                }
+            });
+            if ui.small_button("Remove").clicked() {
+                remove = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(index) = remove {
+        map.controllers.remove(index);
+    }
+
+    if ui.button("Add Controller").clicked() {
+        let next_id = map.controllers.iter()
+            .filter_map(|controller| match controller.port {
+                Port::Can(id) => Some(id + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(1);
+        map.controllers.push(MotorController {
+            name: format!("Controller {}", map.controllers.len() + 1),
+            kind: ControllerKind::default(),
+            port: Port::Can(next_id),
+            actuator: None,
+        });
+    }
+}
+
+fn sensors_ui(ui: &mut Ui, assembly: &Assembly, map: &mut ElectricalMap) {
+    let controllers: Vec<String> = map.controllers.iter().map(|controller| controller.name.clone()).collect();
+    let mut remove = None;
+    egui::Grid::new("sensors").striped(true).show(ui, |ui| {
+        ui.strong("Name");
+        ui.strong("Type");
+        ui.strong("Port");
+        ui.strong("Attached To").on_hover_text("The joint encoders and limit switches measure, or the part IMUs and contact limit switches are mounted on");
+        ui.strong("Settings");
+        ui.end_row();
+
+        for (index, sensor) in map.sensors.iter_mut().enumerate() {
+            ui.text_edit_singleline(&mut sensor.name);
+            let on_part = sensor.on_part();
+            egui::ComboBox::from_id_source(("sensor_kind", index))
+            .selected_text(sensor.kind.name())
+            .show_ui(ui, |ui| {
+                for kind in SensorKind::ALL {
+                    ui.selectable_value(&mut sensor.kind, kind, kind.name());
+                }
+            });
+
+            let controller_port = Port::Controller(controllers.first().cloned().unwrap_or_default());
+            let ports = match sensor.kind {
+                SensorKind::Encoder => vec![Port::DioPair(0, 1), Port::Analog(0), Port::Can(0), controller_port],
+                SensorKind::LimitSwitch => vec![Port::Dio(0), controller_port],
+                SensorKind::Imu => vec![Port::Can(0), Port::Mxp],
+            };
+            port_ui(ui, ("sensor_port", index), &mut sensor.port, &ports);
+            if let Port::Controller(controller) = &mut sensor.port {
+                egui::ComboBox::from_id_source(("sensor_controller", index))
+                .selected_text(controller.as_str())
+                .show_ui(ui, |ui| {
+                    for name in &controllers {
+                        ui.selectable_value(controller, name.clone(), name);
+                    }
+                });
+            }
+
+            let names: Vec<&String> = if sensor.on_part() {
+                assembly.parts.iter().map(|part| &part.name).collect()
+            } else {
+                assembly.joints.iter().filter(|joint| joint.joint_type != JointType::Rigid).map(|joint| &joint.name).collect()
+            };
+            egui::ComboBox::from_id_source(("sensor_attached", index))
+            .selected_text(sensor.attached_to.as_deref().unwrap_or("Nothing"))
+            .show_ui(ui, |ui| {
+                for name in names {
+                    ui.selectable_value(&mut sensor.attached_to, Some(name.clone()), name);
+                }
+            });
+            ui.horizontal(|ui| sensor_settings_ui(ui, index, assembly, sensor));
+            // Changing the type or trigger can move a sensor from a joint to a part
+            if sensor.on_part() != on_part {
+                sensor.attached_to = None;
+            }
+            if ui.small_button("Remove").clicked() {
+                remove = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(index) = remove {
+        map.sensors.remove(index);
+    }
+
+    if ui.button("Add Sensor").clicked() {
+        map.sensors.push(Sensor {
+            name: format!("Sensor {}", map.sensors.len() + 1),
+            kind: SensorKind::default(),
+            port: Port::DioPair(0, 1),
+            attached_to: None,
+            encoder: EncoderSettings::default(),
+            trigger: LimitTrigger::default(),
+        });
+    }
+}
+
+/// Edits the settings particular to the type of `sensor`.
+fn sensor_settings_ui(ui: &mut Ui, index: usize, assembly: &Assembly, sensor: &mut Sensor) {
+    match sensor.kind {
+        SensorKind::Encoder => {
+            let encoder = &mut sensor.encoder;
+            ui.checkbox(&mut encoder.absolute, "Absolute")
+            .on_hover_text("Reports where it is within a turn rather than counting from where it started");
+            ui.add(egui::DragValue::new(&mut encoder.cpr).speed(1.).clamp_range(1.0..=f64::MAX).suffix(" CPR"))
+            .on_hover_text("Counts for each turn of a revolute joint, or each meter a slider travels");
+            ui.add(egui::DragValue::new(&mut encoder.noise).speed(0.1).clamp_range(0.0..=f64::MAX).prefix("Noise "))
+            .on_hover_text("Standard deviation of the noise on each reading, in counts");
+        }
+        SensorKind::LimitSwitch => {
+            let first_part = assembly.parts.first().map(|part| part.name.clone()).unwrap_or_default();
+            let triggers = [LimitTrigger::Below(0.), LimitTrigger::Above(0.), LimitTrigger::Contact(first_part)];
+            egui::ComboBox::from_id_source(("sensor_trigger", index))
+            .width(70.)
+            .selected_text(sensor.trigger.name())
+            .show_ui(ui, |ui| {
+                for trigger in triggers {
+                    if ui.selectable_label(sensor.trigger.name() == trigger.name(), trigger.name()).clicked() && sensor.trigger.name() != trigger.name() {
+                        sensor.trigger = trigger;
+                    }
+                }
+            });
+            match &mut sensor.trigger {
+                LimitTrigger::Below(position) | LimitTrigger::Above(position) => {
+                    ui.add(egui::DragValue::new(position).speed(0.01))
+                    .on_hover_text("Radians for revolute joints, meters for sliders");
+                }
+                LimitTrigger::Contact(part) => {
+                    egui::ComboBox::from_id_source(("sensor_contact", index))
+                    .selected_text(part.as_str())
+                    .show_ui(ui, |ui| {
+                        for other in &assembly.parts {
+                            ui.selectable_value(part, other.name.clone(), &other.name);
+                        }
+                    });
+                }
+            }
+        }
+        SensorKind::Imu => (),
+    }
+}
+
+/// Picks one of `options` for `port`, and the channels or ID it uses.
+fn port_ui(ui: &mut Ui, id: impl std::hash::Hash, port: &mut Port, options: &[Port]) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source(id)
+        .width(90.)
+        .selected_text(port.name())
+        .show_ui(ui, |ui| {
+            for option in options {
+                if ui.selectable_label(port.name() == option.name(), option.name()).clicked() && port.name() != option.name() {
+                    *port = option.clone();
+                }
+            }
+        });
+        match port {
+            Port::Can(id) => {
+                ui.add(egui::DragValue::new(id).clamp_range(0..=electrical::MAX_CAN_ID).prefix("ID "));
+            }
+            Port::Pwm(channel) => {
+                ui.add(egui::DragValue::new(channel).clamp_range(0..=electrical::PWM_CHANNELS - 1));
+            }
+            Port::Dio(channel) => {
+                ui.add(egui::DragValue::new(channel).clamp_range(0..=electrical::DIO_CHANNELS - 1));
+            }
+            Port::DioPair(a, b) => {
+                ui.add(egui::DragValue::new(a).clamp_range(0..=electrical::DIO_CHANNELS - 1).prefix("A "));
+                ui.add(egui::DragValue::new(b).clamp_range(0..=electrical::DIO_CHANNELS - 1).prefix("B "));
+            }
+            Port::Analog(channel) => {
+                ui.add(egui::DragValue::new(channel).clamp_range(0..=electrical::ANALOG_CHANNELS - 1));
+            }
+            Port::Controller(_) | Port::Mxp => (),
+        }
+    });
+}
+
 #[derive(Default)]
 struct LogTab;
 
@@ -92,27 +627,718 @@ impl LogTab {
     }
 }
 
+/// Joint being set up in the Model tab.
+struct NewJoint {
+    parts: [usize; 2],
+    joint_type: JointType,
+    /// In assembly units
+    position: DVec3,
+    axis: DVec3,
+    limited: bool,
+    limits: (f64, f64),
+}
+
+impl Default for NewJoint {
+    fn default() -> Self {
+        Self {
+            parts: [0, 1],
+            joint_type: JointType::Revolute,
+            position: DVec3::ZERO,
+            axis: DVec3::Z,
+            limited: false,
+            limits: (-1., 1.),
+        }
+    }
+}
+
+/// Motor being added to the selected joint in the Model tab.
+struct NewMotor {
+    model: MotorModel,
+    count: u32,
+    /// Motor turns for each turn of the output
+    reduction: f64,
+    /// Diameter in inches of the pulley, drum or pinion that turns the output into travel along a slider
+    pulley_diameter: f64,
+}
+
+impl Default for NewMotor {
+    fn default() -> Self {
+        Self {
+            model: MOTORS[0],
+            count: 1,
+            reduction: 10.,
+            pulley_diameter: 1.5,
+        }
+    }
+}
+
+/// Joints picked for an interference sweep in the Model tab, and what the last sweep found.
+#[derive(Default)]
+struct InterferenceCheck {
+    joints: BTreeSet<usize>,
+    options: SweepOptions,
+    /// Joints of the last sweep, in the order of the positions in its results
+    swept: Vec<usize>,
+    results: Vec<(String, Interference)>,
+}
+
+#[derive(Default)]
+struct ModelTab {
+    new_joint: NewJoint,
+    new_motor: NewMotor,
+    interference: InterferenceCheck,
+    /// Name typed in for a material to add to the library
+    new_material: String,
+}
+
+impl ModelTab {
+    fn ui(&mut self, ui: &mut Ui, assembly: &mut ResMut<Assembly>, editor: &mut AssemblyEditor, joint_states: &mut ResMut<JointStates>, hardware_filter: &mut HardwareFilter, materials: &mut MaterialLibrary, mut log: ResMut<LogMessages>) {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut editor.mode, EditorMode::View, "View");
+                ui.radio_value(&mut editor.mode, EditorMode::Pose, "Pose joints")
+                .on_hover_text("Click a joint in the viewport, then drag its handle to move the robot");
+                ui.radio_value(&mut editor.mode, EditorMode::Edit, "Edit assembly")
+                .on_hover_text("Click joints and parts in the viewport to select them, and drag the handles to move joints");
+            });
+
+            egui::CollapsingHeader::new(format!("Parts ({})", assembly.parts.len())).show(ui, |ui| {
+                self.parts_ui(ui, assembly, editor, materials);
+            });
+
+            egui::CollapsingHeader::new(format!("Joints ({})", assembly.joints.len())).show(ui, |ui| {
+                self.joints_ui(ui, assembly, editor, joint_states, log.as_mut());
+            });
+
+            egui::CollapsingHeader::new(format!("Couplings ({})", assembly.couplings.len())).show(ui, |ui| {
+                couplings_ui(ui, assembly);
+            });
+
+            egui::CollapsingHeader::new("Hardware Filter").show(ui, |ui| {
+                hardware_filter_ui(ui, assembly, hardware_filter, log.as_mut());
+            });
+
+            egui::CollapsingHeader::new("Physical Materials").show(ui, |ui| {
+                self.materials_ui(ui, materials, log.as_mut());
+            });
+
+            egui::CollapsingHeader::new("Interference").show(ui, |ui| {
+                self.interference_ui(ui, assembly, editor, joint_states, log.as_mut());
+            });
+
+            if assembly.parts.len() >= 2 {
+                egui::CollapsingHeader::new("Add Joint").show(ui, |ui| {
+                    self.add_joint_ui(ui, assembly, editor, log.as_mut());
+                });
+            }
+        });
+    }
+
+    fn parts_ui(&mut self, ui: &mut Ui, assembly: &mut ResMut<Assembly>, editor: &mut AssemblyEditor, materials: &MaterialLibrary) {
+        let mut next_group = assembly.parts.iter()
+            .flat_map(|part| part.rigid_group_references.iter())
+            .max()
+            .map_or(0, |group| group + 1);
+
+        egui::Grid::new("parts").striped(true).show(ui, |ui| {
+            ui.strong("Name");
+            ui.strong("Bodies");
+            ui.strong("Triangles");
+            ui.strong("Joints");
+            ui.strong("Rigid Group").on_hover_text("Parts in the same rigid group move together");
+            ui.strong("Hardware").on_hover_text("Hidden in the viewer and left out of collisions, but still adds to the mass of its rigid group");
+            ui.strong("Material").on_hover_text("Physical material with the friction and bounce of the part, saved with the robot. Auto picks the one the CAD material matches.");
+            ui.end_row();
+
+            for index in 0..assembly.parts.len() {
+                let part = &assembly.parts[index];
+                if ui.selectable_label(editor.selected_part == Some(index), &part.name).clicked() {
+                    editor.selected_part = Some(index);
+                }
+                ui.label(part.bodies.len().to_string());
+                ui.label(part.bodies.iter().map(|body| body.indicies.len() / 3).sum::<usize>().to_string());
+                ui.label(part.joint_references.len().to_string());
+
+                let mut group = part.rigid_group_references.first().copied();
+                let mut group_changed = false;
+                ui.horizontal(|ui| {
+                    match &mut group {
+                        Some(group) => group_changed |= ui.add(egui::DragValue::new(group).speed(0.1)).changed(),
+                        None => {
+                            ui.label("None");
+                        }
+                    }
+                    if ui.small_button("New").on_hover_text("Move the part into a rigid group of its own").clicked() {
+                        group = Some(next_group);
+                        next_group += 1;
+                        group_changed = true;
+                    }
+                });
+                if let Some(group) = group.filter(|_| group_changed) {
+                    let references = &mut assembly.parts[index].rigid_group_references;
+                    references.truncate(1);
+                    match references.first_mut() {
+                        Some(reference) => *reference = group,
+                        None => references.push(group),
+                    }
+                }
+
+                let mut hardware = assembly.parts[index].hardware;
+                if ui.checkbox(&mut hardware, "").changed() {
+                    assembly.parts[index].hardware = hardware;
+                }
+
+                let part = &assembly.parts[index];
+                let auto = format!("Auto ({})", materials.cad_material(part)
+                    .or_else(|| materials.get(&materials.default))
+                    .map_or("none", |material| material.name.as_str()));
+                let mut physical_material = part.physical_material.clone();
+                egui::ComboBox::from_id_source(("part_material", index))
+                .selected_text(physical_material.clone().unwrap_or_else(|| auto.clone()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut physical_material, None, auto);
+                    for material in &materials.materials {
+                        ui.selectable_value(&mut physical_material, Some(material.name.clone()), &material.name);
+                    }
+                });
+                if physical_material != assembly.parts[index].physical_material {
+                    assembly.parts[index].physical_material = physical_material;
+                }
+                ui.end_row();
+            }
+        });
+    }
+
+    fn joints_ui(&mut self, ui: &mut Ui, assembly: &mut ResMut<Assembly>, editor: &mut AssemblyEditor, joint_states: &mut ResMut<JointStates>, log: &mut LogMessages) {
+        for (index, joint) in assembly.joints.iter().enumerate() {
+            if ui.selectable_label(editor.selected_joint == Some(index), &joint.name).clicked() {
+                editor.selected_joint = Some(index);
+            }
+        }
+
+        if joint_states.0.iter().any(|state| state.position != 0.) && ui.button("Reset Pose").clicked() {
+            for state in &mut joint_states.0 {
+                *state = JointState::default();
+            }
+        }
+
+        let Some(index) = editor.selected_joint.filter(|&index| index < assembly.joints.len()) else {
+            return;
+        };
+        ui.separator();
+
+        // Edit a copy so the assembly is only marked as changed when something was actually edited
+        let mut joint = assembly.joints[index].clone();
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut joint.name);
+            ui.label("Name");
+        });
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut joint.joint_type, JointType::Revolute, "Revolute");
+            ui.radio_value(&mut joint.joint_type, JointType::Slider, "Slider");
+            ui.radio_value(&mut joint.joint_type, JointType::Rigid, "Rigid");
+        });
+
+        vector_ui(ui, "Position (in)", &mut joint.pose.0);
+        let mut axis = joint.axis();
+        vector_ui(ui, "Axis", &mut axis);
+        if axis != joint.axis() && axis.length_squared() > 0. {
+            joint.pose.1 = DQuat::from_rotation_arc(DVec3::Z, axis.normalize());
+        }
+
+        if joint.joint_type != JointType::Rigid {
+            ui.horizontal(|ui| {
+                let mut limited = joint.limits.is_some();
+                ui.checkbox(&mut limited, "Limits");
+                let mut limits = joint.limits.unwrap_or((-1., 1.));
+                ui.add_enabled(limited, egui::DragValue::new(&mut limits.0).speed(0.01));
+                ui.add_enabled(limited, egui::DragValue::new(&mut limits.1).speed(0.01));
+                ui.label(if joint.joint_type == JointType::Revolute { "rad" } else { "m" });
+                joint.limits = Some(limits).filter(|_| limited);
+            });
+
+            if let Some(state) = joint_states.0.get(index) {
+                let (lower, upper) = joint.limits.unwrap_or(match joint.joint_type {
+                    JointType::Revolute => (-PI, PI),
+                    _ => (-1., 1.),
+                });
+                let mut position = state.position;
+                ui.add(egui::Slider::new(&mut position, lower..=upper)
+                    .text(if joint.joint_type == JointType::Revolute { "Position (rad)" } else { "Position (m)" }));
+                if position != state.position {
+                    joint_states.0[index] = JointState { position, ..default() };
+                }
+            }
+        }
+
+        // Joints imported with only their child part are attached to the world
+        let parts = assembly.joint_parts(index);
+        let unattached = if parts.is_empty() { "None" } else { "World" };
+        let mut new_parts = [0, 1].map(|i| parts.get(i).copied());
+        let mut parts_changed = false;
+        for (i, label) in ["Between", "and"].into_iter().enumerate() {
+            egui::ComboBox::from_id_source(("joint_part", i))
+            .selected_text(new_parts[i].map_or(unattached, |part| assembly.parts[part].name.as_str()))
+            .show_ui(ui, |ui| {
+                parts_changed |= ui.selectable_value(&mut new_parts[i], None, "World").changed();
+                for (part_index, part) in assembly.parts.iter().enumerate() {
+                    parts_changed |= ui.selectable_value(&mut new_parts[i], Some(part_index), &part.name).changed();
+                }
+            });
+            ui.label(label);
+        }
+
+        if joint != assembly.joints[index] {
+            assembly.joints[index] = joint;
+        }
+        let new_parts: Vec<usize> = new_parts.into_iter().flatten().collect();
+        let distinct = new_parts.len() < 2 || new_parts[0] != new_parts[1];
+        if parts_changed && !new_parts.is_empty() && distinct {
+            assembly.set_joint_parts(index, &new_parts);
+        }
+
+        if assembly.joints[index].joint_type != JointType::Rigid {
+            self.motors_ui(ui, assembly, index);
+        }
+
+        if ui.button("Delete Joint").clicked() {
+            let name = assembly.joints[index].name.clone();
+            assembly.remove_joint(index);
+            editor.selected_joint = None;
+            log.info("Joint Deleted", &format!("Deleted {name}."));
+        }
+    }
+
+    fn motors_ui(&mut self, ui: &mut Ui, assembly: &mut ResMut<Assembly>, joint: usize) {
+        let slider = assembly.joints[joint].joint_type == JointType::Slider;
+        let gear_hover = if slider {
+            "Motor radians for each meter of travel"
+        } else {
+            "Motor turns for each turn of the joint"
+        };
+
+        ui.label("Motors");
+        let mut remove = None;
+        for index in 0..assembly.actuators.len() {
+            if assembly.actuators[index].joint != joint {
+                continue;
+            }
+            let mut actuator = assembly.actuators[index].clone();
+            ui.horizontal(|ui| {
+                ui.label(&actuator.name);
+                if let Some(motor) = &mut actuator.motor {
+                    ui.add(egui::DragValue::new(&mut motor.count).clamp_range(1..=8).suffix("x"));
+                    egui::ComboBox::from_id_source(("motor", index))
+                    .selected_text(motor.model.name)
+                    .show_ui(ui, |ui| {
+                        for model in MOTORS {
+                            ui.selectable_value(&mut motor.model, model, model.name);
+                        }
+                    });
+                }
+                ui.add(egui::DragValue::new(&mut actuator.gear).speed(0.1).prefix("Gear: ")).on_hover_text(gear_hover);
+                if ui.small_button("Remove").clicked() {
+                    remove = Some(index);
+                }
+            });
+            if actuator != assembly.actuators[index] {
+                assembly.actuators[index] = actuator;
+            }
+        }
+        if let Some(index) = remove {
+            assembly.actuators.remove(index);
+        }
+
+        let new_motor = &mut self.new_motor;
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut new_motor.count).clamp_range(1..=8).suffix("x"));
+            egui::ComboBox::from_id_source("new_motor")
+            .selected_text(new_motor.model.name)
+            .show_ui(ui, |ui| {
+                for model in MOTORS {
+                    ui.selectable_value(&mut new_motor.model, model, model.name);
+                }
+            });
+            ui.add(egui::DragValue::new(&mut new_motor.reduction).speed(0.1).clamp_range(0.01..=10000.0).suffix(":1"))
+            .on_hover_text("Motor turns for each turn of the output");
+            if slider {
+                ui.add(egui::DragValue::new(&mut new_motor.pulley_diameter).speed(0.01).clamp_range(0.01..=100.0).suffix(" in"))
+                .on_hover_text("Diameter of the pulley, drum or pinion that turns the output into travel");
+            }
+
+            if ui.button("Add Motor").clicked() {
+                let gear = if slider {
+                    new_motor.reduction / (new_motor.pulley_diameter / 2. * METERS_PER_UNIT)
+                } else {
+                    new_motor.reduction
+                };
+                let name = format!("{} {}", assembly.joints[joint].name, new_motor.model.name);
+                assembly.actuators.push(Actuator {
+                    name,
+                    joint,
+                    gear,
+                    control_range: Some((-NOMINAL_VOLTAGE, NOMINAL_VOLTAGE)),
+                    force_range: None,
+                    motor: Some(Motor::new(new_motor.model, new_motor.count)),
+                });
+            }
+        });
+    }
+
+    fn interference_ui(&mut self, ui: &mut Ui, assembly: &Assembly, editor: &mut AssemblyEditor, joint_states: &mut ResMut<JointStates>, log: &mut LogMessages) {
+        let check = &mut self.interference;
+        ui.label("Sweep these joints through their range of motion:");
+        for (index, joint) in assembly.joints.iter().enumerate() {
+            if interference::sweep_range(assembly, index).is_err() {
+                continue;
+            }
+            let mut swept = check.joints.contains(&index);
+            if ui.checkbox(&mut swept, &joint.name).changed() {
+                if swept {
+                    check.joints.insert(index);
+                } else {
+                    check.joints.remove(&index);
+                }
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut check.options.steps).clamp_range(2..=100));
+            ui.label("Steps per joint");
+        });
+        ui.checkbox(&mut check.options.include_adjacent, "Include joined parts that already touch")
+        .on_hover_text("Parts connected by a joint often touch where they are joined, so they are skipped if they intersect in the current pose");
+
+        let joints: Vec<usize> = check.joints.iter().copied().filter(|&joint| joint < assembly.joints.len()).collect();
+        if ui.add_enabled(!joints.is_empty(), egui::Button::new("Check")).clicked() {
+            match interference::sweep(assembly, &joints, &joint_states.positions(), &check.options) {
+                Ok(found) => {
+                    log.info("Interference Check", &format!("Found {} pair(s) of intersecting bodies.", found.len()));
+                    check.results = found.into_iter()
+                        .map(|interference| (interference.describe(assembly, &joints), interference))
+                        .collect();
+                    check.swept = joints;
+                }
+                Err(e) => log.error("Interference Check Failed", &format!("{e:#}"), false),
+            }
+        }
+
+        for (description, interference) in &check.results {
+            if ui.link(description).on_hover_text("Move the joints to where the bodies first intersect").clicked() {
+                for (&joint, &position) in check.swept.iter().zip(&interference.positions[0]) {
+                    if let Some(state) = joint_states.0.get_mut(joint) {
+                        *state = JointState { position, ..default() };
+                    }
+                }
+                editor.selected_part = Some(interference.bodies[0].part);
+            }
+        }
+    }
+
+    fn materials_ui(&mut self, ui: &mut Ui, library: &mut MaterialLibrary, log: &mut LogMessages) {
+        let names: Vec<String> = library.materials.iter().map(|material| material.name.clone()).collect();
+        for (label, hover, selected) in [
+            ("Field", "Material of the field floor", &mut library.field),
+            ("Default", "Material of parts whose CAD material doesn't match any in the library", &mut library.default),
+        ] {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source(("library_material", label))
+                .selected_text(selected.as_str())
+                .show_ui(ui, |ui| {
+                    for name in &names {
+                        ui.selectable_value(selected, name.clone(), name);
+                    }
+                });
+                ui.label(label).on_hover_text(hover);
+            });
+        }
+
+        let mut remove = None;
+        egui::Grid::new("physical_materials").striped(true).show(ui, |ui| {
+            ui.strong("Name");
+            ui.strong("Keywords").on_hover_text("Words in CAD material names that mean this material");
+            ui.strong("Static Friction");
+            ui.strong("Dynamic Friction");
+            ui.strong("Restitution").on_hover_text("0 for no bounce, 1 for a perfectly elastic bounce");
+            ui.strong("Friction Combine").on_hover_text("How friction is combined with the other material, the later mode in the list wins");
+            ui.strong("Restitution Combine");
+            ui.end_row();
+
+            for (index, material) in library.materials.iter_mut().enumerate() {
+                ui.label(&material.name);
+                let mut keywords = material.keywords.join(", ");
+                if ui.text_edit_singleline(&mut keywords).changed() {
+                    material.keywords = keywords.split(',')
+                        .map(|keyword| keyword.trim().to_owned())
+                        .filter(|keyword| !keyword.is_empty())
+                        .collect();
+                }
+                ui.add(egui::DragValue::new(&mut material.static_friction).speed(0.01).clamp_range(0.0..=2.0));
+                ui.add(egui::DragValue::new(&mut material.dynamic_friction).speed(0.01).clamp_range(0.0..=2.0));
+                ui.add(egui::DragValue::new(&mut material.restitution).speed(0.01).clamp_range(0.0..=1.0));
+                for (kind, mode) in [("friction", &mut material.friction_combine), ("restitution", &mut material.restitution_combine)] {
+                    egui::ComboBox::from_id_source(("combine", kind, index))
+                    .selected_text(mode.name())
+                    .show_ui(ui, |ui| {
+                        for option in CombineMode::ALL {
+                            ui.selectable_value(mode, option, option.name());
+                        }
+                    });
+                }
+                if ui.small_button("Remove").clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(index) = remove {
+            let name = &library.materials[index].name;
+            if *name == library.field || *name == library.default {
+                log.warn("Material In Use", &format!("\"{name}\" is the field or default material, pick another one before removing it."));
+            } else {
+                library.materials.remove(index);
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_material);
+            let name = self.new_material.trim();
+            if ui.add_enabled(!name.is_empty() && library.get(name).is_none(), egui::Button::new("Add Material")).clicked() {
+                library.materials.push(PhysicalMaterial::new(name, &[], 0.5, 0.4, 0.3));
+                self.new_material.clear();
+            }
+        });
+
+        let path = MaterialLibrary::default_path();
+        ui.horizontal(|ui| {
+            if ui.button("Save Library").clicked() {
+                match library.save(&path) {
+                    Ok(()) => log.info("Material Library Saved", &format!("Saved to {}.", path.display())),
+                    Err(err) => log.error("Saving Material Library Failed", &format!("{err:#}"), true),
+                }
+            }
+            if ui.button("Reload").on_hover_text("Throw away unsaved changes to the library").clicked() {
+                match MaterialLibrary::load_or_default(&path) {
+                    Ok(loaded) => *library = loaded,
+                    Err(err) => log.error("Loading Material Library Failed", &format!("{err:#}"), true),
+                }
+            }
+        });
+    }
+
+    fn add_joint_ui(&mut self, ui: &mut Ui, assembly: &mut ResMut<Assembly>, editor: &mut AssemblyEditor, log: &mut LogMessages) {
+        let joint = &mut self.new_joint;
+
+        for (i, label) in ["Between", "and"].into_iter().enumerate() {
+            joint.parts[i] = joint.parts[i].min(assembly.parts.len() - 1);
+            egui::ComboBox::from_label(label)
+            .selected_text(&assembly.parts[joint.parts[i]].name)
+            .show_ui(ui, |ui| {
+                for (index, part) in assembly.parts.iter().enumerate() {
+                    ui.selectable_value(&mut joint.parts[i], index, &part.name);
+                }
+            });
+        }
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut joint.joint_type, JointType::Revolute, "Revolute");
+            ui.radio_value(&mut joint.joint_type, JointType::Slider, "Slider");
+            ui.radio_value(&mut joint.joint_type, JointType::Rigid, "Rigid");
+        });
+
+        vector_ui(ui, "Position (in)", &mut joint.position);
+        vector_ui(ui, "Axis", &mut joint.axis);
+
+        if joint.joint_type != JointType::Rigid {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut joint.limited, "Limits");
+                ui.add_enabled(joint.limited, egui::DragValue::new(&mut joint.limits.0).speed(0.01));
+                ui.add_enabled(joint.limited, egui::DragValue::new(&mut joint.limits.1).speed(0.01));
+                ui.label(if joint.joint_type == JointType::Revolute { "rad" } else { "m" });
+            });
+        }
+
+        if ui.button("Add Joint").clicked() {
+            if joint.parts[0] == joint.parts[1] {
+                log.warn("Joint Not Added", "A joint has to connect two different parts.");
+            } else if joint.axis.length_squared() == 0. {
+                log.warn("Joint Not Added", "The joint axis can't be zero.");
+            } else {
+                let name = format!("Joint {}", assembly.joints.len() + 1);
+                editor.selected_joint = Some(assembly.add_joint(Joint {
+                    name: name.clone(),
+                    joint_type: joint.joint_type,
+                    pose: Pose(joint.position, DQuat::from_rotation_arc(DVec3::Z, joint.axis.normalize())),
+                    limits: Some(joint.limits).filter(|_| joint.limited && joint.joint_type != JointType::Rigid),
+                }, joint.parts));
+                log.info("Joint Added", &format!("Added {name}."));
+            }
+        }
+    }
+}
+
+/// Marks the hardware in a newly imported robot, so it is hidden in the viewer and left out of collisions.
+fn mark_hardware(assembly: &mut Assembly, filter: &HardwareFilter, log: &mut LogMessages) {
+    let count = filter.apply(assembly);
+    if filter.enabled && count > 0 {
+        log.info("Hardware Hidden", &format!("Hid {count} hardware part(s). Show them with View > Hardware, or change the filter in the Model tab."));
+    }
+}
+
+fn hardware_filter_ui(ui: &mut Ui, assembly: &mut ResMut<Assembly>, filter: &mut HardwareFilter, log: &mut LogMessages) {
+    ui.checkbox(&mut filter.enabled, "Hide hardware when importing");
+
+    let mut patterns = filter.name_patterns.join(", ");
+    ui.horizontal(|ui| {
+        if ui.text_edit_singleline(&mut patterns).changed() {
+            filter.name_patterns = patterns.split(',')
+                .map(|pattern| pattern.trim().to_owned())
+                .filter(|pattern| !pattern.is_empty())
+                .collect();
+        }
+        ui.label("Names").on_hover_text("Words in part names that mark hardware. A trailing * matches any ending.");
+    });
+    ui.checkbox(&mut filter.standard_content, "Standard Content names")
+    .on_hover_text("Names with a standard such as ANSI B18.3 or a thread size such as 10-32");
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut filter.max_size).speed(0.05).clamp_range(0.0..=100.0).suffix(" in"));
+        ui.label("Largest size").on_hover_text("Parts whose bounding box diagonal is shorter than this");
+    });
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut filter.max_triangles).speed(10));
+        ui.label("Most triangles").on_hover_text("Parts up to twice the largest size with more triangles than this, like threaded fasteners");
+    });
+
+    if ui.button("Apply to This Robot").clicked() {
+        let count = HardwareFilter { enabled: true, ..filter.clone() }.apply(assembly);
+        log.info("Hardware Filter Applied", &format!("Marked {count} part(s) as hardware."));
+    }
+}
+
+fn couplings_ui(ui: &mut Ui, assembly: &mut ResMut<Assembly>) {
+    let moving: Vec<usize> = (0..assembly.joints.len())
+        .filter(|&joint| assembly.joints[joint].joint_type != JointType::Rigid)
+        .collect();
+
+    let mut remove = None;
+    egui::Grid::new("couplings").striped(true).show(ui, |ui| {
+        ui.strong("Name");
+        ui.strong("Kind");
+        ui.strong("Driving");
+        ui.strong("Following");
+        ui.strong("Ratio").on_hover_text("How far the following joint moves for each radian or meter the driving joint moves, negative if it turns the other way");
+        ui.strong("Backlash").on_hover_text("How far the following joint can move without the driving one, in radians or meters");
+        ui.strong("Efficiency").on_hover_text("Fraction of the power passed from one joint to the other");
+        ui.end_row();
+
+        for index in 0..assembly.couplings.len() {
+            // Edit a copy so the assembly is only marked as changed when something was actually edited
+            let mut coupling = assembly.couplings[index].clone();
+            ui.text_edit_singleline(&mut coupling.name);
+            egui::ComboBox::from_id_source(("coupling_kind", index))
+            .selected_text(coupling.kind.name())
+            .show_ui(ui, |ui| {
+                for kind in CouplingKind::ALL {
+                    ui.selectable_value(&mut coupling.kind, kind, kind.name());
+                }
+            });
+            for (i, joint) in coupling.joints.iter_mut().enumerate() {
+                egui::ComboBox::from_id_source(("coupling_joint", index, i))
+                .selected_text(assembly.joints.get(*joint).map_or("", |joint| joint.name.as_str()))
+                .show_ui(ui, |ui| {
+                    for &option in &moving {
+                        ui.selectable_value(joint, option, &assembly.joints[option].name);
+                    }
+                });
+            }
+            ui.add(egui::DragValue::new(&mut coupling.ratio).speed(0.01));
+            ui.add(egui::DragValue::new(&mut coupling.backlash).speed(0.001).clamp_range(0.0..=1.0));
+            ui.add(egui::DragValue::new(&mut coupling.efficiency).speed(0.01).clamp_range(0.01..=1.0));
+            if ui.small_button("Remove").clicked() {
+                remove = Some(index);
+            }
+            ui.end_row();
+
+            if coupling != assembly.couplings[index] {
+                assembly.couplings[index] = coupling;
+            }
+        }
+    });
+    if let Some(index) = remove {
+        assembly.couplings.remove(index);
+    }
+
+    if ui.add_enabled(moving.len() >= 2, egui::Button::new("Add Coupling"))
+    .on_disabled_hover_text("Couplings need two revolute or slider joints")
+    .clicked() {
+        let name = format!("Coupling {}", assembly.couplings.len() + 1);
+        assembly.couplings.push(Coupling::new(&name, CouplingKind::default(), [moving[0], moving[1]]));
+    }
+}
+
+fn vector_ui(ui: &mut Ui, label: &str, vector: &mut DVec3) {
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut vector.x).speed(0.1).prefix("x: "));
+        ui.add(egui::DragValue::new(&mut vector.y).speed(0.1).prefix("y: "));
+        ui.add(egui::DragValue::new(&mut vector.z).speed(0.1).prefix("z: "));
+        ui.label(label);
+    });
+}
+
 fn bottom_panel_system(
     mut contexts: EguiContexts,
     mut bottom_panel: ResMut<BottomPanel>,
+    mut assembly: ResMut<Assembly>,
+    mut editor: ResMut<AssemblyEditor>,
+    mut joint_states: ResMut<JointStates>,
+    mut hardware_filter: ResMut<HardwareFilter>,
+    mut materials: ResMut<MaterialLibrary>,
+    mut actuator_states: ResMut<ActuatorStates>,
+    mut battery: ResMut<Battery>,
+    telemetry: Res<Telemetry>,
+    mut electrical_map: ResMut<ElectricalMap>,
+    sensor_readings: Res<SensorReadings>,
+    mut hal_sim: ResMut<HalSim>,
+    mut network_tables: ResMut<NetworkTables>,
     log: ResMut<LogMessages>
 ) {
     egui::TopBottomPanel::bottom("bottom_panel")
     .resizable(true)
     .show(contexts.ctx_mut(), |ui| {
-        bottom_panel.ui(ui, log);
+        bottom_panel.ui(ui, &mut assembly, &mut editor, &mut joint_states, &mut hardware_filter, &mut materials, &mut actuator_states, &mut battery, &telemetry, &mut electrical_map, &sensor_readings, &mut hal_sim, &mut network_tables, log);
     });
 }
 
+fn load_material_library(mut materials: ResMut<MaterialLibrary>, mut log: ResMut<LogMessages>) {
+    match MaterialLibrary::load_or_default(&MaterialLibrary::default_path()) {
+        Ok(loaded) => *materials = loaded,
+        Err(err) => log.error("Loading Material Library Failed", &format!("{err:#}\n\nUsing the built in materials."), false),
+    }
+}
+
 #[derive(Default)]
-struct FilePanel;
+struct FilePanel {
+    /// Animate .glb exports with the joint positions recorded so far
+    animate_export: bool,
+    /// Mesh files waiting for their units to be picked
+    mesh_files: Vec<PathBuf>,
+    mesh_unit: LengthUnit,
+    add_meshes: bool,
+}
 
 impl FilePanel {
-    fn ui(&mut self, ui: &mut Ui, mut assembly: ResMut<Assembly>, log: &mut LogMessages) {
+    fn ui(&mut self, ui: &mut Ui, assembly: &mut ResMut<Assembly>, joint_states: &JointStates, telemetry: &Telemetry, hardware_filter: &HardwareFilter, log: &mut LogMessages) {
         if ui.button("Import Robot").clicked() {
+            let assemblies_dir = paths::get().assemblies();
+            std::fs::create_dir_all(&assemblies_dir).ok();
+
             let path = FileDialog::new()
-                .set_location("C:\\Users\\Public\\MechSim\\assemblies")
+                .set_location(&assemblies_dir)
                 .add_filter("MRR Robot Description", &["mrr"])
+                .add_filter("MJCF Model", &["xml", "mjcf"])
+                .add_filter("URDF Robot Description", &["urdf"])
+                .add_filter("Mesh", &["stl", "obj"])
                 .show_open_single_file()
                 .unwrap();
 
@@ -121,12 +1347,89 @@ impl FilePanel {
                 None => return,
             };
 
-            assembly.file_path = path;
+            if meshes::is_mesh_file(&path) {
+                self.mesh_files = vec![path];
+                return;
+            }
 
-            assembly.load_meshes();
-            println!("Import Robot");
+            match Assembly::open(&path) {
+                Ok(opened) => {
+                    **assembly = opened;
+                    assembly.load_meshes();
+                    log.info("Robot Imported", &format!("Imported {}.", assembly.get_name()));
+                    for warning in &assembly.warnings {
+                        log.warn("Import Warning", warning);
+                    }
+                    mark_hardware(assembly, hardware_filter, log);
+                }
+                Err(e) => log.error("Import Failed", &format!("{e:#}"), true),
+            }
         }
 
+        if ui.button("Import Meshes").clicked() {
+            let paths = FileDialog::new()
+                .add_filter("Mesh", &["stl", "obj"])
+                .show_open_multiple_file()
+                .unwrap();
+            if !paths.is_empty() {
+                self.mesh_files = paths;
+            }
+        }
+
+        if ui.button("Save Robot").clicked() {
+            let path = FileDialog::new()
+                .set_location(&paths::get().assemblies())
+                .set_filename(&format!("{}.mrr", assembly.get_name()))
+                .add_filter("MRR Robot Description", &["mrr"])
+                .show_save_single_file()
+                .unwrap();
+
+            if let Some(path) = path.map(|path| path.with_extension("mrr")) {
+                match assembly.export(&path) {
+                    Ok(warnings) => {
+                        log.info("Robot Saved", &format!("Saved {} to {}.", assembly.get_name(), path.display()));
+                        for warning in &warnings {
+                            log.warn("Save Warning", warning);
+                        }
+                    }
+                    Err(e) => log.error("Save Failed", &format!("{e:#}"), true),
+                }
+            }
+        }
+
+        if ui.button("Export Robot").clicked() {
+            let path = FileDialog::new()
+                .set_location(&paths::get().assemblies())
+                .set_filename(&format!("{}.urdf", assembly.get_name()))
+                .add_filter("URDF Robot Description", &["urdf"])
+                .add_filter("glTF Binary", &["glb"])
+                .show_save_single_file()
+                .unwrap();
+
+            if let Some(path) = path {
+                let result = if path.extension().and_then(OsStr::to_str) == Some("glb") {
+                    let options = GlbOptions {
+                        joint_positions: &joint_states.positions(),
+                        telemetry: Some(telemetry).filter(|_| self.animate_export),
+                    };
+                    gltf::save(assembly, &path, &options)
+                } else {
+                    assembly.export(&path)
+                };
+
+                match result {
+                    Ok(warnings) => {
+                        log.info("Robot Exported", &format!("Exported {} to {}.", assembly.get_name(), path.display()));
+                        for warning in &warnings {
+                            log.warn("Export Warning", warning);
+                        }
+                    }
+                    Err(e) => log.error("Export Failed", &format!("{e:#}"), true),
+                }
+            }
+        }
+        ui.checkbox(&mut self.animate_export, "Animate glTF exports from telemetry");
+
         let response = ui.button("Delete Robot");
         if response.clicked() {
             println!("{}", assembly.get_name());
@@ -142,8 +1445,58 @@ impl FilePanel {
         }
     }
 
-    fn windows(&mut self, ui: &mut Ui) {
+    fn windows(&mut self, ui: &mut Ui, assembly: &mut ResMut<Assembly>, hardware_filter: &HardwareFilter, log: &mut LogMessages) {
+        self.mesh_units_window(ui, assembly, hardware_filter, log);
+    }
 
+    fn mesh_units_window(&mut self, ui: &mut Ui, assembly: &mut ResMut<Assembly>, hardware_filter: &HardwareFilter, log: &mut LogMessages) {
+        if self.mesh_files.is_empty() {
+            return;
+        }
+
+        let mut open = true;
+        let mut import = false;
+        egui::Window::new("Import Meshes")
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut open)
+        .show(ui.ctx(), |ui| {
+            for path in &self.mesh_files {
+                ui.label(path.file_name().and_then(OsStr::to_str).unwrap_or_default());
+            }
+            ui.separator();
+
+            egui::ComboBox::from_label("Units")
+            .selected_text(self.mesh_unit.to_string())
+            .show_ui(ui, |ui| {
+                for unit in LengthUnit::ALL {
+                    ui.selectable_value(&mut self.mesh_unit, unit, unit.to_string());
+                }
+            });
+            ui.checkbox(&mut self.add_meshes, "Add to the current robot");
+
+            import = ui.button("Import").clicked();
+        });
+
+        if import {
+            let files = std::mem::take(&mut self.mesh_files);
+            let result = if self.add_meshes {
+                files.iter().try_for_each(|path| meshes::add(assembly, path, self.mesh_unit))
+            } else {
+                meshes::load(&files, self.mesh_unit).map(|opened| **assembly = opened)
+            };
+
+            match result {
+                Ok(()) => {
+                    assembly.load_meshes();
+                    log.info("Meshes Imported", &format!("Imported {} mesh file(s) into {}.", files.len(), assembly.get_name()));
+                    mark_hardware(assembly, hardware_filter, log);
+                }
+                Err(e) => log.error("Import Failed", &format!("{e:#}"), true),
+            }
+        } else if !open {
+            self.mesh_files.clear();
+        }
     }
 }
 
@@ -206,15 +1559,80 @@ impl HelpPanel {
     }
 }
 
+#[derive(Default)]
+struct ViewPanel {
+    /// Levels of detail being edited, until they're applied
+    lod_levels: Option<Vec<LodLevel>>,
+}
+
+impl ViewPanel {
+    fn ui(&mut self, ui: &mut Ui, settings: &mut ResMut<ViewerSettings>, lod_settings: &mut ResMut<LodSettings>) {
+        let (mut show_joints, mut show_hardware) = (settings.show_joints, settings.show_hardware);
+        ui.checkbox(&mut show_joints, "Joints");
+        ui.checkbox(&mut show_hardware, "Hardware")
+        .on_hover_text("Nuts, bolts and other small parts picked out by the hardware filter in the Model tab");
+        if (show_joints, show_hardware) != (settings.show_joints, settings.show_hardware) {
+            settings.show_joints = show_joints;
+            settings.show_hardware = show_hardware;
+        }
+
+        ui.menu_button("Color By", |ui| {
+            for color_by in ColorBy::ALL {
+                if ui.radio(settings.color_by == color_by, color_by.name()).clicked() && settings.color_by != color_by {
+                    settings.color_by = color_by;
+                }
+            }
+        });
+        ui.menu_button("Level of Detail", |ui| self.lod_ui(ui, lod_settings));
+    }
+
+    fn lod_ui(&mut self, ui: &mut Ui, lod_settings: &mut ResMut<LodSettings>) {
+        let mut enabled = lod_settings.enabled;
+        ui.checkbox(&mut enabled, "Simplify distant parts")
+        .on_hover_text("Draws bodies with fewer triangles when they're small on screen. Simplified meshes are saved next to the robot file.");
+        if enabled != lod_settings.enabled {
+            lod_settings.enabled = enabled;
+        }
+
+        let levels = self.lod_levels.get_or_insert_with(|| lod_settings.levels.clone());
+        egui::Grid::new("lod_levels").show(ui, |ui| {
+            ui.label("Level");
+            ui.label("Triangles kept");
+            ui.label("Below view height");
+            ui.end_row();
+
+            for (index, level) in levels.iter_mut().enumerate() {
+                let (mut ratio, mut screen_size) = (level.ratio * 100., level.screen_size * 100.);
+                ui.label((index + 1).to_string());
+                ui.add(egui::DragValue::new(&mut ratio).speed(0.1).clamp_range(0.1..=100.0).suffix("%"));
+                ui.add(egui::DragValue::new(&mut screen_size).speed(0.1).clamp_range(0.0..=100.0).suffix("%"));
+                level.ratio = ratio / 100.;
+                level.screen_size = screen_size / 100.;
+                ui.end_row();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.add_enabled(*levels != lod_settings.levels, egui::Button::new("Apply")).clicked() {
+                lod_settings.levels = levels.clone();
+            }
+            if ui.button("Reset").clicked() {
+                *levels = LodSettings::default().levels;
+            }
+        });
+    }
+}
+
 #[derive(Resource, Default)]
 pub struct MenuBar {
     file_panel: FilePanel,
+    view_panel: ViewPanel,
     help_panel: HelpPanel,
 }
 
 impl MenuBar {
-    fn windows(&mut self, ui: &mut Ui) {
-        self.file_panel.windows(ui);
+    fn windows(&mut self, ui: &mut Ui, assembly: &mut ResMut<Assembly>, hardware_filter: &HardwareFilter, log: &mut LogMessages) {
+        self.file_panel.windows(ui, assembly, hardware_filter, log);
         self.help_panel.windows(ui);
     }
 }
@@ -222,19 +1640,28 @@ impl MenuBar {
 fn menu_bar_system(
     mut contexts: EguiContexts,
     mut menu_bar: ResMut<MenuBar>,
-    assembly: ResMut<Assembly>,
+    mut assembly: ResMut<Assembly>,
+    joint_states: Res<JointStates>,
+    telemetry: Res<Telemetry>,
+    mut viewer_settings: ResMut<ViewerSettings>,
+    mut lod_settings: ResMut<LodSettings>,
+    hardware_filter: Res<HardwareFilter>,
     mut log: ResMut<LogMessages>
 ) {
     egui::TopBottomPanel::top("menu_bar").show(contexts.ctx_mut(), |ui| {
         egui::menu::bar(ui, |ui| {
             ui.menu_button("File", |ui| {
-                menu_bar.file_panel.ui(ui, assembly, log.as_mut());
+                menu_bar.file_panel.ui(ui, &mut assembly, &joint_states, &telemetry, &hardware_filter, log.as_mut());
+            });
+
+            ui.menu_button("View", |ui| {
+                menu_bar.view_panel.ui(ui, &mut viewer_settings, &mut lod_settings);
             });
 
             ui.menu_button("Help", |ui| {
                 menu_bar.help_panel.ui(ui, log.as_mut());
             });
-            menu_bar.windows(ui); 
+            menu_bar.windows(ui, &mut assembly, &hardware_filter, log.as_mut());
         });
     });
 }
@@ -246,6 +1673,9 @@ impl Plugin for UIPlugin {
         app.add_plugin(WorldInspectorPlugin::new())
         .init_resource::<BottomPanel>()
         .init_resource::<MenuBar>()
+        .init_resource::<HardwareFilter>()
+        .init_resource::<MaterialLibrary>()
+        .add_startup_system(load_material_library)
         .add_system(bottom_panel_system)
         .add_system(menu_bar_system);
     }
@@ -0,0 +1,657 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use bevy::math::{DQuat, DVec3, EulerRot, Vec3};
+use roxmltree::{Document, Node};
+
+use crate::kinematics::KinematicTree;
+use crate::mrr::{Assembly, Body, Inertial, Joint, JointType, Part, Pose, METERS_PER_UNIT, Z_UP};
+use crate::{meshes, primitives, stl};
+
+/// Effort limit written for joints without an actuator, in N·m or N. URDF requires one on every moving joint.
+const DEFAULT_EFFORT_LIMIT: f64 = 1000.;
+/// Velocity limit written for every moving joint, in rad/s or m/s.
+const DEFAULT_VELOCITY_LIMIT: f64 = 100.;
+/// Travel written for sliders without limits, in meters, since URDF prismatic joints must have limits.
+const UNLIMITED_SLIDER_TRAVEL: f64 = 1000.;
+/// Directory next to an exported URDF file that its meshes are written to.
+const MESH_DIR: &str = "meshes";
+
+/// Imports a URDF robot description.
+pub fn load(path: &Path) -> Result<Assembly> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    parse(&text, path.parent().unwrap_or(Path::new("")))
+}
+
+/// Imports a URDF robot description from `text`, resolving mesh files relative to `dir`.
+pub fn parse(text: &str, dir: &Path) -> Result<Assembly> {
+    let document = Document::parse(text)?;
+    let root = document.root_element();
+    if !root.has_tag_name("robot") {
+        bail!("Expected a <robot> root element, found <{}>", root.tag_name().name())
+    }
+
+    let mut importer = Importer {
+        dir: dir.to_owned(),
+        links: HashMap::new(),
+        child_joints: HashMap::new(),
+        read_links: HashSet::new(),
+        loaded_meshes: HashMap::new(),
+        next_rigid_group: 0,
+        assembly: Assembly::default(),
+    };
+
+    let mut link_names = vec![];
+    for link in root.children().filter(|child| child.has_tag_name("link")) {
+        let name = link.attribute("name").context("Link without a name")?;
+        importer.links.insert(name, link);
+        link_names.push(name);
+    }
+
+    let mut child_links = HashSet::new();
+    for joint in root.children().filter(|child| child.has_tag_name("joint")) {
+        let parent = child_link(joint, "parent")?;
+        let child = child_link(joint, "child")?;
+        if !importer.links.contains_key(parent) || !importer.links.contains_key(child) {
+            bail!("Joint \"{}\" connects links that don't exist", joint.attribute("name").unwrap_or_default())
+        }
+
+        importer.child_joints.entry(parent).or_default().push(joint);
+        child_links.insert(child);
+    }
+
+    // URDF is Z-up, so root links are turned to stand the robot up in the assembly
+    for name in link_names.into_iter().filter(|name| !child_links.contains(name)) {
+        let rigid_group = importer.new_rigid_group();
+        importer.read_link(name, &Pose(DVec3::ZERO, Z_UP), rigid_group)?;
+    }
+
+    Ok(importer.assembly)
+}
+
+fn child_link<'a>(joint: Node<'a, '_>, tag: &str) -> Result<&'a str> {
+    joint.children()
+        .find(|child| child.has_tag_name(tag))
+        .and_then(|child| child.attribute("link"))
+        .with_context(|| format!("Joint \"{}\" has no {tag} link", joint.attribute("name").unwrap_or_default()))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(tag))
+}
+
+fn parse_vector(text: &str) -> Result<DVec3> {
+    let numbers = text.split_whitespace()
+        .map(|number| number.parse::<f64>().with_context(|| format!("Invalid number \"{number}\"")))
+        .collect::<Result<Vec<f64>>>()?;
+    match numbers[..] {
+        [x, y, z] => Ok(DVec3::new(x, y, z)),
+        _ => bail!("Expected 3 numbers, found {}", numbers.len()),
+    }
+}
+
+fn parse_number(node: Node, name: &str) -> Result<Option<f64>> {
+    node.attribute(name)
+        .map(|value| value.parse::<f64>().with_context(|| format!("Invalid {name} \"{value}\"")))
+        .transpose()
+}
+
+/// Reads the `<origin>` of `node`, in meters.
+fn origin(node: Node) -> Result<Pose> {
+    let Some(origin) = child(node, "origin") else {
+        return Ok(Pose::IDENTITY);
+    };
+
+    let xyz = origin.attribute("xyz").map(parse_vector).transpose().context("In origin xyz")?;
+    let rpy = origin.attribute("rpy").map(parse_vector).transpose().context("In origin rpy")?.unwrap_or_default();
+    // Roll, pitch and yaw are applied about the fixed X, Y and Z axes, in that order
+    Ok(Pose(xyz.unwrap_or_default(), DQuat::from_euler(EulerRot::ZYX, rpy.z, rpy.y, rpy.x)))
+}
+
+fn to_units(pose: &Pose) -> Pose {
+    Pose(pose.0 / METERS_PER_UNIT, pose.1)
+}
+
+struct Importer<'a, 'input> {
+    dir: PathBuf,
+    links: HashMap<&'a str, Node<'a, 'input>>,
+    child_joints: HashMap<&'a str, Vec<Node<'a, 'input>>>,
+    read_links: HashSet<String>,
+    loaded_meshes: HashMap<String, Vec<Body>>,
+    next_rigid_group: u32,
+    assembly: Assembly,
+}
+
+impl<'a, 'input> Importer<'a, 'input> {
+    fn new_rigid_group(&mut self) -> u32 {
+        self.next_rigid_group += 1;
+        self.next_rigid_group - 1
+    }
+
+    fn warn(&mut self, warning: String) {
+        self.assembly.warnings.push(warning);
+    }
+
+    /// Adds the link at `pose` and everything attached to it, returning the index of its part.
+    fn read_link(&mut self, name: &str, pose: &Pose, rigid_group: u32) -> Result<usize> {
+        if !self.read_links.insert(name.to_owned()) {
+            bail!("Link \"{name}\" is the child of more than one joint")
+        }
+        let link = self.links[name];
+
+        // Collision geometry is only used when a link has nothing to show
+        let mut geometry: Vec<Node> = link.children().filter(|child| child.has_tag_name("visual")).collect();
+        if geometry.is_empty() {
+            geometry = link.children().filter(|child| child.has_tag_name("collision")).collect();
+        }
+
+        let mut bodies = vec![];
+        for element in geometry {
+            let element_pose = pose.then(&origin(element).with_context(|| format!("In link \"{name}\""))?);
+            for mut body in self.read_geometry(element).with_context(|| format!("In link \"{name}\""))? {
+                body.transform(&to_units(&element_pose), 1. / METERS_PER_UNIT);
+                bodies.push(body);
+            }
+        }
+
+        let inertial = match child(link, "inertial") {
+            Some(inertial) => Some(read_inertial(inertial, pose).with_context(|| format!("In the inertial of link \"{name}\""))?),
+            None => None,
+        };
+
+        let part = self.assembly.parts.len();
+        self.assembly.parts.push(Part {
+            name: name.to_owned(),
+            pose: to_units(pose),
+            joint_references: vec![],
+            rigid_group_references: vec![rigid_group],
+            bodies,
+            inertial,
+            hardware: false,
+            appearance: None,
+            material: None,
+            physical_material: None,
+        });
+
+        for joint in self.child_joints.get(name).cloned().unwrap_or_default() {
+            self.read_joint(joint, pose, part, rigid_group)
+                .with_context(|| format!("In joint \"{}\"", joint.attribute("name").unwrap_or_default()))?;
+        }
+        Ok(part)
+    }
+
+    fn read_joint(&mut self, node: Node<'a, 'input>, parent_pose: &Pose, parent_part: usize, parent_rigid_group: u32) -> Result<()> {
+        let name = node.attribute("name").unwrap_or_default().to_owned();
+        let frame = parent_pose.then(&origin(node)?);
+        let child_name = child_link(node, "child")?;
+
+        let joint_type = match node.attribute("type") {
+            Some("revolute") | Some("continuous") => Some(JointType::Revolute),
+            Some("prismatic") => Some(JointType::Slider),
+            Some("fixed") => Some(JointType::Rigid),
+            Some(other) => {
+                self.warn(format!("{other} joint \"{name}\" was not imported, its child link is left free"));
+                None
+            }
+            None => bail!("Joint has no type"),
+        };
+
+        let rigid_group = match joint_type {
+            Some(JointType::Rigid) => parent_rigid_group,
+            _ => self.new_rigid_group(),
+        };
+        let Some(joint_type) = joint_type else {
+            self.read_link(child_name, &frame, rigid_group)?;
+            return Ok(());
+        };
+
+        let axis = match child(node, "axis").and_then(|axis| axis.attribute("xyz")) {
+            Some(xyz) => parse_vector(xyz).context("In axis")?.normalize(),
+            None => DVec3::X,
+        };
+
+        let limits = match (node.attribute("type"), child(node, "limit")) {
+            (Some("revolute") | Some("prismatic"), Some(limit)) => Some((
+                parse_number(limit, "lower")?.unwrap_or(0.),
+                parse_number(limit, "upper")?.unwrap_or(0.),
+            )),
+            _ => None,
+        };
+
+        let index = self.assembly.joints.len() as u32;
+        self.assembly.joints.push(Joint {
+            name,
+            joint_type,
+            pose: to_units(&frame.then(&Pose(DVec3::ZERO, DQuat::from_rotation_arc(DVec3::Z, axis)))),
+            limits,
+        });
+
+        let child_part = self.read_link(child_name, &frame, rigid_group)?;
+        self.assembly.parts[parent_part].joint_references.push(index);
+        self.assembly.parts[child_part].joint_references.push(index);
+        Ok(())
+    }
+
+    /// Reads the shape in a `<visual>` or `<collision>` element, in meters relative to the element's origin.
+    fn read_geometry(&mut self, node: Node) -> Result<Vec<Body>> {
+        let Some(shape) = child(node, "geometry").and_then(|geometry| geometry.children().find(Node::is_element)) else {
+            return Ok(vec![]);
+        };
+        let number = |name: &str| -> Result<f32> {
+            Ok(parse_number(shape, name)?.with_context(|| format!("<{}> without a {name}", shape.tag_name().name()))? as f32)
+        };
+
+        Ok(match shape.tag_name().name() {
+            "box" => {
+                let size = parse_vector(shape.attribute("size").context("Box without a size")?).context("In box size")?;
+                vec![primitives::cuboid(size.as_vec3() / 2.)]
+            }
+            "cylinder" => vec![primitives::cylinder(number("radius")?, number("length")? / 2.)],
+            "sphere" => vec![primitives::ellipsoid(Vec3::splat(number("radius")?))],
+            "capsule" => primitives::capsule(number("radius")?, number("length")? / 2.),
+            "mesh" => {
+                let filename = shape.attribute("filename").context("Mesh without a filename")?;
+                let scale = shape.attribute("scale").map(parse_vector).transpose().context("In mesh scale")?;
+                self.load_mesh(filename)
+                    .into_iter()
+                    .map(|mut body| {
+                        body.scale(scale.unwrap_or(DVec3::ONE).as_vec3());
+                        body
+                    })
+                    .collect()
+            }
+            other => {
+                self.warn(format!("Geometry type {other} is not supported"));
+                vec![]
+            }
+        })
+    }
+
+    /// Loads the bodies of a mesh file, warning and returning none if it can't be loaded.
+    fn load_mesh(&mut self, filename: &str) -> Vec<Body> {
+        if let Some(mesh) = self.loaded_meshes.get(filename) {
+            return mesh.clone();
+        }
+
+        let mesh = match meshes::load_file(&self.resolve(filename)) {
+            Ok(objects) => objects.into_iter().map(|(_, body)| body).collect(),
+            Err(e) => {
+                self.warn(format!("{e:#}"));
+                vec![]
+            }
+        };
+        self.loaded_meshes.insert(filename.to_owned(), mesh.clone());
+        mesh
+    }
+
+    /// Finds the file a mesh filename refers to. `package://` paths are looked up in the directory of the
+    /// URDF file and the directories above it, since ROS packages usually keep it in a subdirectory.
+    fn resolve(&self, filename: &str) -> PathBuf {
+        if let Some(path) = filename.strip_prefix("file://") {
+            return PathBuf::from(path);
+        }
+
+        let Some(path) = filename.strip_prefix("package://") else {
+            return self.dir.join(filename);
+        };
+        let (package, relative) = path.split_once('/').unwrap_or((path, ""));
+
+        self.dir.ancestors()
+            .flat_map(|dir| [dir.join(package).join(relative), dir.join(relative)])
+            .find(|candidate| candidate.is_file())
+            .unwrap_or_else(|| self.dir.join(relative))
+    }
+}
+
+fn read_inertial(node: Node, link_pose: &Pose) -> Result<Inertial> {
+    let pose = link_pose.then(&origin(node)?);
+    let mass = child(node, "mass")
+        .map(|mass| parse_number(mass, "value"))
+        .transpose()?
+        .flatten()
+        .context("Inertial without a mass")?;
+
+    // Only the moments are kept, the products of inertia are dropped
+    let inertia = match child(node, "inertia") {
+        Some(inertia) => DVec3::new(
+            parse_number(inertia, "ixx")?.unwrap_or(0.),
+            parse_number(inertia, "iyy")?.unwrap_or(0.),
+            parse_number(inertia, "izz")?.unwrap_or(0.),
+        ),
+        None => DVec3::ZERO,
+    };
+
+    Ok(Inertial {
+        mass,
+        center_of_mass: pose.0 / METERS_PER_UNIT,
+        inertia,
+    })
+}
+
+/// Writes `assembly` as a URDF file, with the bodies of each link in an STL file in a `meshes`
+/// directory next to it. Rigid groups become links. Returns anything that couldn't be exported.
+pub fn save(assembly: &Assembly, path: &Path) -> Result<Vec<String>> {
+    let tree = KinematicTree::new(assembly);
+    let mut warnings = tree.warnings.clone();
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    std::fs::create_dir_all(dir.join(MESH_DIR))?;
+
+    // Each link's frame is the frame of the joint to its parent, so joint axes are always Z. Root links
+    // are Z-up, turned back from the assembly the way they're turned on import.
+    let frames: Vec<Pose> = (0..tree.links.len())
+        .map(|link| match tree.links[link].parent {
+            Some(_) => tree.rest_frame(assembly, link),
+            None => Pose(DVec3::ZERO, Z_UP),
+        })
+        .collect();
+
+    let mut used_names = HashSet::new();
+    let link_names: Vec<String> = tree.links.iter()
+        .map(|link| unique_name(&mut used_names, &assembly.parts[link.parts[0]].name, "link"))
+        .collect();
+
+    // Link names can differ only in characters file names can't have, or in case, so file names are made
+    // unique on their own
+    let mut used_files = HashSet::new();
+    let mut new_mesh_path = |name: &str| format!("{MESH_DIR}/{}.stl", unique_file_name(&mut used_files, name));
+
+    let mut urdf = String::new();
+    writeln!(urdf, "<?xml version=\"1.0\"?>")?;
+    writeln!(urdf, "<robot name=\"{}\">", escape(assembly.get_name()))?;
+
+    for (index, link) in tree.links.iter().enumerate() {
+        let name = &link_names[index];
+        // From assembly units to meters in the link's frame
+        let to_link = frames[index].inverse();
+        let to_link = Pose(to_link.0 * METERS_PER_UNIT, to_link.1);
+
+        writeln!(urdf, "  <link name=\"{}\">", escape(name))?;
+
+        let link_bodies = |include_hardware: bool| -> Vec<Body> {
+            link.parts.iter()
+                .filter(|&&part| include_hardware || !assembly.parts[part].hardware)
+                .flat_map(|&part| assembly.parts[part].bodies.iter().cloned())
+                .map(|mut body| {
+                    body.transform(&to_link, METERS_PER_UNIT);
+                    body
+                })
+                .collect()
+        };
+        let bodies = link_bodies(true);
+        if !bodies.is_empty() {
+            let mesh_path = new_mesh_path(name);
+            stl::save(&dir.join(&mesh_path), &bodies)?;
+            write_geometry(&mut urdf, "visual", &mesh_path)?;
+
+            // Hardware is left out of collisions
+            let collision_bodies = link_bodies(false);
+            if collision_bodies.len() == bodies.len() {
+                write_geometry(&mut urdf, "collision", &mesh_path)?;
+            } else if !collision_bodies.is_empty() {
+                let collision_path = new_mesh_path(&format!("{name}_collision"));
+                stl::save(&dir.join(&collision_path), &collision_bodies)?;
+                write_geometry(&mut urdf, "collision", &collision_path)?;
+            }
+        }
+
+        let inertials: Vec<(Inertial, DVec3)> = link.parts.iter()
+            .filter_map(|&part| assembly.parts[part].inertial)
+            .map(|inertial| (inertial, to_link.transform_point(inertial.center_of_mass * METERS_PER_UNIT)))
+            .collect();
+        let mass: f64 = inertials.iter().map(|(inertial, _)| inertial.mass).sum();
+        if mass > 0. {
+            let center_of_mass = inertials.iter()
+                .map(|(inertial, center)| *center * inertial.mass)
+                .sum::<DVec3>() / mass;
+            // Moments about the combined center of mass. The parts' own orientations are ignored,
+            // so this is only exact when their principal axes line up with the link's.
+            let inertia = inertials.iter()
+                .map(|(inertial, center)| {
+                    let offset = *center - center_of_mass;
+                    inertial.inertia + inertial.mass * DVec3::new(
+                        offset.y * offset.y + offset.z * offset.z,
+                        offset.x * offset.x + offset.z * offset.z,
+                        offset.x * offset.x + offset.y * offset.y,
+                    )
+                })
+                .sum::<DVec3>();
+
+            writeln!(urdf, "    <inertial>")?;
+            writeln!(urdf, "      <origin xyz=\"{}\"/>", vector(center_of_mass))?;
+            writeln!(urdf, "      <mass value=\"{mass}\"/>")?;
+            writeln!(urdf, "      <inertia ixx=\"{}\" ixy=\"0\" ixz=\"0\" iyy=\"{}\" iyz=\"0\" izz=\"{}\"/>", inertia.x, inertia.y, inertia.z)?;
+            writeln!(urdf, "    </inertial>")?;
+        }
+
+        writeln!(urdf, "  </link>")?;
+    }
+
+    let mut used_names = HashSet::new();
+    for (index, link) in tree.links.iter().enumerate() {
+        let Some((parent, joint_index)) = link.parent else {
+            continue;
+        };
+        let joint = &assembly.joints[joint_index];
+        let name = unique_name(&mut used_names, &joint.name, "joint");
+
+        let joint_type = match (joint.joint_type, joint.limits) {
+            (JointType::Rigid, _) => "fixed",
+            (JointType::Revolute, Some(_)) => "revolute",
+            (JointType::Revolute, None) => "continuous",
+            (JointType::Slider, _) => "prismatic",
+        };
+        let origin = frames[parent].inverse().then(&frames[index]);
+        let (roll, pitch, yaw) = {
+            let (yaw, pitch, roll) = origin.1.to_euler(EulerRot::ZYX);
+            (roll, pitch, yaw)
+        };
+
+        writeln!(urdf, "  <joint name=\"{}\" type=\"{joint_type}\">", escape(&name))?;
+        writeln!(urdf, "    <parent link=\"{}\"/>", escape(&link_names[parent]))?;
+        writeln!(urdf, "    <child link=\"{}\"/>", escape(&link_names[index]))?;
+        writeln!(urdf, "    <origin xyz=\"{}\" rpy=\"{roll} {pitch} {yaw}\"/>", vector(origin.0 * METERS_PER_UNIT))?;
+
+        if joint.joint_type != JointType::Rigid {
+            writeln!(urdf, "    <axis xyz=\"0 0 1\"/>")?;
+
+            let effort = assembly.actuators.iter()
+                .filter(|actuator| actuator.joint == joint_index)
+                .find_map(|actuator| match (actuator.force_range, actuator.motor) {
+                    (Some((lower, upper)), _) => Some(lower.abs().max(upper.abs()) * actuator.gear.abs()),
+                    (None, Some(motor)) => Some(motor.stall_torque() * actuator.gear.abs()),
+                    (None, None) => None,
+                })
+                .unwrap_or(DEFAULT_EFFORT_LIMIT);
+            let limits = match (joint.joint_type, joint.limits) {
+                (_, Some((lower, upper))) => format!(" lower=\"{lower}\" upper=\"{upper}\""),
+                (JointType::Slider, None) => {
+                    warnings.push(format!("{} has no limits, it was exported with {UNLIMITED_SLIDER_TRAVEL} m of travel each way", joint.name));
+                    format!(" lower=\"{}\" upper=\"{UNLIMITED_SLIDER_TRAVEL}\"", -UNLIMITED_SLIDER_TRAVEL)
+                }
+                _ => String::new(),
+            };
+            writeln!(urdf, "    <limit{limits} effort=\"{effort}\" velocity=\"{DEFAULT_VELOCITY_LIMIT}\"/>")?;
+        }
+
+        writeln!(urdf, "  </joint>")?;
+    }
+
+    if tree.links.iter().filter(|link| link.parent.is_none()).count() > 1 {
+        warnings.push("Some parts aren't connected to the rest of the robot, URDF only allows one root link".to_owned());
+    }
+
+    writeln!(urdf, "</robot>")?;
+    std::fs::write(path, urdf).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(warnings)
+}
+
+fn vector(vector: DVec3) -> String {
+    format!("{} {} {}", vector.x, vector.y, vector.z)
+}
+
+/// Returns `name`, or `fallback` if it is empty, with a number added if it was already used.
+fn unique_name(used: &mut HashSet<String>, name: &str, fallback: &str) -> String {
+    let name = if name.is_empty() { fallback } else { name };
+    let mut unique = name.to_owned();
+    let mut count = 1;
+    while !used.insert(unique.clone()) {
+        count += 1;
+        unique = format!("{name}_{count}");
+    }
+    unique
+}
+
+fn write_geometry(urdf: &mut String, element: &str, mesh_path: &str) -> std::fmt::Result {
+    writeln!(urdf, "    <{element}>")?;
+    writeln!(urdf, "      <geometry><mesh filename=\"{}\"/></geometry>", escape(mesh_path))?;
+    writeln!(urdf, "    </{element}>")
+}
+
+/// Returns `name` with only characters every file system allows, with a number added if it was already
+/// used, ignoring case.
+fn unique_file_name(used: &mut HashSet<String>, name: &str) -> String {
+    let name: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    let mut unique = name.clone();
+    let mut count = 1;
+    while !used.insert(unique.to_ascii_lowercase()) {
+        count += 1;
+        unique = format!("{name}_{count}");
+    }
+    unique
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mrr::UP;
+
+    fn part(name: &str, joint_references: Vec<u32>, rigid_group: u32, offset: f32) -> Part {
+        Part {
+            name: name.to_owned(),
+            pose: Pose::IDENTITY,
+            joint_references,
+            rigid_group_references: vec![rigid_group],
+            bodies: vec![Body::from_triangles(vec![0., offset, 0., 1., offset, 0., 0., offset, 1.], vec![0, 1, 2])],
+            inertial: Some(Inertial { mass: 1., center_of_mass: DVec3::new(0., offset as f64, 0.), inertia: DVec3::ONE }),
+            hardware: false,
+            appearance: None,
+            material: None,
+            physical_material: None,
+        }
+    }
+
+    /// A directory of its own for each test, so tests writing files can run at the same time.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mechsim-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn stands_z_up_robots_up() {
+        let assembly = parse(r#"
+            <robot name="turret">
+                <link name="base"/>
+                <link name="turret"/>
+                <joint name="spin" type="continuous">
+                    <parent link="base"/>
+                    <child link="turret"/>
+                    <origin xyz="0 0 1"/>
+                    <axis xyz="0 0 1"/>
+                </joint>
+            </robot>
+        "#, Path::new("")).unwrap();
+        let joint = &assembly.joints[0];
+        assert_eq!(joint.joint_type, JointType::Revolute);
+        assert!(joint.axis().abs_diff_eq(UP, 1e-9));
+        assert!(joint.pose.0.abs_diff_eq(UP / METERS_PER_UNIT, 1e-9));
+    }
+
+    #[test]
+    fn rejects_malformed_robots() {
+        let robot = |body: &str| parse(&format!("<robot name=\"robot\">{body}</robot>"), Path::new(""));
+        assert!(parse("<mujoco/>", Path::new("")).is_err());
+        assert!(robot(r#"<link name="a"/><joint name="j" type="fixed"><parent link="a"/><child link="b"/></joint>"#).is_err());
+        assert!(robot(r#"<link name="a"/><link name="b"/>
+            <joint name="j" type="fixed"><parent link="a"/><child link="b"/></joint>
+            <joint name="k" type="fixed"><parent link="a"/><child link="b"/></joint>"#).is_err());
+        assert!(robot(r#"<link name="a"><visual><origin xyz="1 2"/></visual></link>"#).is_err());
+        assert!(robot(r#"<link name="a"><inertial><mass value="heavy"/></inertial></link>"#).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let assembly = Assembly {
+            parts: vec![part("Base", vec![0], 0, 0.), part("Arm", vec![0], 1, 10.)],
+            joints: vec![Joint {
+                name: "Shoulder".to_owned(),
+                joint_type: JointType::Revolute,
+                pose: Pose(DVec3::new(0., 10., 0.), DQuat::from_rotation_arc(DVec3::Z, UP)),
+                limits: Some((-1., 1.)),
+            }],
+            ..Default::default()
+        };
+        let dir = test_dir("urdf-round-trip");
+        let path = dir.join("robot.urdf");
+        assert!(save(&assembly, &path).unwrap().is_empty());
+        let loaded = load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(loaded.warnings.is_empty(), "{:?}", loaded.warnings);
+        assert_eq!(loaded.joints.len(), 1);
+        let joint = &loaded.joints[0];
+        assert_eq!((joint.name.as_str(), joint.joint_type, joint.limits), ("Shoulder", JointType::Revolute, Some((-1., 1.))));
+        assert!(joint.pose.0.abs_diff_eq(assembly.joints[0].pose.0, 1e-6));
+        assert!(joint.axis().abs_diff_eq(UP, 1e-9));
+
+        for (original, loaded) in assembly.parts.iter().zip(&loaded.parts) {
+            assert_eq!(original.name, loaded.name);
+            let [original, loaded] = [original, loaded].map(|part| part.bodies[0].vertex(2));
+            assert!(original.abs_diff_eq(loaded, 1e-4), "{original} {loaded}");
+        }
+        let inertial = loaded.parts[1].inertial.unwrap();
+        assert!(inertial.center_of_mass.abs_diff_eq(DVec3::new(0., 10., 0.), 1e-6));
+    }
+
+    #[test]
+    fn writes_a_mesh_file_for_every_link() {
+        let assembly = Assembly {
+            parts: vec![part("arm 1", vec![0], 0, 0.), part("arm_1", vec![0], 1, 10.), part("ARM-1", vec![], 2, 20.)],
+            joints: vec![Joint { name: "Wrist".to_owned(), joint_type: JointType::Rigid, pose: Pose::IDENTITY, limits: None }],
+            ..Default::default()
+        };
+        let dir = test_dir("urdf-mesh-names");
+        save(&assembly, &dir.join("robot.urdf")).unwrap();
+        let mut files: Vec<String> = std::fs::read_dir(dir.join(MESH_DIR)).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_lowercase())
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        files.sort();
+        files.dedup();
+        assert_eq!(files.len(), 3, "{files:?}");
+    }
+
+    #[test]
+    fn loads_obj_meshes() {
+        let dir = test_dir("urdf-obj");
+        std::fs::write(dir.join("arm.obj"), "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let assembly = parse(r#"<robot name="robot"><link name="arm"><visual><geometry><mesh filename="arm.obj"/></geometry></visual></link></robot>"#, &dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let assembly = assembly.unwrap();
+        assert!(assembly.warnings.is_empty(), "{:?}", assembly.warnings);
+        assert_eq!(assembly.parts[0].bodies.len(), 1);
+    }
+}
@@ -0,0 +1,645 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use bevy::prelude::*;
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::input::mouse::MouseMotion;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy::render::view::RenderLayers;
+
+use crate::appearance::{self, Appearance, ColorBy};
+use crate::instances::{BodyInstance, MeshInstances};
+use crate::kinematics::KinematicTree;
+use crate::log::LogMessages;
+use crate::lod::{self, LodCache, LodSettings};
+use crate::mrr::{Assembly, Body, Joint, JointType, Part, Pose, DISPLAY_SCALE, METERS_PER_UNIT};
+use crate::sim::JointStates;
+
+/// The index of the part a mesh entity belongs to.
+#[derive(Component)]
+pub struct PartEntity(pub usize);
+
+/// What the spawned models were built from, so edits that don't touch them can update them in place.
+#[derive(Default)]
+struct SpawnedModels {
+    /// [`lod::body_hash`] of every body, by part
+    bodies: Vec<Vec<u64>>,
+    /// Type and limits of every joint, which shape its indicator
+    joints: Vec<(JointType, Option<(f64, f64)>)>,
+}
+
+/// Spawns meshes for the bodies and indicators for the joints of the [`Assembly`]. Meshes are only rebuilt
+/// when bodies change, other edits such as a part's appearance or hardware flag update the spawned ones.
+#[allow(clippy::too_many_arguments)]
+fn setup_models(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    assembly: Res<Assembly>,
+    settings: Res<ViewerSettings>,
+    lod_settings: Res<LodSettings>,
+    mut spawned: Local<SpawnedModels>,
+    mut parts: Query<(Entity, &PartEntity, &mut Visibility, &Handle<StandardMaterial>), Without<JointIndicator>>,
+    indicators: Query<Entity, With<JointIndicator>>,
+    mut log: ResMut<LogMessages>,
+) {
+    let bodies: Vec<Vec<u64>> = assembly.parts.iter()
+        .map(|part| part.bodies.iter().map(lod::body_hash).collect())
+        .collect();
+    if bodies == spawned.bodies && !lod_settings.is_changed() {
+        style_parts(&settings, &assembly, &mut materials, &mut parts);
+    } else {
+        for (entity, ..) in &parts {
+            commands.entity(entity).despawn_recursive();
+        }
+        spawn_parts(&mut commands, &mut meshes, &mut materials, &assembly, &settings, &lod_settings, &mut log);
+        spawned.bodies = bodies;
+    }
+
+    let joints: Vec<_> = assembly.joints.iter().map(|joint| (joint.joint_type, joint.limits)).collect();
+    if joints != spawned.joints {
+        for entity in &indicators {
+            commands.entity(entity).despawn_recursive();
+        }
+        for (index, joint) in assembly.joints.iter().enumerate() {
+            spawn_joint_indicator(&mut commands, &mut meshes, &mut materials, index, joint, settings.show_joints);
+        }
+        spawned.joints = joints;
+    }
+}
+
+fn spawn_parts(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    assembly: &Assembly,
+    settings: &ViewerSettings,
+    lod_settings: &LodSettings,
+    log: &mut LogMessages,
+) {
+    let appearances = appearance::part_appearances(assembly, settings.color_by);
+    let mut lod_cache = lod_settings.enabled.then(|| LodCache::open(&assembly.file_path));
+
+    // Copies of the same shape share their meshes, from full detail to the simplest level
+    let instances = MeshInstances::new(assembly);
+    let shape_meshes: Vec<Vec<Handle<Mesh>>> = (0..instances.shapes.len())
+        .map(|shape| {
+            let body = instances.shape_body(assembly, shape);
+            let mut lods = vec![meshes.add(body.to_mesh())];
+            if let Some(lod_cache) = lod_cache.as_mut().filter(|_| body.indicies.len() / 3 >= lod_settings.min_triangles) {
+                let hash = lod::body_hash(body);
+                for level in &lod_settings.levels {
+                    lods.push(meshes.add(lod_cache.get(body, hash, level.ratio).to_mesh()));
+                }
+            }
+            lods
+        })
+        .collect();
+
+    for (index, part) in assembly.parts.iter().enumerate() {
+        let mut material = StandardMaterial::default();
+        apply_appearance(&mut material, &appearances[index]);
+        let material = materials.add(material);
+
+        for &instance in &instances.bodies[index] {
+            let lods = &shape_meshes[instance.shape];
+            let mut entity = commands.spawn((PbrBundle {
+                mesh: lods[0].clone(),
+                material: material.clone(),
+                transform: display_transform(&instance.offset),
+                visibility: if settings.part_visible(part) { Visibility::Inherited } else { Visibility::Hidden },
+                ..default()
+            }, instance, PartEntity(index)));
+
+            if lods.len() > 1 {
+                entity.insert(BodyLods::new(instances.shape_body(assembly, instance.shape), lods.clone()));
+            }
+        }
+    }
+    if let Some(Err(err)) = lod_cache.as_mut().map(LodCache::save) {
+        log.warn("Level of Detail", &format!("Couldn't save simplified meshes: {err:#}"));
+    }
+}
+
+/// Meshes of a body from full detail to its simplest level, with the sphere around its shape.
+#[derive(Component)]
+struct BodyLods {
+    meshes: Vec<Handle<Mesh>>,
+    center: Vec3,
+    radius: f32,
+    level: usize,
+}
+
+impl BodyLods {
+    fn new(body: &Body, meshes: Vec<Handle<Mesh>>) -> Self {
+        let vertices = (0..body.vertex_count()).map(|i| body.vertex(i) * DISPLAY_SCALE);
+        let (min, max) = vertices.fold((Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)), |(min, max), vertex| (min.min(vertex), max.max(vertex)));
+        Self { meshes, center: (min + max) / 2., radius: min.distance(max) / 2., level: 0 }
+    }
+}
+
+/// Swaps each body to the level of detail for how much of the view it covers.
+fn select_lods(
+    settings: Res<LodSettings>,
+    cameras: Query<(&GlobalTransform, &Projection), With<CameraController>>,
+    mut bodies: Query<(&GlobalTransform, &mut BodyLods, &mut Handle<Mesh>)>,
+) {
+    let Ok((camera, Projection::Perspective(projection))) = cameras.get_single() else {
+        return;
+    };
+    let half_height = (projection.fov / 2.).tan();
+
+    for (transform, mut lods, mut mesh) in &mut bodies {
+        let distance = transform.transform_point(lods.center).distance(camera.translation()).max(f32::EPSILON);
+        let level = settings.level(lods.radius / (distance * half_height)).min(lods.meshes.len() - 1);
+        if level != lods.level {
+            lods.level = level;
+            *mesh = lods.meshes[level].clone();
+        }
+    }
+}
+
+/// Display options picked in the View menu.
+#[derive(Resource)]
+pub struct ViewerSettings {
+    pub show_joints: bool,
+    /// Show parts marked as hardware, such as nuts and bolts
+    pub show_hardware: bool,
+    pub color_by: ColorBy,
+}
+
+impl Default for ViewerSettings {
+    fn default() -> Self {
+        Self {
+            show_joints: true,
+            show_hardware: false,
+            color_by: ColorBy::default(),
+        }
+    }
+}
+
+impl ViewerSettings {
+    pub fn part_visible(&self, part: &Part) -> bool {
+        self.show_hardware || !part.hardware
+    }
+}
+
+/// Render layer for gizmos, which the overlay camera draws on top of the robot.
+pub const OVERLAY_LAYER: u8 = 1;
+
+/// Size of joint indicators, in viewer units.
+const JOINT_INDICATOR_SIZE: f32 = 0.4;
+
+/// Marks the indicator of the joint at an index into [`Assembly::joints`].
+#[derive(Component)]
+struct JointIndicator(usize);
+
+/// Spawns an icon for the type of the joint, an arrow along the axis it moves around, and its limits.
+/// Children are in the joint's frame, so the joint's Z axis is the axis of motion.
+fn spawn_joint_indicator(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    index: usize,
+    joint: &Joint,
+    visible: bool,
+) {
+    let size = JOINT_INDICATOR_SIZE;
+    let overlay = RenderLayers::layer(OVERLAY_LAYER);
+    let mut overlay_material = |color: Color| materials.add(StandardMaterial {
+        base_color: color,
+        unlit: true,
+        cull_mode: None,
+        ..default()
+    });
+
+    let color = match joint.joint_type {
+        JointType::Rigid => Color::GRAY,
+        JointType::Revolute => Color::ORANGE,
+        JointType::Slider => Color::CYAN,
+    };
+    let material = overlay_material(color);
+    let limit_material = overlay_material(Color::rgba(1., 1., 0., 0.6));
+
+    let mut shapes: Vec<(Mesh, Transform, Handle<StandardMaterial>)> = vec![];
+    let arrow = |length: f32, direction: f32| {
+        let rotation = Quat::from_rotation_arc(Vec3::Y, Vec3::Z * direction);
+        [
+            (shape::Cylinder { radius: size * 0.03, height: length, ..default() }.into(),
+             Transform::from_translation(Vec3::Z * direction * length / 2.).with_rotation(rotation)),
+            (cone(size * 0.08, size * 0.2, 16),
+             Transform::from_translation(Vec3::Z * direction * length).with_rotation(rotation)),
+        ]
+    };
+
+    match joint.joint_type {
+        JointType::Rigid => {
+            shapes.push((shape::Cube { size: size * 0.3 }.into(), Transform::IDENTITY, material.clone()));
+        }
+        JointType::Revolute => {
+            shapes.push((shape::Torus {
+                radius: size * 0.5,
+                ring_radius: size * 0.04,
+                subdivisions_segments: 32,
+                subdivisions_sides: 8,
+            }.into(), Transform::from_rotation(Quat::from_rotation_x(FRAC_PI_2)), material.clone()));
+            shapes.extend(arrow(size * 1.5, 1.).map(|(mesh, transform)| (mesh, transform, material.clone())));
+
+            if let Some((lower, upper)) = joint.limits {
+                let (lower, upper) = (lower as f32, upper as f32);
+                shapes.push((arc(size * 0.8, size * 0.1, lower, upper), Transform::IDENTITY, limit_material.clone()));
+                for angle in [lower, upper] {
+                    shapes.push((shape::Box::new(size * 0.8, size * 0.03, size * 0.03).into(),
+                        Transform::from_rotation(Quat::from_rotation_z(angle)) * Transform::from_xyz(size * 0.4, 0., 0.),
+                        limit_material.clone()));
+                }
+            }
+        }
+        JointType::Slider => {
+            shapes.push((shape::Box::new(size * 0.4, size * 0.4, size * 0.2).into(), Transform::IDENTITY, material.clone()));
+            for direction in [1., -1.] {
+                shapes.extend(arrow(size * 1.2, direction).map(|(mesh, transform)| (mesh, transform, material.clone())));
+            }
+
+            if let Some((lower, upper)) = joint.limits {
+                // Slider limits are in meters
+                let [lower, upper] = [lower, upper].map(|limit| (limit / METERS_PER_UNIT) as f32 * DISPLAY_SCALE);
+                shapes.push((shape::Cylinder { radius: size * 0.05, height: upper - lower, ..default() }.into(),
+                    Transform::from_xyz(0., 0., (lower + upper) / 2.).with_rotation(Quat::from_rotation_x(FRAC_PI_2)),
+                    limit_material.clone()));
+                for limit in [lower, upper] {
+                    shapes.push((shape::Box::new(size * 0.3, size * 0.3, size * 0.02).into(),
+                        Transform::from_xyz(0., 0., limit),
+                        limit_material.clone()));
+                }
+            }
+        }
+    }
+
+    commands.spawn((SpatialBundle {
+        transform: display_transform(&joint.pose),
+        visibility: if visible { Visibility::Inherited } else { Visibility::Hidden },
+        ..default()
+    }, JointIndicator(index)))
+    .with_children(|parent| {
+        for (mesh, transform, material) in shapes {
+            parent.spawn((PbrBundle {
+                mesh: meshes.add(mesh),
+                material,
+                transform,
+                ..default()
+            }, overlay));
+        }
+    });
+}
+
+/// A cone around the Y axis, with its base centered on the origin.
+fn cone(radius: f32, height: f32, segments: u32) -> Mesh {
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut indices = vec![];
+    let slope = radius / height;
+
+    for i in 0..segments {
+        let (sin, cos) = (i as f32 * TAU / segments as f32).sin_cos();
+        let (next_sin, next_cos) = ((i + 1) as f32 * TAU / segments as f32).sin_cos();
+        let offset = positions.len() as u32;
+
+        // Side
+        positions.extend([[cos * radius, 0., sin * radius], [0., height, 0.], [next_cos * radius, 0., next_sin * radius]]);
+        normals.extend([[cos, slope, sin], [(cos + next_cos) / 2., slope, (sin + next_sin) / 2.], [next_cos, slope, next_sin]]
+            .map(|normal| Vec3::from(normal).normalize().to_array()));
+        indices.extend([offset, offset + 1, offset + 2]);
+
+        // Base
+        positions.extend([[0., 0., 0.], [cos * radius, 0., sin * radius], [next_cos * radius, 0., next_sin * radius]]);
+        normals.extend([[0., -1., 0.]; 3]);
+        indices.extend([offset + 3, offset + 4, offset + 5]);
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+/// A flat band in the XY plane from angle `start` to `end` around Z, measured from the X axis.
+fn arc(radius: f32, width: f32, start: f32, end: f32) -> Mesh {
+    let segments = ((end - start).abs() / TAU * 64.).ceil().max(1.) as u32;
+    let mut positions = vec![];
+    let mut indices = vec![];
+
+    for i in 0..=segments {
+        let angle = start + (end - start) * i as f32 / segments as f32;
+        let direction = Vec2::from_angle(angle);
+        positions.push((direction * (radius - width / 2.)).extend(0.).to_array());
+        positions.push((direction * (radius + width / 2.)).extend(0.).to_array());
+
+        if i < segments {
+            let offset = i * 2;
+            indices.extend([offset, offset + 1, offset + 3, offset, offset + 3, offset + 2]);
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0., 0., 1.]; positions.len()]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+/// Converts a pose in assembly units to a transform in the viewer.
+pub fn display_transform(pose: &Pose) -> Transform {
+    Transform::from_translation(pose.0.as_vec3() * DISPLAY_SCALE).with_rotation(pose.1.as_f32())
+}
+
+/// Where the parts and joints of the robot are with the joints at their current positions.
+#[derive(Resource, Default)]
+pub struct RobotPose {
+    pub tree: KinematicTree,
+    /// How far each part has moved from where it is in the assembly
+    pub part_poses: Vec<Pose>,
+    /// Frame of each joint, moved along with the parts on its parent side
+    pub joint_frames: Vec<Pose>,
+}
+
+fn update_robot_pose(assembly: Res<Assembly>, joint_states: Res<JointStates>, mut pose: ResMut<RobotPose>) {
+    if assembly.is_changed() {
+        pose.tree = KinematicTree::new(&assembly);
+    } else if !joint_states.is_changed() {
+        return;
+    }
+
+    let link_poses = pose.tree.link_poses(&assembly, &joint_states.positions());
+    let part_poses: Vec<Pose> = pose.tree.part_links.iter().map(|&link| link_poses[link]).collect();
+
+    // Parents come before their children in the tree, so the lowest link a joint connects is its parent side
+    let joint_frames = assembly.joints.iter()
+        .enumerate()
+        .map(|(index, joint)| {
+            let link = assembly.parts.iter()
+                .enumerate()
+                .filter(|(_, part)| part.joint_references.contains(&(index as u32)))
+                .map(|(part, _)| pose.tree.part_links[part])
+                .min();
+            link.map_or(joint.pose, |link| link_poses[link].then(&joint.pose))
+        })
+        .collect();
+
+    pose.part_poses = part_poses;
+    pose.joint_frames = joint_frames;
+}
+
+fn pose_entities(
+    pose: Res<RobotPose>,
+    mut parts: Query<(&PartEntity, &BodyInstance, &mut Transform), Without<JointIndicator>>,
+    mut joints: Query<(&JointIndicator, &mut Transform), Without<PartEntity>>,
+) {
+    for (part, instance, mut transform) in &mut parts {
+        if let Some(part_pose) = pose.part_poses.get(part.0) {
+            *transform = display_transform(&part_pose.then(&instance.offset));
+        }
+    }
+    for (joint, mut transform) in &mut joints {
+        if let Some(frame) = pose.joint_frames.get(joint.0) {
+            *transform = display_transform(frame);
+        }
+    }
+}
+
+fn apply_appearance(material: &mut StandardMaterial, appearance: &Appearance) {
+    material.base_color = appearance.base_color();
+    material.metallic = appearance.metallic;
+    material.perceptual_roughness = appearance.roughness;
+    material.alpha_mode = if appearance.opacity < 1. { AlphaMode::Blend } else { AlphaMode::Opaque };
+}
+
+fn apply_view_settings(
+    settings: Res<ViewerSettings>,
+    assembly: Res<Assembly>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut indicators: Query<&mut Visibility, (With<JointIndicator>, Without<PartEntity>)>,
+    mut parts: Query<(Entity, &PartEntity, &mut Visibility, &Handle<StandardMaterial>), Without<JointIndicator>>,
+) {
+    for mut visibility in &mut indicators {
+        *visibility = if settings.show_joints { Visibility::Inherited } else { Visibility::Hidden };
+    }
+
+    style_parts(&settings, &assembly, &mut materials, &mut parts);
+}
+
+/// Shows or hides every part mesh and sets its material to the part's appearance.
+fn style_parts(
+    settings: &ViewerSettings,
+    assembly: &Assembly,
+    materials: &mut Assets<StandardMaterial>,
+    parts: &mut Query<(Entity, &PartEntity, &mut Visibility, &Handle<StandardMaterial>), Without<JointIndicator>>,
+) {
+    let appearances = appearance::part_appearances(assembly, settings.color_by);
+    for (_, part, mut visibility, material) in parts {
+        let visible = assembly.parts.get(part.0).is_none_or(|part| settings.part_visible(part));
+        *visibility = if visible { Visibility::Inherited } else { Visibility::Hidden };
+
+        if let (Some(appearance), Some(material)) = (appearances.get(part.0), materials.get_mut(material)) {
+            apply_appearance(material, appearance);
+        }
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn(PointLightBundle {
+        point_light: PointLight {
+            intensity: 9000.0,
+            range: 1000.,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(8.0, 16.0, 8.0),
+        ..default()
+    });
+
+    // ground plane
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(shape::Plane::from_size(50.0).into()),
+        material: materials.add(Color::SILVER.into()),
+        ..default()
+    });
+
+    commands.spawn((Camera3dBundle {
+        transform: Transform::from_xyz(0.0, 6., 12.0).looking_at(Vec3::new(0., 1., 0.), Vec3::Y),
+        ..default()
+    },
+    CameraController::default())).with_children(|parent| {
+        // Draws gizmos over the robot, clearing the depth of what the main camera drew
+        parent.spawn((Camera3dBundle {
+            camera: Camera { order: 1, ..default() },
+            camera_3d: Camera3d { clear_color: ClearColorConfig::None, ..default() },
+            ..default()
+        }, RenderLayers::layer(OVERLAY_LAYER)));
+        parent.spawn(PointLightBundle {
+            point_light: PointLight {
+                intensity: 9000.0,
+                range: 1000.,
+                shadows_enabled: true,
+                ..default()
+            },
+            transform: Transform::from_xyz(8.0, 16.0, 8.0),
+            ..default() 
+        });
+    });
+}
+
+
+#[derive(Component)]
+pub struct CameraController {
+    pub enabled: bool,
+    pub initialized: bool,
+    pub sensitivity: f32,
+    pub key_forward: KeyCode,
+    pub key_back: KeyCode,
+    pub key_left: KeyCode,
+    pub key_right: KeyCode,
+    pub key_up: KeyCode,
+    pub key_down: KeyCode,
+    pub key_run: KeyCode,
+    pub mouse_key_enable_mouse: MouseButton,
+    pub keyboard_key_enable_mouse: KeyCode,
+    pub walk_speed: f32,
+    pub run_speed: f32,
+    pub friction: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub velocity: Vec3,
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            initialized: false,
+            sensitivity: 0.5,
+            key_forward: KeyCode::W,
+            key_back: KeyCode::S,
+            key_left: KeyCode::A,
+            key_right: KeyCode::D,
+            key_up: KeyCode::E,
+            key_down: KeyCode::Q,
+            key_run: KeyCode::LShift,
+            mouse_key_enable_mouse: MouseButton::Left,
+            keyboard_key_enable_mouse: KeyCode::M,
+            walk_speed: 2.0,
+            run_speed: 6.0,
+            friction: 0.5,
+            pitch: 0.0,
+            yaw: 0.0,
+            velocity: Vec3::ZERO,
+        }
+    }
+}
+
+pub fn camera_controller(
+    time: Res<Time>,
+    mut mouse_events: EventReader<MouseMotion>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    key_input: Res<Input<KeyCode>>,
+    mut move_toggled: Local<bool>,
+    mut query: Query<(&mut Transform, &mut CameraController), With<Camera>>,
+) {
+    let dt = time.delta_seconds();
+
+    if let Ok((mut transform, mut options)) = query.get_single_mut() {
+        if !options.initialized {
+            let (yaw, pitch, _roll) = transform.rotation.to_euler(EulerRot::YXZ);
+            options.yaw = yaw;
+            options.pitch = pitch;
+            options.initialized = true;
+        }
+        if !options.enabled {
+            return;
+        }
+
+        // Handle key input
+        let mut axis_input = Vec3::ZERO;
+        if key_input.pressed(options.key_forward) {
+            axis_input.z += 1.0;
+        }
+        if key_input.pressed(options.key_back) {
+            axis_input.z -= 1.0;
+        }
+        if key_input.pressed(options.key_right) {
+            axis_input.x += 1.0;
+        }
+        if key_input.pressed(options.key_left) {
+            axis_input.x -= 1.0;
+        }
+        if key_input.pressed(options.key_up) {
+            axis_input.y += 1.0;
+        }
+        if key_input.pressed(options.key_down) {
+            axis_input.y -= 1.0;
+        }
+        if key_input.just_pressed(options.keyboard_key_enable_mouse) {
+            *move_toggled = !*move_toggled;
+        }
+
+        // Apply movement update
+        if axis_input != Vec3::ZERO {
+            let max_speed = if key_input.pressed(options.key_run) {
+                options.run_speed
+            } else {
+                options.walk_speed
+            };
+            options.velocity = axis_input.normalize() * max_speed;
+        } else {
+            let friction = options.friction.clamp(0.0, 1.0);
+            options.velocity *= 1.0 - friction;
+            if options.velocity.length_squared() < 1e-6 {
+                options.velocity = Vec3::ZERO;
+            }
+        }
+        let forward = transform.forward();
+        let right = transform.right();
+        transform.translation += options.velocity.x * dt * right
+            + options.velocity.y * dt * Vec3::Y
+            + options.velocity.z * dt * forward;
+
+        // Handle mouse input
+        let mut mouse_delta = Vec2::ZERO;
+        if mouse_button_input.pressed(options.mouse_key_enable_mouse) || *move_toggled {
+            for mouse_event in mouse_events.iter() {
+                mouse_delta += mouse_event.delta;
+            }
+        }
+
+        if mouse_delta != Vec2::ZERO {
+            // Apply look update
+            options.pitch = (options.pitch - mouse_delta.y * 0.5 * options.sensitivity * dt)
+                .clamp(-PI / 2., PI / 2.);
+            options.yaw -= mouse_delta.x * options.sensitivity * dt;
+            transform.rotation = Quat::from_euler(EulerRot::ZYX, 0.0, options.yaw, options.pitch);
+        }
+    }
+}
+
+pub struct ViewerPlugin;
+
+impl Plugin for ViewerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ViewerSettings>()
+        .init_resource::<RobotPose>()
+        .init_resource::<LodSettings>()
+        .add_startup_system(setup)
+        .add_system(setup_models.run_if(resource_changed::<Assembly>().or_else(resource_changed::<LodSettings>())))
+        .add_system(update_robot_pose)
+        .add_system(pose_entities.after(update_robot_pose).run_if(resource_changed::<RobotPose>()))
+        .add_system(apply_view_settings.run_if(resource_changed::<ViewerSettings>()))
+        .add_system(camera_controller)
+        .add_system(select_lods.after(camera_controller))
+        .add_system(bevy::window::close_on_esc);
+    }
+}